@@ -54,6 +54,8 @@ struct MD_HTML_tag {
     unsigned flags;
     int image_nesting_level;
     char escape_map[256];
+    const MD_HTML_HOOKS* hooks;
+    int hook_depth;
 };
 
 #define NEED_HTML_ESC_FLAG   0x1
@@ -375,12 +377,32 @@ render_open_wikilink_span(MD_HTML* r, const MD_SPAN_WIKILINK_DETAIL* det)
  ***  HTML renderer implementation  ***
  **************************************/
 
+#define IS_BLOCK_HOOKED(r, type)                                            \
+        ((r)->hooks != NULL  &&  ((r)->hooks->block_mask & (1u << (type))))
+#define IS_SPAN_HOOKED(r, type)                                             \
+        ((r)->hooks != NULL  &&  ((r)->hooks->span_mask & (1u << (type))))
+
 static int
 enter_block_callback(MD_BLOCKTYPE type, void* detail, void* userdata)
 {
     static const MD_CHAR* head[6] = { "<h1>", "<h2>", "<h3>", "<h4>", "<h5>", "<h6>" };
     MD_HTML* r = (MD_HTML*) userdata;
 
+    if(IS_BLOCK_HOOKED(r, type)) {
+        int use_default = 1;
+        int ret;
+
+        r->hook_depth++;
+        if(r->hooks->enter_block != NULL) {
+            use_default = 0;
+            ret = r->hooks->enter_block(type, detail, &use_default, r->userdata);
+            if(ret != 0)
+                return ret;
+        }
+        if(!use_default)
+            return 0;
+    }
+
     switch(type) {
         case MD_BLOCK_DOC:      /* noop */ break;
         case MD_BLOCK_QUOTE:    RENDER_VERBATIM(r, "<blockquote>\n"); break;
@@ -409,6 +431,21 @@ leave_block_callback(MD_BLOCKTYPE type, void* detail, void* userdata)
     static const MD_CHAR* head[6] = { "</h1>\n", "</h2>\n", "</h3>\n", "</h4>\n", "</h5>\n", "</h6>\n" };
     MD_HTML* r = (MD_HTML*) userdata;
 
+    if(IS_BLOCK_HOOKED(r, type)) {
+        int use_default = 1;
+        int ret;
+
+        r->hook_depth--;
+        if(r->hooks->leave_block != NULL) {
+            use_default = 0;
+            ret = r->hooks->leave_block(type, detail, &use_default, r->userdata);
+            if(ret != 0)
+                return ret;
+        }
+        if(!use_default)
+            return 0;
+    }
+
     switch(type) {
         case MD_BLOCK_DOC:      /*noop*/ break;
         case MD_BLOCK_QUOTE:    RENDER_VERBATIM(r, "</blockquote>\n"); break;
@@ -451,10 +488,35 @@ enter_span_callback(MD_SPANTYPE type, void* detail, void* userdata)
          *
          * This make-it-a-plain-text approach is the recommended practice by
          * CommonMark specification (for HTML output).
+         *
+         * Nested images are counted, so that leave_span_callback() knows
+         * which image closes the label.
          */
+        if(type == MD_SPAN_IMG)
+            r->image_nesting_level++;
         return 0;
     }
 
+    if(IS_SPAN_HOOKED(r, type)) {
+        int use_default = 1;
+        int ret;
+
+        r->hook_depth++;
+        if(r->hooks->enter_span != NULL) {
+            use_default = 0;
+            ret = r->hooks->enter_span(type, detail, &use_default, r->userdata);
+            if(ret != 0)
+                return ret;
+        }
+        if(!use_default) {
+            /* Keep suppressing the nested spans of an image label, even if
+             * the <img> tag itself is rendered by the hook. */
+            if(type == MD_SPAN_IMG)
+                r->image_nesting_level++;
+            return 0;
+        }
+    }
+
     switch(type) {
         case MD_SPAN_EM:                RENDER_VERBATIM(r, "<em>"); break;
         case MD_SPAN_STRONG:            RENDER_VERBATIM(r, "<strong>"); break;
@@ -476,11 +538,36 @@ leave_span_callback(MD_SPANTYPE type, void* detail, void* userdata)
 {
     MD_HTML* r = (MD_HTML*) userdata;
 
-    if(r->image_nesting_level > 0) {
-        /* Ditto as in enter_span_callback(), except we have to allow the
-         * end of the <img> tag. */
-        if(r->image_nesting_level == 1  &&  type == MD_SPAN_IMG)
-            render_close_img_span(r, (MD_SPAN_IMG_DETAIL*) detail);
+    /* Spans inside an image label were neither rendered nor hooked by
+     * enter_span_callback(); only the image which opened the label may
+     * close it. */
+    if(r->image_nesting_level > (type == MD_SPAN_IMG ? 1 : 0)) {
+        if(type == MD_SPAN_IMG)
+            r->image_nesting_level--;
+        return 0;
+    }
+
+    if(IS_SPAN_HOOKED(r, type)) {
+        int use_default = 1;
+        int ret;
+
+        r->hook_depth--;
+        if(r->hooks->leave_span != NULL) {
+            use_default = 0;
+            ret = r->hooks->leave_span(type, detail, &use_default, r->userdata);
+            if(ret != 0)
+                return ret;
+        }
+        if(!use_default) {
+            if(type == MD_SPAN_IMG)
+                r->image_nesting_level--;
+            return 0;
+        }
+    }
+
+    if(type == MD_SPAN_IMG) {
+        /* The end of the <img> tag, whose label is now complete. */
+        render_close_img_span(r, (MD_SPAN_IMG_DETAIL*) detail);
         return 0;
     }
 
@@ -505,6 +592,17 @@ text_callback(MD_TEXTTYPE type, const MD_CHAR* text, MD_SIZE size, void* userdat
 {
     MD_HTML* r = (MD_HTML*) userdata;
 
+    if(r->hook_depth > 0  &&  r->hooks->text != NULL) {
+        int use_default = 0;
+        int ret;
+
+        ret = r->hooks->text(type, text, size, &use_default, r->userdata);
+        if(ret != 0)
+            return ret;
+        if(!use_default)
+            return 0;
+    }
+
     switch(type) {
         case MD_TEXT_NULLCHAR:  render_utf8_codepoint(r, 0x0000, render_verbatim); break;
         case MD_TEXT_BR:        RENDER_VERBATIM(r, (r->image_nesting_level == 0
@@ -533,7 +631,17 @@ md_html(const MD_CHAR* input, MD_SIZE input_size,
         void (*process_output)(const MD_CHAR*, MD_SIZE, void*),
         void* userdata, unsigned parser_flags, unsigned renderer_flags)
 {
-    MD_HTML render = { process_output, userdata, renderer_flags, 0, { 0 } };
+    return md_html_ex(input, input_size, process_output, userdata,
+                      parser_flags, renderer_flags, NULL);
+}
+
+int
+md_html_ex(const MD_CHAR* input, MD_SIZE input_size,
+           void (*process_output)(const MD_CHAR*, MD_SIZE, void*),
+           void* userdata, unsigned parser_flags, unsigned renderer_flags,
+           const MD_HTML_HOOKS* hooks)
+{
+    MD_HTML render = { process_output, userdata, renderer_flags, 0, { 0 }, hooks, 0 };
     int i;
 
     MD_PARSER parser = {
@@ -61,6 +61,39 @@ int md_html(const MD_CHAR* input, MD_SIZE input_size,
             void* userdata, unsigned parser_flags, unsigned renderer_flags);
 
 
+/* Hooks letting the caller take over rendering of chosen elements.
+ *
+ * Blocks (spans) whose bit (1 << type) is set in block_mask (span_mask) are
+ * not rendered by md_html_ex(): the respective callback is called instead.
+ * The callback may set *use_default to non-zero to have the element rendered
+ * as usual after it returns. A NULL callback behaves as if it always did so.
+ *
+ * Text nested in such elements is passed to text() first, when it is set,
+ * with the same semantics.
+ *
+ * All callbacks get the userdata passed to md_html_ex(). Any non-zero return
+ * value aborts rendering, as with MD_PARSER callbacks.
+ */
+typedef struct MD_HTML_HOOKS {
+    unsigned block_mask;
+    unsigned span_mask;
+
+    int (*enter_block)(MD_BLOCKTYPE /*type*/, void* /*detail*/, int* /*use_default*/, void* /*userdata*/);
+    int (*leave_block)(MD_BLOCKTYPE /*type*/, void* /*detail*/, int* /*use_default*/, void* /*userdata*/);
+    int (*enter_span)(MD_SPANTYPE /*type*/, void* /*detail*/, int* /*use_default*/, void* /*userdata*/);
+    int (*leave_span)(MD_SPANTYPE /*type*/, void* /*detail*/, int* /*use_default*/, void* /*userdata*/);
+    int (*text)(MD_TEXTTYPE /*type*/, const MD_CHAR* /*text*/, MD_SIZE /*size*/, int* /*use_default*/, void* /*userdata*/);
+} MD_HTML_HOOKS;
+
+/* Same as md_html(), with rendering of some elements delegated to hooks.
+ * Param hooks may be NULL, which is equivalent to md_html().
+ */
+int md_html_ex(const MD_CHAR* input, MD_SIZE input_size,
+               void (*process_output)(const MD_CHAR*, MD_SIZE, void*),
+               void* userdata, unsigned parser_flags, unsigned renderer_flags,
+               const MD_HTML_HOOKS* hooks);
+
+
 #ifdef __cplusplus
     }  /* extern "C" { */
 #endif
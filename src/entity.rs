@@ -72,8 +72,28 @@ pub fn entity_lookup(name: &str) -> Option<String> {
     Some(res)
 }
 
+/// Decode an entity as md4c reports it, `&name;`, `&#nnn;` or
+/// `&#xhhh;`. Invalid code points decode to U+FFFD, unknown names to
+/// `None`.
+pub fn entity_decode(text: &str) -> Option<String> {
+    let body = match text.strip_prefix("&#") {
+        Some(body) => body.strip_suffix(';')?,
+        None => return entity_lookup(text),
+    };
+    let codepoint = match body.strip_prefix(['x', 'X']) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => body.parse().ok(),
+    };
+    let ch = codepoint
+        .filter(|&cp| cp != 0)
+        .and_then(char::from_u32)
+        .unwrap_or('\u{fffd}');
+    Some(ch.to_string())
+}
+
 #[cfg(test)]
 mod test {
+    use super::entity_decode;
     use super::entity_lookup;
     use super::entity_lookup_raw;
     #[test]
@@ -102,4 +122,13 @@ mod test {
         );
         assert_eq!(entity_lookup_raw(b"&noSuchEntity;"), None);
     }
+
+    #[test]
+    fn test4() {
+        assert_eq!(entity_decode("&amp;"), Some("&".to_owned()));
+        assert_eq!(entity_decode("&#35;"), Some("#".to_owned()));
+        assert_eq!(entity_decode("&#X22;"), Some("\"".to_owned()));
+        assert_eq!(entity_decode("&#0;"), Some("\u{fffd}".to_owned()));
+        assert_eq!(entity_decode("&noSuchEntity;"), None);
+    }
 }
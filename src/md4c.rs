@@ -5,6 +5,8 @@ use std::ptr::slice_from_raw_parts;
 use std::str::FromStr;
 use std::{slice, vec};
 
+use crate::entity::entity_decode;
+
 mod sys {
     pub use crate::md4c_sys::md4c::*;
 }
@@ -173,6 +175,23 @@ impl MdAttribute {
         let text = self.0.text as *const u8;
         text.is_null()
     }
+
+    /// The attribute as plain text, with its entities decoded and
+    /// NUL characters replaced by U+FFFD.
+    pub fn decode(&self) -> String {
+        let mut res = String::new();
+        for (s, t) in self.clone() {
+            match t {
+                MdTexttype::Nullchar => res.push('\u{fffd}'),
+                MdTexttype::Entity => match entity_decode(s) {
+                    Some(decoded) => res.push_str(&decoded),
+                    None => res.push_str(s),
+                },
+                _ => res.push_str(s),
+            }
+        }
+        res
+    }
 }
 
 impl Clone for MdAttribute {
@@ -298,6 +317,109 @@ fn from_empty_attribute() {
     assert_eq!(iter.next(), None);
 }
 
+/// Typed detail of a block, as passed along with `enter_block` and
+/// `leave_block`.
+#[derive(Debug, Clone)]
+pub enum MdBlockDetail {
+    Doc,
+    Quote,
+    Ul {
+        is_tight: bool,
+        mark: MdChar,
+    },
+    Ol {
+        start: u32,
+        is_tight: bool,
+        mark_delimiter: MdChar,
+    },
+    Li {
+        is_task: bool,
+        task_mark: MdChar,
+        task_mark_offset: MdOffset,
+    },
+    Hr,
+    H {
+        level: u32,
+    },
+    Code {
+        info: MdAttribute,
+        lang: MdAttribute,
+        fence_char: MdChar,
+    },
+    Html,
+    P,
+    Table {
+        col_count: u32,
+        head_row_count: u32,
+        body_row_count: u32,
+    },
+    Thead,
+    Tbody,
+    Tr,
+    Th {
+        align: MdAlign,
+    },
+    Td {
+        align: MdAlign,
+    },
+}
+
+impl MdBlockDetail {
+    pub fn blocktype(&self) -> MdBlocktype {
+        match self {
+            Self::Doc => MdBlocktype::Doc,
+            Self::Quote => MdBlocktype::Quote,
+            Self::Ul { .. } => MdBlocktype::Ul,
+            Self::Ol { .. } => MdBlocktype::Ol,
+            Self::Li { .. } => MdBlocktype::Li,
+            Self::Hr => MdBlocktype::Hr,
+            Self::H { .. } => MdBlocktype::H,
+            Self::Code { .. } => MdBlocktype::Code,
+            Self::Html => MdBlocktype::Html,
+            Self::P => MdBlocktype::P,
+            Self::Table { .. } => MdBlocktype::Table,
+            Self::Thead => MdBlocktype::Thead,
+            Self::Tbody => MdBlocktype::Tbody,
+            Self::Tr => MdBlocktype::Tr,
+            Self::Th { .. } => MdBlocktype::Th,
+            Self::Td { .. } => MdBlocktype::Td,
+        }
+    }
+}
+
+/// Typed detail of a span, as passed along with `enter_span` and
+/// `leave_span`.
+#[derive(Debug, Clone)]
+pub enum MdSpanDetail {
+    Em,
+    Strong,
+    A { href: MdAttribute, title: MdAttribute },
+    Img { src: MdAttribute, title: MdAttribute },
+    Code,
+    Del,
+    Latexmath,
+    LatexmathDisplay,
+    Wikilink { target: MdAttribute },
+    U,
+}
+
+impl MdSpanDetail {
+    pub fn spantype(&self) -> MdSpantype {
+        match self {
+            Self::Em => MdSpantype::Em,
+            Self::Strong => MdSpantype::Strong,
+            Self::A { .. } => MdSpantype::A,
+            Self::Img { .. } => MdSpantype::Img,
+            Self::Code => MdSpantype::Code,
+            Self::Del => MdSpantype::Del,
+            Self::Latexmath => MdSpantype::Latexmath,
+            Self::LatexmathDisplay => MdSpantype::LatexmathDisplay,
+            Self::Wikilink { .. } => MdSpantype::Wikilink,
+            Self::U => MdSpantype::U,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub struct MdParserFlags(pub u32);
 
@@ -442,7 +564,7 @@ impl From<u32> for MdParserFlags {
     }
 }
 
-unsafe extern "C" fn cb_enter_block<T: MdParser>(
+unsafe extern "C" fn cb_enter_block<T: MdRawParser>(
     blocktype: sys::MD_BLOCKTYPE,
     detail: *mut c_void,
     userdata: *mut c_void,
@@ -455,7 +577,7 @@ unsafe extern "C" fn cb_enter_block<T: MdParser>(
     }
 }
 
-unsafe extern "C" fn cb_leave_block<T: MdParser>(
+unsafe extern "C" fn cb_leave_block<T: MdRawParser>(
     blocktype: sys::MD_BLOCKTYPE,
     detail: *mut c_void,
     userdata: *mut c_void,
@@ -468,7 +590,7 @@ unsafe extern "C" fn cb_leave_block<T: MdParser>(
     }
 }
 
-unsafe extern "C" fn cb_enter_span<T: MdParser>(
+unsafe extern "C" fn cb_enter_span<T: MdRawParser>(
     spantype: sys::MD_SPANTYPE,
     detail: *mut c_void,
     userdata: *mut c_void,
@@ -481,7 +603,7 @@ unsafe extern "C" fn cb_enter_span<T: MdParser>(
     }
 }
 
-unsafe extern "C" fn cb_leave_span<T: MdParser>(
+unsafe extern "C" fn cb_leave_span<T: MdRawParser>(
     spantype: sys::MD_SPANTYPE,
     detail: *mut c_void,
     userdata: *mut c_void,
@@ -494,7 +616,7 @@ unsafe extern "C" fn cb_leave_span<T: MdParser>(
     }
 }
 
-unsafe extern "C" fn cb_text<T: MdParser>(
+unsafe extern "C" fn cb_text<T: MdRawParser>(
     texttype: sys::MD_TEXTTYPE,
     text: *const MdChar,
     size: MdSize,
@@ -511,7 +633,7 @@ unsafe extern "C" fn cb_text<T: MdParser>(
     }
 }
 
-unsafe extern "C" fn cb_debug_log<T: MdParser>(
+unsafe extern "C" fn cb_debug_log<T: MdRawParser>(
     msg: *const c_char,
     userdata: *mut c_void,
 ) {
@@ -522,7 +644,7 @@ unsafe extern "C" fn cb_debug_log<T: MdParser>(
     userdata.debug_log(msg)
 }
 
-fn parse_work<T: MdParser>(
+fn parse_work<T: MdRawParser>(
     parser: &mut T,
     src: &str,
     flags: &MdParserFlags,
@@ -559,6 +681,90 @@ fn parse_work<T: MdParser>(
     }
 }
 
+/// The callbacks of md4c, undispatched. An `MdParser` receives them
+/// through one method per element type.
+trait MdRawParser: Sized {
+    fn enter_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut c_void,
+    ) -> MdResult;
+
+    fn leave_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut c_void,
+    ) -> MdResult;
+
+    fn enter_span(
+        &mut self,
+        spantype: MdSpantype,
+        detail: *mut c_void,
+    ) -> MdResult;
+
+    fn leave_span(
+        &mut self,
+        spantype: MdSpantype,
+        detail: *mut c_void,
+    ) -> MdResult;
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult;
+
+    fn debug_log(&mut self, _msg: &str) {}
+}
+
+/// Hands the callbacks of md4c to the callback traits of an
+/// `MdParser`.
+struct Dispatch<'a, T>(&'a mut T);
+
+impl<'a, T: MdParser> MdRawParser for Dispatch<'a, T> {
+    #[inline]
+    fn enter_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut c_void,
+    ) -> MdResult {
+        self.0.enter_block(blocktype, detail)
+    }
+
+    #[inline]
+    fn leave_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut c_void,
+    ) -> MdResult {
+        self.0.leave_block(blocktype, detail)
+    }
+
+    #[inline]
+    fn enter_span(
+        &mut self,
+        spantype: MdSpantype,
+        detail: *mut c_void,
+    ) -> MdResult {
+        self.0.enter_span(spantype, detail)
+    }
+
+    #[inline]
+    fn leave_span(
+        &mut self,
+        spantype: MdSpantype,
+        detail: *mut c_void,
+    ) -> MdResult {
+        self.0.leave_span(spantype, detail)
+    }
+
+    #[inline]
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        self.0.text(texttype, text)
+    }
+
+    #[inline]
+    fn debug_log(&mut self, msg: &str) {
+        self.0.debug_log(msg)
+    }
+}
+
 pub trait MdParser:
     MdEnterBlockCallback
     + MdEnterSpanCallback
@@ -576,7 +782,7 @@ pub trait MdParser:
         src: &str,
         flags: &MdParserFlags,
     ) -> MdResult {
-        parse_work(self, src, flags, None)
+        parse_work(&mut Dispatch(self), src, flags, None)
     }
 
     fn parse_with_logger(
@@ -584,7 +790,8 @@ pub trait MdParser:
         src: &str,
         flags: &MdParserFlags,
     ) -> MdResult {
-        parse_work(self, src, flags, Some(cb_debug_log::<Self>))
+        let debug_log = cb_debug_log::<Dispatch<Self>>;
+        parse_work(&mut Dispatch(self), src, flags, Some(debug_log))
     }
 }
 
@@ -595,7 +802,9 @@ pub mod prelude {
     pub use super::MdResult;
     pub use super::MdSize;
 
+    pub use super::MdBlockDetail;
     pub use super::MdBlocktype;
+    pub use super::MdSpanDetail;
     pub use super::MdSpantype;
     pub use super::MdTexttype;
 
@@ -5,6 +5,127 @@ fn intoref<T>(detail: *mut c_void) -> &'static T {
     unsafe { detail.as_ref().unwrap_unchecked() }
 }
 
+impl MdBlockDetail {
+    pub(crate) fn from_raw(
+        blocktype: MdBlocktype,
+        detail: *mut c_void,
+    ) -> Self {
+        match blocktype {
+            MdBlocktype::Doc => Self::Doc,
+            MdBlocktype::Quote => Self::Quote,
+            MdBlocktype::Ul => {
+                let detail: &sys::MD_BLOCK_UL_DETAIL =
+                    intoref(detail);
+                Self::Ul {
+                    is_tight: detail.is_tight != 0,
+                    mark: detail.mark as MdChar,
+                }
+            }
+            MdBlocktype::Ol => {
+                let detail: &sys::MD_BLOCK_OL_DETAIL =
+                    intoref(detail);
+                Self::Ol {
+                    start: detail.start,
+                    is_tight: detail.is_tight != 0,
+                    mark_delimiter: detail.mark_delimiter as MdChar,
+                }
+            }
+            MdBlocktype::Li => {
+                let detail: &sys::MD_BLOCK_LI_DETAIL =
+                    intoref(detail);
+                Self::Li {
+                    is_task: detail.is_task != 0,
+                    task_mark: detail.task_mark as MdChar,
+                    task_mark_offset: detail.task_mark_offset,
+                }
+            }
+            MdBlocktype::Hr => Self::Hr,
+            MdBlocktype::H => {
+                let detail: &sys::MD_BLOCK_H_DETAIL = intoref(detail);
+                Self::H {
+                    level: detail.level,
+                }
+            }
+            MdBlocktype::Code => {
+                let detail: &sys::MD_BLOCK_CODE_DETAIL =
+                    intoref(detail);
+                Self::Code {
+                    info: detail.info.into(),
+                    lang: detail.lang.into(),
+                    fence_char: detail.fence_char as MdChar,
+                }
+            }
+            MdBlocktype::Html => Self::Html,
+            MdBlocktype::P => Self::P,
+            MdBlocktype::Table => {
+                let detail: &sys::MD_BLOCK_TABLE_DETAIL =
+                    intoref(detail);
+                Self::Table {
+                    col_count: detail.col_count,
+                    head_row_count: detail.head_row_count,
+                    body_row_count: detail.body_row_count,
+                }
+            }
+            MdBlocktype::Thead => Self::Thead,
+            MdBlocktype::Tbody => Self::Tbody,
+            MdBlocktype::Tr => Self::Tr,
+            MdBlocktype::Th => {
+                let detail: &sys::MD_BLOCK_TD_DETAIL =
+                    intoref(detail);
+                Self::Th {
+                    align: detail.align.into(),
+                }
+            }
+            MdBlocktype::Td => {
+                let detail: &sys::MD_BLOCK_TD_DETAIL =
+                    intoref(detail);
+                Self::Td {
+                    align: detail.align.into(),
+                }
+            }
+        }
+    }
+}
+
+impl MdSpanDetail {
+    pub(crate) fn from_raw(
+        spantype: MdSpantype,
+        detail: *mut c_void,
+    ) -> Self {
+        match spantype {
+            MdSpantype::Em => Self::Em,
+            MdSpantype::Strong => Self::Strong,
+            MdSpantype::A => {
+                let detail: &sys::MD_SPAN_A_DETAIL = intoref(detail);
+                Self::A {
+                    href: detail.href.into(),
+                    title: detail.title.into(),
+                }
+            }
+            MdSpantype::Img => {
+                let detail: &sys::MD_SPAN_IMG_DETAIL =
+                    intoref(detail);
+                Self::Img {
+                    src: detail.src.into(),
+                    title: detail.title.into(),
+                }
+            }
+            MdSpantype::Code => Self::Code,
+            MdSpantype::Del => Self::Del,
+            MdSpantype::Latexmath => Self::Latexmath,
+            MdSpantype::LatexmathDisplay => Self::LatexmathDisplay,
+            MdSpantype::Wikilink => {
+                let detail: &sys::MD_SPAN_WIKILINK_DETAIL =
+                    intoref(detail);
+                Self::Wikilink {
+                    target: detail.target.into(),
+                }
+            }
+            MdSpantype::U => Self::U,
+        }
+    }
+}
+
 pub trait MdEnterBlockCallback {
     #[allow(unused)]
     fn enter_block(
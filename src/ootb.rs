@@ -25,3 +25,25 @@ pub fn github_md(src: &str) -> String {
 pub fn commonmark(src: &str) -> String {
     ootb_md(src, &MdParserFlags::commonmark())
 }
+
+/// Flip the task list item whose mark is at byte `offset` of `src`,
+/// as rendered in `data-task-offset` by
+/// [`Md2HtmlFlags::interactive_tasks`]: `[ ]` becomes `[x]` and
+/// `[x]` or `[X]` becomes `[ ]`. Any other offset leaves `src` as is.
+pub fn toggle_task(src: &str, offset: usize) -> String {
+    let data = src.as_bytes();
+    let is_task = offset > 0
+        && offset + 1 < data.len()
+        && data[offset - 1] == b'['
+        && data[offset + 1] == b']';
+    let mark = match data.get(offset) {
+        Some(b' ') if is_task => "x",
+        Some(b'x' | b'X') if is_task => " ",
+        _ => return src.to_owned(),
+    };
+    let mut toggled = String::with_capacity(src.len());
+    toggled.push_str(&src[..offset]);
+    toggled.push_str(mark);
+    toggled.push_str(&src[offset + 1..]);
+    toggled
+}
@@ -1,9 +1,36 @@
 pub use crate::md4c::{MdParserFlags, MdResult};
 use std::marker::PhantomData;
+use std::num::NonZeroI32;
 
+pub mod ansi;
+pub mod asciidoc;
+pub mod bbcode;
+pub mod chat;
+pub(crate) mod detail;
+pub mod docx;
+pub mod gemtext;
+pub mod hooks;
+pub mod ipynb;
+pub mod jira;
+pub(crate) mod json;
+pub mod latex;
+pub(crate) mod layout;
+pub mod markdown;
 pub mod md2htmlflags;
+pub mod mdast;
 pub mod md_html_c;
 pub mod md_html_rs;
+pub mod mediawiki;
+pub mod org;
+pub mod outline;
+pub mod pandoc;
+pub mod plain;
+pub mod roff;
+pub mod rtf;
+pub(crate) mod sourcepos;
+pub mod typst;
+pub mod xml;
+pub(crate) mod zip;
 
 pub trait SpecifiedRenderer {
     type Userdata;
@@ -47,10 +74,118 @@ where
     }
 }
 
+/// Userdata of the renderers whose output is text, which they append
+/// piece by piece; `String` simply collects it.
+pub trait Md2TextUserdata: Sized {
+    fn render_append(&mut self, text: &str);
+}
+
+impl Md2TextUserdata for String {
+    #[inline]
+    fn render_append(&mut self, text: &str) {
+        self.push_str(text);
+    }
+}
+
+/// Object-safe rendering interface, for choosing the output format at
+/// runtime and keeping the renderer as `Box<dyn MarkdownRenderer>`.
+///
+/// It is implemented by every renderer whose userdata collects the
+/// output as text, i.e. is `Default + Into<String>`; `String` itself
+/// is a valid userdata for all of them.
+pub trait MarkdownRenderer {
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult;
+
+    /// Take the output rendered so far, leaving it empty.
+    fn take_output(&mut self) -> String;
+
+    fn render_to_string(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> Result<String, NonZeroI32> {
+        let res = self.render(input, parser_flags);
+        let output = self.take_output();
+        res.map(|()| output)
+    }
+}
+
+impl<I> MarkdownRenderer for I
+where
+    I: SpecifiedRenderer,
+    I::Userdata: Default + Into<String>,
+{
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        SpecifiedRenderer::render(self, input, parser_flags)
+    }
+
+    #[inline]
+    fn take_output(&mut self) -> String {
+        std::mem::take(self.userdata()).into()
+    }
+}
+
+impl<Ud, I> MarkdownRenderer for Renderer<Ud, I>
+where
+    Ud: Default + Into<String>,
+    I: SpecifiedRenderer<Userdata = Ud>,
+{
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.0.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn take_output(&mut self) -> String {
+        std::mem::take(self.0.userdata()).into()
+    }
+}
+
 pub mod prelude {
+    pub use super::ansi::Md2AnsiOptions;
+    pub use super::asciidoc::Md2AsciidocOptions;
+    pub use super::bbcode::Md2BbcodeOptions;
+    pub use super::chat::{Md2ChatDialect, Md2ChatOptions};
+    pub use super::docx::Md2DocxOptions;
+    pub use super::docx::Md2DocxUserdata as Md2Docx;
+    pub use super::gemtext::Md2GemtextOptions;
+    pub use super::hooks::MdHooks;
+    pub use super::ipynb::Md2IpynbOptions;
+    pub use super::jira::Md2JiraOptions;
+    pub use super::latex::Md2LatexOptions;
+    pub use super::markdown::Md2MarkdownOptions;
     pub use super::md2htmlflags::Md2HtmlFlags;
     pub use super::md_html_c::Md2HtmlUserdata as Md2HtmlC;
     pub use super::md_html_rs::Md2HtmlUserdata as Md2HtmlRs;
+    pub use super::md_html_rs::{MdSourceMap, MdSourceMapEntry};
+    pub use super::mdast::Md2MdastOptions;
+    pub use super::mediawiki::Md2MediawikiOptions;
+    pub use super::org::Md2OrgOptions;
+    pub use super::outline::{Md2OutlineFormat, Md2OutlineOptions};
+    pub use super::pandoc::Md2PandocOptions;
+    pub use super::plain::Md2PlainOptions;
+    pub use super::roff::Md2RoffOptions;
+    pub use super::rtf::Md2RtfOptions;
+    pub use super::typst::Md2TypstOptions;
+    pub use super::xml::Md2XmlOptions;
+    pub use super::MarkdownRenderer;
+    pub use super::Md2TextUserdata as Md2Text;
     pub use super::Renderer as MdRenderer;
-    pub use crate::md4c::{MdParserFlags, MdResult};
+    pub use crate::md4c::{
+        MdBlockDetail, MdBlocktype, MdParserFlags, MdResult,
+        MdSpanDetail, MdSpantype,
+    };
 }
@@ -0,0 +1,488 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{self, str_width, Layout, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug)]
+pub struct Md2AnsiOptions {
+    width: usize,
+    hyperlinks: bool,
+}
+
+impl Md2AnsiOptions {
+    /// Options wrapping to 80 columns.
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            width: 80,
+            hyperlinks: true,
+        }
+    }
+
+    /// Wrap paragraphs to the width of the terminal, as given by the
+    /// `COLUMNS` environment variable, if it is set.
+    #[inline]
+    pub fn terminal_width(mut self) -> Self {
+        if let Some(width) = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|cols| cols.trim().parse().ok())
+        {
+            self.width = width;
+        }
+        self
+    }
+
+    /// Wrap paragraphs to `width` columns; 0 keeps every paragraph on
+    /// one line.
+    #[inline]
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Write link targets in parentheses after the link text, for
+    /// terminals without OSC 8 hyperlinks.
+    #[inline]
+    pub fn no_hyperlinks(mut self) -> Self {
+        self.hyperlinks = false;
+        self
+    }
+}
+
+impl Default for Md2AnsiOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// SGR colours of the heading levels.
+const HEADING_COLORS: [&str; 6] =
+    ["35", "34", "36", "32", "33", "33"];
+
+struct List {
+    next: Option<u32>,
+}
+
+/// Renders Markdown for a terminal with ANSI escape sequences: styled
+/// spans, coloured headings, boxed code blocks, box-drawing tables and
+/// OSC 8 hyperlinks.
+pub struct Md2AnsiRenderer<T> {
+    pub options: Md2AnsiOptions,
+    pub userdata: T,
+    layout: Layout,
+    lists: Vec<List>,
+    table: Option<TextTable>,
+    code: Option<String>,
+    links: Vec<(String, usize)>,
+}
+
+impl<T> Md2AnsiRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2AnsiOptions) -> Self {
+        Self {
+            layout: Layout::new(options.width),
+            options,
+            userdata,
+            lists: Vec::new(),
+            table: None,
+            code: None,
+            links: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn sink(&mut self) -> &mut String {
+        layout::sink(
+            self.code.as_mut(),
+            self.table.as_mut(),
+            self.layout.inline(),
+        )
+    }
+
+    fn sgr(&mut self, code: &str) {
+        let sgr = format!("\x1b[{}m", code);
+        self.sink().push_str(&sgr);
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Layout::new(self.options.width);
+            }
+            MdBlockDetail::Quote => {
+                self.layout.separate();
+                let bar = "\x1b[90m│\x1b[39m ";
+                self.layout.push_prefix(bar, bar);
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List { next: None });
+            }
+            MdBlockDetail::Ol {
+                start, is_tight, ..
+            } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List { next: Some(start) });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.layout.separate();
+                let mut marker = match self.lists.last_mut() {
+                    Some(List {
+                        next: Some(next), ..
+                    }) => {
+                        *next += 1;
+                        format!("{}. ", *next - 1)
+                    }
+                    _ => "• ".to_owned(),
+                };
+                let rest = " ".repeat(str_width(&marker));
+                if is_task {
+                    marker.push_str(match task_mark as u8 {
+                        b' ' => "☐ ",
+                        _ => "☑ ",
+                    });
+                }
+                self.layout.push_prefix(&marker, &rest);
+            }
+            MdBlockDetail::Hr => {
+                self.layout.separate();
+                let width = match self.layout.text_width() {
+                    0 => 40,
+                    width => width,
+                };
+                let rule =
+                    format!("\x1b[90m{}\x1b[39m", "─".repeat(width));
+                self.layout.line(&rule);
+            }
+            MdBlockDetail::H { level } => {
+                self.layout.separate();
+                let color =
+                    HEADING_COLORS[(level as usize - 1).min(5)];
+                match level {
+                    1 => self.sgr(&format!("1;4;{}", color)),
+                    _ => self.sgr(&format!("1;{}", color)),
+                }
+            }
+            MdBlockDetail::Html | MdBlockDetail::P => {
+                self.layout.separate()
+            }
+            MdBlockDetail::Code { .. } => {
+                self.layout.separate();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.layout.separate();
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Quote | MdBlockDetail::Li { .. } => {
+                self.layout.pop_prefix();
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.lists.pop();
+            }
+            MdBlockDetail::H { .. } => {
+                self.sgr("0");
+                self.layout.flush();
+            }
+            MdBlockDetail::Code { info, .. } => {
+                let code = self.code.take().unwrap_or_default();
+                let info = info.as_str().unwrap_or("");
+                self.code_box(&code, &replace_controls(info));
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    self.table_box(&table);
+                }
+            }
+            _ => self.layout.flush(),
+        }
+    }
+
+    /// Output a code block in a box, with the info string on its top
+    /// edge.
+    fn code_box(&mut self, code: &str, info: &str) {
+        let lines: Vec<String> =
+            code.lines().map(expand_tabs).collect();
+        let width = lines
+            .iter()
+            .map(|line| str_width(line))
+            .chain([str_width(info) + 2])
+            .max()
+            .unwrap_or(0);
+        let top = match info {
+            "" => "─".repeat(width + 2),
+            info => format!(
+                "─ {} {}",
+                info,
+                "─".repeat(width - str_width(info) - 1)
+            ),
+        };
+        self.layout.line(&format!("\x1b[90m┌{}┐\x1b[39m", top));
+        for line in lines.iter() {
+            let fill = " ".repeat(width - str_width(line));
+            self.layout.line(&format!(
+                "\x1b[90m│\x1b[39m {}{} \x1b[90m│\x1b[39m",
+                line, fill
+            ));
+        }
+        let bottom = "─".repeat(width + 2);
+        self.layout.line(&format!("\x1b[90m└{}┘\x1b[39m", bottom));
+    }
+
+    fn table_box(&mut self, table: &TextTable) {
+        let widths = table.widths(1);
+        let rule = |left: &str, mid: &str, right: &str| {
+            let cols: Vec<String> =
+                widths.iter().map(|&w| "─".repeat(w + 2)).collect();
+            format!("{}{}{}", left, cols.join(mid), right)
+        };
+        self.layout.line(&rule("┌", "┬", "┐"));
+        for (idx, row) in table.padded_rows(1).iter().enumerate() {
+            let cells: Vec<String> = row
+                .iter()
+                .map(|cell| match idx < table.head_rows() {
+                    true => format!(" \x1b[1m{}\x1b[22m ", cell),
+                    false => format!(" {} ", cell),
+                })
+                .collect();
+            self.layout.line(&format!("│{}│", cells.join("│")));
+            if idx + 1 == table.head_rows() {
+                self.layout.line(&rule("├", "┼", "┤"));
+            }
+        }
+        self.layout.line(&rule("└", "┴", "┘"));
+    }
+
+    fn enter_link(&mut self, url: String) {
+        let url = encode_url(&url);
+        if self.options.hyperlinks {
+            let osc = format!("\x1b]8;;{}\x1b\\", url);
+            self.sink().push_str(&osc);
+        }
+        self.sgr("4");
+        let start = self.sink().len();
+        self.links.push((url, start));
+    }
+
+    /// Close a link; without hyperlinks, its target follows the text
+    /// unless the text is the target itself, as with autolinks.
+    fn leave_link(&mut self) {
+        self.sgr("24");
+        let (url, start) = match self.links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        if self.options.hyperlinks {
+            self.sink().push_str("\x1b]8;;\x1b\\");
+            return;
+        }
+        let text = self.sink().get(start..).unwrap_or("").to_owned();
+        let text = text.strip_suffix("\x1b[24m").unwrap_or(&text);
+        if !url.is_empty()
+            && text != url
+            && url.strip_prefix("mailto:") != Some(text)
+        {
+            self.sink().push_str(&format!(" ({})", url));
+        }
+    }
+}
+
+/// Replace the control characters of `text` but newlines and tabs,
+/// which would reach the terminal as escape sequences of their own.
+fn replace_controls(text: &str) -> String {
+    text.chars()
+        .map(|ch| match ch {
+            '\n' | '\t' => ch,
+            _ if ch.is_control() => '\u{fffd}',
+            _ => ch,
+        })
+        .collect()
+}
+
+/// Percent-encode the spaces and control characters of `url`, which
+/// would end or escape the OSC 8 sequence.
+fn encode_url(url: &str) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for ch in url.chars() {
+        match ch == ' ' || ch.is_control() {
+            true => {
+                let mut buf = [0; 4];
+                for byte in ch.encode_utf8(&mut buf).bytes() {
+                    encoded.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            false => encoded.push(ch),
+        }
+    }
+    encoded
+}
+
+/// Replace tabs by spaces up to the next multiple of 4 columns.
+fn expand_tabs(line: &str) -> String {
+    let mut res = String::with_capacity(line.len());
+    let mut col = 0;
+    for ch in line.chars() {
+        if ch == '\t' {
+            let fill = 4 - col % 4;
+            res.push_str(&" ".repeat(fill));
+            col += fill;
+        } else {
+            res.push(ch);
+            col += str_width(ch.encode_utf8(&mut [0; 4]));
+        }
+    }
+    res
+}
+
+impl<T> MdDetailParser for Md2AnsiRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        match detail {
+            MdSpanDetail::Em => self.sgr("3"),
+            MdSpanDetail::Strong => self.sgr("1"),
+            MdSpanDetail::U => self.sgr("4"),
+            MdSpanDetail::Del => self.sgr("9"),
+            MdSpanDetail::Code
+            | MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => self.sgr("36"),
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode());
+                self.sink().push_str("[image: ");
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(target.decode())
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        match spantype {
+            MdSpantype::Em => self.sgr("23"),
+            MdSpantype::Strong => self.sgr("22"),
+            MdSpantype::U => self.sgr("24"),
+            MdSpantype::Del => self.sgr("29"),
+            MdSpantype::Code
+            | MdSpantype::Latexmath
+            | MdSpantype::LatexmathDisplay => self.sgr("39"),
+            MdSpantype::Img => {
+                self.sink().push(']');
+                self.leave_link();
+            }
+            MdSpantype::A | MdSpantype::Wikilink => self.leave_link(),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        let in_cell = self.code.is_none() && self.table.is_some();
+        let decoded;
+        let text = match texttype {
+            MdTexttype::Html => return Ok(()),
+            MdTexttype::Nullchar => "\u{fffd}",
+            MdTexttype::Br if in_cell => " ",
+            MdTexttype::Br => "\n",
+            MdTexttype::Softbr if self.code.is_none() => " ",
+            MdTexttype::Entity => {
+                decoded = entity_decode(text);
+                decoded.as_deref().unwrap_or(text)
+            }
+            _ => text,
+        };
+        self.sink().push_str(&replace_controls(text));
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2AnsiRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2AnsiRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2AnsiRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn ansi(userdata: T, options: Md2AnsiOptions) -> Self {
+        Self(Md2AnsiRenderer::new(userdata, options), PhantomData)
+    }
+}
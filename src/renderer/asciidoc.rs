@@ -0,0 +1,648 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::TextTable;
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2AsciidocOptions {
+    heading_offset: usize,
+}
+
+impl Md2AsciidocOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add `offset` to the level of the headings. By default a level 1
+    /// heading is the document title, `=`; with an offset of 1 it is a
+    /// section, `==`.
+    #[inline]
+    pub fn heading_offset(mut self, offset: usize) -> Self {
+        self.heading_offset = offset;
+        self
+    }
+}
+
+/// Prefixes of the paragraphs starting with an admonition label.
+const ADMONITIONS: [&str; 5] =
+    ["NOTE:", "TIP:", "IMPORTANT:", "WARNING:", "CAUTION:"];
+
+/// Characters that start a block at the beginning of a line.
+const BLOCK_STARTS: [char; 10] =
+    ['.', '-', '=', '|', '/', '[', ':', '<', '\'', '>'];
+
+/// A container of blocks: the document, a quote or a list item.
+struct Container {
+    is_item: bool,
+    /// Whether a block, or the text of a tight item, was output.
+    started: bool,
+    last_is_list: bool,
+}
+
+/// How a block is separated from the previous one.
+#[derive(PartialEq)]
+enum BlockKind {
+    Paragraph,
+    List,
+    Other,
+}
+
+/// Whether `line` of a paragraph would be read as the start of another
+/// block, e.g. a list item or a block title.
+fn needs_guard(line: &str) -> bool {
+    let after_digits =
+        line.trim_start_matches(|ch: char| ch.is_ascii_digit());
+    line.starts_with(BLOCK_STARTS)
+        || (after_digits.len() < line.len()
+            && after_digits.starts_with(". "))
+        || ADMONITIONS.iter().any(|label| line.starts_with(label))
+}
+
+/// Prefix the lines of `text` that need it with `{empty}`.
+fn guard_lines(text: &str) -> String {
+    let lines: Vec<String> = text
+        .split('\n')
+        .map(|line| match needs_guard(line) {
+            true => format!("{{empty}}{}", line),
+            false => line.to_owned(),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+/// Target of a `link:` or `image:` macro, as a passthrough if it would
+/// end the macro early.
+fn macro_target(url: &str) -> String {
+    match url.contains(|ch: char| ch.is_whitespace() || ch == '[') {
+        true => format!("++{}++", url),
+        false => url.to_owned(),
+    }
+}
+
+/// Renders Markdown as AsciiDoc, in the syntax of Asciidoctor.
+pub struct Md2AsciidocRenderer<T> {
+    pub options: Md2AsciidocOptions,
+    pub userdata: T,
+    containers: Vec<Container>,
+    /// Whether each open list is ordered, the innermost last.
+    lists: Vec<bool>,
+    quote_depth: usize,
+    /// Text of the current paragraph, heading or table cell.
+    inline: String,
+    heading_level: usize,
+    /// Content of the current code or HTML block, and the language of
+    /// the code.
+    literal: Option<String>,
+    lang: String,
+    /// The table being output, whose column specification is only
+    /// known once the cells of the first row are.
+    table: Option<TextTable>,
+    link_depth: usize,
+    /// Alternative text of the current image.
+    alt: Option<String>,
+    image_nesting_level: usize,
+    /// End of the image that started its paragraph, in `inline`.
+    leading_image_end: Option<usize>,
+    in_code_span: bool,
+    in_math: bool,
+}
+
+impl<T> Md2AsciidocRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2AsciidocOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            containers: Vec::new(),
+            lists: Vec::new(),
+            quote_depth: 0,
+            inline: String::new(),
+            heading_level: 0,
+            literal: None,
+            lang: String::new(),
+            table: None,
+            link_depth: 0,
+            alt: None,
+            image_nesting_level: 0,
+            leading_image_end: None,
+            in_code_span: false,
+            in_math: false,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.containers.clear();
+        self.lists.clear();
+        self.inline.clear();
+        self.parse(input, parser_flags)
+    }
+
+    fn write(&mut self, text: &str) {
+        self.userdata.render_append(text);
+    }
+
+    /// Output the text of a tight list item before its next block.
+    fn flush_item_text(&mut self) {
+        if self.inline.is_empty() {
+            return;
+        }
+        let text = guard_lines(&std::mem::take(&mut self.inline));
+        self.write(&text);
+        self.write("\n");
+    }
+
+    /// Separate a block from the previous one in its container: by a
+    /// blank line, or within a list item by a list continuation.
+    fn start_block(&mut self, kind: BlockKind) {
+        self.flush_item_text();
+        let container = match self.containers.last_mut() {
+            Some(container) => container,
+            None => return,
+        };
+        let is_list = kind == BlockKind::List;
+        let separator = match (container.is_item, container.started) {
+            // The first paragraph of an item follows its marker.
+            (true, false) if kind == BlockKind::Paragraph => "",
+            (true, false) if is_list => "{empty}\n",
+            (true, false) => "{empty}\n+\n",
+            (true, true) if is_list => "",
+            // A blank line attaches the block to the item rather than
+            // to the last item of its nested list.
+            (true, true) if container.last_is_list => "\n+\n",
+            (true, true) => "+\n",
+            (false, true) if is_list && container.last_is_list => {
+                // Keep adjacent lists apart.
+                "\n//\n\n"
+            }
+            (false, true) => "\n",
+            (false, false) => "",
+        };
+        container.started = true;
+        container.last_is_list = is_list;
+        self.write(separator);
+    }
+
+    /// Note the text of a tight list item.
+    fn start_inline(&mut self) {
+        if let Some(container) = self.containers.last_mut() {
+            if container.is_item {
+                container.started = true;
+            }
+        }
+    }
+
+    fn write_escaped(&mut self, text: &str) {
+        let chars: Vec<char> = text.chars().collect();
+        let is_boundary = |ch: Option<&char>| {
+            !ch.is_some_and(|ch| ch.is_alphanumeric())
+        };
+        for (idx, ch) in chars.iter().enumerate() {
+            let prev = idx.checked_sub(1).and_then(|i| chars.get(i));
+            let next = chars.get(idx + 1);
+            // Constrained marks only apply at word boundaries.
+            let boundary = is_boundary(prev) || is_boundary(next);
+            let escaped = match ch {
+                '*' if boundary => "{asterisk}",
+                '`' if boundary => "{backtick}",
+                '+' if boundary => "{plus}",
+                '_' if boundary => "&#95;",
+                '#' if boundary => "&#35;",
+                '^' => "{caret}",
+                '~' => "{tilde}",
+                '\\' => "{backslash}",
+                '<' if next == Some(&'<') => "{lt}",
+                ']' if self.link_depth > 0 => "\\]",
+                _ => {
+                    self.inline.push(*ch);
+                    continue;
+                }
+            };
+            self.inline.push_str(escaped);
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.containers.push(Container {
+                is_item: false,
+                started: false,
+                last_is_list: false,
+            }),
+            MdBlockDetail::Quote => {
+                self.start_block(BlockKind::Other);
+                self.quote_depth += 1;
+                let delimiter = "_".repeat(3 + self.quote_depth);
+                self.write(&format!("{}\n", delimiter));
+                self.containers.push(Container {
+                    is_item: false,
+                    started: false,
+                    last_is_list: false,
+                });
+            }
+            MdBlockDetail::Ul { .. } => {
+                self.start_block(BlockKind::List);
+                self.lists.push(false);
+            }
+            MdBlockDetail::Ol { start, .. } => {
+                self.start_block(BlockKind::List);
+                self.lists.push(true);
+                if start != 1 {
+                    self.write(&format!("[start={}]\n", start));
+                }
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                let mark = match self.lists.last() {
+                    Some(true) => ".",
+                    _ => "*",
+                };
+                let mut marker = mark.repeat(self.lists.len());
+                marker.push(' ');
+                match (is_task, task_mark as u8) {
+                    (false, _) => (),
+                    (true, b' ') => marker.push_str("[ ] "),
+                    (true, _) => marker.push_str("[x] "),
+                }
+                self.write(&marker);
+                self.containers.push(Container {
+                    is_item: true,
+                    started: false,
+                    last_is_list: false,
+                });
+            }
+            MdBlockDetail::Hr => {
+                self.start_block(BlockKind::Other);
+                self.write("'''\n");
+            }
+            MdBlockDetail::H { level } => {
+                self.start_block(BlockKind::Other);
+                self.heading_level = level as usize;
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                self.start_block(BlockKind::Other);
+                self.lang = lang.decode();
+                self.literal = Some(String::new());
+            }
+            MdBlockDetail::Html => {
+                self.start_block(BlockKind::Other);
+                self.literal = Some(String::new());
+            }
+            MdBlockDetail::P => {
+                self.start_block(BlockKind::Paragraph);
+                self.leading_image_end = None;
+            }
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.start_block(BlockKind::Other);
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.containers.pop();
+            }
+            MdBlockDetail::Quote => {
+                self.containers.pop();
+                let delimiter = "_".repeat(3 + self.quote_depth);
+                self.quote_depth -= 1;
+                self.write(&format!("{}\n", delimiter));
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.lists.pop();
+            }
+            MdBlockDetail::Li { .. } => {
+                self.flush_item_text();
+                if let Some(Container { started: false, .. }) =
+                    self.containers.pop()
+                {
+                    self.write("{empty}\n");
+                }
+            }
+            MdBlockDetail::Hr
+            | MdBlockDetail::Thead
+            | MdBlockDetail::Tbody
+            | MdBlockDetail::Tr => (),
+            MdBlockDetail::H { .. } => {
+                let level =
+                    self.heading_level + self.options.heading_offset;
+                let text = std::mem::take(&mut self.inline);
+                self.write(&format!(
+                    "{} {}\n",
+                    "=".repeat(level),
+                    text
+                ));
+            }
+            MdBlockDetail::Code { .. } => {
+                let code = self.literal.take().unwrap_or_default();
+                // The delimiter must be longer than any line of dashes
+                // in the code.
+                let dashes = code
+                    .lines()
+                    .filter(|line| {
+                        !line.is_empty()
+                            && line.bytes().all(|b| b == b'-')
+                    })
+                    .map(str::len)
+                    .max()
+                    .map_or(4, |len| (len + 1).max(4));
+                let delimiter = "-".repeat(dashes);
+                if !self.lang.is_empty() {
+                    let lang = std::mem::take(&mut self.lang);
+                    self.write(&format!("[source,{}]\n", lang));
+                }
+                self.write(&format!(
+                    "{}\n{}{}\n",
+                    delimiter, code, delimiter
+                ));
+            }
+            MdBlockDetail::Html => {
+                let html = self.literal.take().unwrap_or_default();
+                self.write(&format!("++++\n{}++++\n", html));
+            }
+            MdBlockDetail::P => {
+                let mut text =
+                    guard_lines(&std::mem::take(&mut self.inline));
+                // An image alone in its paragraph is a block image.
+                if self.leading_image_end.take() == Some(text.len()) {
+                    text.insert(text.find(':').unwrap_or(0), ':');
+                }
+                self.write(&text);
+                self.write("\n");
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    self.write_table(table);
+                }
+            }
+            MdBlockDetail::Th { .. } | MdBlockDetail::Td { .. } => {
+                let text = std::mem::take(&mut self.inline);
+                if let Some(cell) =
+                    self.table.as_mut().and_then(TextTable::cell)
+                {
+                    *cell = text.replace('|', "\\|");
+                }
+            }
+        }
+    }
+
+    fn write_table(&mut self, table: TextTable) {
+        let cols: Vec<&str> = table
+            .aligns()
+            .iter()
+            .map(|align| match align {
+                MdAlign::Default => "1",
+                MdAlign::Left => "<",
+                MdAlign::Center => "^",
+                MdAlign::Right => ">",
+            })
+            .collect();
+        let mut out = format!("[cols=\"{}\"", cols.join(","));
+        if table.head_rows() > 0 {
+            out.push_str(",options=\"header\"");
+        }
+        out.push_str("]\n|===\n");
+        for (idx, row) in table.rows().iter().enumerate() {
+            for cell in row {
+                match cell.is_empty() {
+                    true => out.push_str("| "),
+                    false => out.push_str(&format!("| {} ", cell)),
+                }
+            }
+            out.pop();
+            out.push('\n');
+            if idx + 1 == table.head_rows() {
+                out.push('\n');
+            }
+        }
+        out.push_str("|===\n");
+        self.write(&out);
+    }
+}
+
+impl<T> MdDetailParser for Md2AsciidocRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if self.image_nesting_level > 0 {
+            if spantype == MdSpantype::Img {
+                self.image_nesting_level += 1;
+            }
+            return Ok(());
+        }
+        self.start_inline();
+        match detail {
+            MdSpanDetail::Em => self.inline.push_str("__"),
+            MdSpanDetail::Strong => self.inline.push_str("**"),
+            MdSpanDetail::U => self.inline.push_str("[.underline]##"),
+            MdSpanDetail::Del => {
+                self.inline.push_str("[.line-through]##")
+            }
+            MdSpanDetail::Code => {
+                self.in_code_span = true;
+                self.inline.push_str("``+");
+            }
+            MdSpanDetail::A { href, .. } => {
+                self.link_depth += 1;
+                let target = macro_target(&href.decode());
+                self.inline.push_str(&format!("link:{}[", target));
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.image_nesting_level = 1;
+                if self.inline.is_empty() {
+                    self.leading_image_end = Some(0);
+                }
+                let target = macro_target(&src.decode());
+                self.inline.push_str(&format!("image:{}[", target));
+                self.alt = Some(String::new());
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.link_depth += 1;
+                let target = macro_target(&target.decode());
+                self.inline.push_str(&format!("xref:{}[", target));
+            }
+            MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => {
+                self.in_math = true;
+                self.inline.push_str("stem:[");
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        // Spans in the alternative text of an image are left out.
+        let is_img = spantype == MdSpantype::Img;
+        if self.image_nesting_level > 1 && is_img {
+            self.image_nesting_level -= 1;
+            return Ok(());
+        }
+        if self.image_nesting_level > 0 && !is_img {
+            return Ok(());
+        }
+        match spantype {
+            MdSpantype::Em => self.inline.push_str("__"),
+            MdSpantype::Strong => self.inline.push_str("**"),
+            MdSpantype::U | MdSpantype::Del => {
+                self.inline.push_str("##")
+            }
+            MdSpantype::Code => {
+                self.in_code_span = false;
+                self.inline.push_str("+``");
+            }
+            MdSpantype::A | MdSpantype::Wikilink => {
+                self.link_depth -= 1;
+                self.inline.push(']');
+            }
+            MdSpantype::Img => {
+                self.image_nesting_level = 0;
+                let alt = self.alt.take().unwrap_or_default();
+                let alt = alt.replace(']', "\\]");
+                // A comma would start the next attribute.
+                match alt.contains(',') {
+                    true => {
+                        self.inline.push_str(&format!("\"{}\"]", alt))
+                    }
+                    false => {
+                        self.inline.push_str(&format!("{}]", alt))
+                    }
+                }
+                if let Some(end) = self.leading_image_end.as_mut() {
+                    *end = self.inline.len();
+                }
+            }
+            MdSpantype::Latexmath | MdSpantype::LatexmathDisplay => {
+                self.in_math = false;
+                self.inline.push(']');
+            }
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(literal) = self.literal.as_mut() {
+            literal.push_str(text);
+            return Ok(());
+        }
+        if let Some(alt) = self.alt.as_mut() {
+            match texttype {
+                MdTexttype::Softbr | MdTexttype::Br => alt.push(' '),
+                MdTexttype::Entity => match entity_decode(text) {
+                    Some(decoded) => alt.push_str(&decoded),
+                    None => alt.push_str(text),
+                },
+                _ => alt.push_str(text),
+            }
+            return Ok(());
+        }
+        self.start_inline();
+        if self.in_code_span {
+            match texttype {
+                MdTexttype::Softbr => self.inline.push(' '),
+                _ => self.inline.push_str(text),
+            }
+            return Ok(());
+        }
+        if self.in_math {
+            self.inline.push_str(&text.replace(']', "\\]"));
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => {
+                self.inline.push_str(&format!("+++{}+++", text))
+            }
+            MdTexttype::Nullchar => self.inline.push('\u{fffd}'),
+            MdTexttype::Br if self.table.is_some() => {
+                self.inline.push(' ')
+            }
+            MdTexttype::Br => self.inline.push_str(" +\n"),
+            MdTexttype::Softbr => self.inline.push('\n'),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2AsciidocRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2AsciidocRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2AsciidocRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn asciidoc(
+        userdata: T,
+        options: Md2AsciidocOptions,
+    ) -> Self {
+        Self(Md2AsciidocRenderer::new(userdata, options), PhantomData)
+    }
+}
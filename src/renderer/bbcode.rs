@@ -0,0 +1,433 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{self, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2BbcodeOptions {
+    no_sizes: bool,
+}
+
+impl Md2BbcodeOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Render headings in bold only, for boards without `[size]`.
+    #[inline]
+    pub fn no_sizes(mut self) -> Self {
+        self.no_sizes = true;
+        self
+    }
+}
+
+/// Font sizes of the headings, in percent as phpBB has them.
+const HEADING_SIZES: [u32; 3] = [200, 150, 120];
+
+/// What breaks a literal `[` from a following tag name: an empty tag.
+const BRACKET_ESCAPE: &str = "[[i][/i]";
+
+struct List {
+    is_tight: bool,
+}
+
+/// Renders Markdown as BBCode for phpBB-style forums. Tables, which
+/// BBCode lacks, are aligned columns in a `[code]` block.
+pub struct Md2BbcodeRenderer<T> {
+    pub options: Md2BbcodeOptions,
+    pub userdata: T,
+    /// Output of the open containers, the innermost last: quotes and
+    /// list items are only wrapped once their content is known.
+    buffers: Vec<String>,
+    lists: Vec<List>,
+    table: Option<TextTable>,
+    code: Option<String>,
+    heading_size: Option<u32>,
+    image_nesting_level: usize,
+}
+
+impl<T> Md2BbcodeRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2BbcodeOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            buffers: Vec::new(),
+            lists: Vec::new(),
+            table: None,
+            code: None,
+            heading_size: None,
+            image_nesting_level: 0,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.buffers.clear();
+        self.lists.clear();
+        self.parse(input, parser_flags)
+    }
+
+    /// Where text goes, the innermost container standing for the
+    /// paragraph.
+    fn sink(&mut self) -> &mut String {
+        if self.buffers.is_empty() {
+            self.buffers.push(String::new());
+        }
+        let container = self.buffers.last_mut().unwrap();
+        layout::sink(None, self.table.as_mut(), container)
+    }
+
+    fn write(&mut self, text: &str) {
+        self.sink().push_str(text);
+    }
+
+    fn write_escaped(&mut self, text: &str) {
+        let mut escaped = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            match (ch, chars.peek()) {
+                ('[', None) => escaped.push_str(BRACKET_ESCAPE),
+                ('[', Some(&next))
+                    if next.is_ascii_alphabetic()
+                        || next == '/'
+                        || next == '*' =>
+                {
+                    escaped.push_str(BRACKET_ESCAPE)
+                }
+                (ch, _) => escaped.push(ch),
+            }
+        }
+        self.write(&escaped);
+    }
+
+    /// Start a block on a line of its own.
+    fn start_block(&mut self) {
+        let sink = self.sink();
+        if !sink.is_empty() && !sink.ends_with('\n') {
+            sink.push('\n');
+        }
+    }
+
+    /// End a block, with a blank line after it unless it is in a
+    /// tight list.
+    fn end_block(&mut self) {
+        match self.lists.last() {
+            Some(list) if list.is_tight => self.write("\n"),
+            _ => self.write("\n\n"),
+        }
+    }
+
+    fn pop_buffer(&mut self) -> String {
+        let mut content = self.buffers.pop().unwrap_or_default();
+        content.truncate(content.trim_end().len());
+        content
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.buffers.push(String::new()),
+            MdBlockDetail::Quote => {
+                self.start_block();
+                self.buffers.push(String::new());
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                self.start_block();
+                self.write("[list]\n");
+                self.lists.push(List { is_tight });
+            }
+            MdBlockDetail::Ol { is_tight, .. } => {
+                self.start_block();
+                self.write("[list=1]\n");
+                self.lists.push(List { is_tight });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                let mut item = String::from("[*]");
+                match (is_task, task_mark as u8) {
+                    (false, _) => (),
+                    (true, b' ') => item.push_str("☐ "),
+                    (true, _) => item.push_str("☑ "),
+                }
+                self.buffers.push(item);
+            }
+            MdBlockDetail::Hr => {
+                self.start_block();
+                self.write(&"─".repeat(10));
+                self.end_block();
+            }
+            MdBlockDetail::H { level } => {
+                self.start_block();
+                let size = HEADING_SIZES.get(level as usize - 1);
+                self.heading_size = match self.options.no_sizes {
+                    true => None,
+                    false => size.copied(),
+                };
+                if let Some(size) = self.heading_size {
+                    self.write(&format!("[size={}]", size));
+                }
+                self.write("[b]");
+            }
+            MdBlockDetail::Code { .. } => {
+                self.start_block();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Html => (),
+            MdBlockDetail::P => self.start_block(),
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.start_block();
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                let mut out = self.pop_buffer();
+                if !out.is_empty() {
+                    out.push('\n');
+                }
+                self.userdata.render_append(&out);
+            }
+            MdBlockDetail::Quote => {
+                let content = self.pop_buffer();
+                self.write(&format!("[quote]{}[/quote]", content));
+                self.end_block();
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.write("[/list]");
+                self.lists.pop();
+                self.end_block();
+            }
+            MdBlockDetail::Li { .. } => {
+                let item = self.pop_buffer();
+                self.write(&item);
+                self.write("\n");
+            }
+            MdBlockDetail::Hr
+            | MdBlockDetail::Html
+            | MdBlockDetail::Thead
+            | MdBlockDetail::Tbody
+            | MdBlockDetail::Tr
+            | MdBlockDetail::Th { .. }
+            | MdBlockDetail::Td { .. } => (),
+            MdBlockDetail::H { .. } => {
+                self.write("[/b]");
+                if self.heading_size.take().is_some() {
+                    self.write("[/size]");
+                }
+                self.end_block();
+            }
+            MdBlockDetail::Code { .. } => {
+                let code = self.code.take().unwrap_or_default();
+                let code = code.trim_end_matches('\n');
+                self.write(&code_block(code));
+                self.end_block();
+            }
+            MdBlockDetail::P => self.end_block(),
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    let lines = table.lines("  ", Some('-'));
+                    self.write(&code_block(&lines.join("\n")));
+                    self.end_block();
+                }
+            }
+        }
+    }
+}
+
+/// `text` in a `[code]` block, whose tags are not parsed but which a
+/// `[/code]` in the text would end early: a zero-width space after its
+/// slash breaks it.
+fn code_block(text: &str) -> String {
+    let mut block = String::from("[code]");
+    let mut rest = text;
+    while let Some(idx) = rest.find("[/") {
+        let (before, after) = rest.split_at(idx + 2);
+        block.push_str(before);
+        if after
+            .get(..4)
+            .is_some_and(|tag| tag.eq_ignore_ascii_case("code"))
+        {
+            block.push('\u{200b}');
+        }
+        rest = after;
+    }
+    block.push_str(rest);
+    block.push_str("[/code]");
+    block
+}
+
+/// A URL as the argument or content of a tag, where brackets would
+/// end it early.
+fn escape_url(url: &str) -> String {
+    url.replace('[', "%5B").replace(']', "%5D")
+}
+
+impl<T> MdDetailParser for Md2BbcodeRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if self.image_nesting_level > 0 {
+            if spantype == MdSpantype::Img {
+                self.image_nesting_level += 1;
+            }
+            return Ok(());
+        }
+        // The cells of tables are plain text.
+        if self.table.is_some() {
+            return Ok(());
+        }
+        match detail {
+            MdSpanDetail::Em => self.write("[i]"),
+            MdSpanDetail::Strong => self.write("[b]"),
+            MdSpanDetail::U => self.write("[u]"),
+            MdSpanDetail::Del => self.write("[s]"),
+            MdSpanDetail::Code
+            | MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => {
+                self.write("[font=monospace]")
+            }
+            MdSpanDetail::A { href, .. } => {
+                let url = escape_url(&href.decode());
+                self.write(&format!("[url={}]", url));
+            }
+            MdSpanDetail::Wikilink { target } => {
+                let url = escape_url(&target.decode());
+                self.write(&format!("[url={}]", url));
+            }
+            MdSpanDetail::Img { src, .. } => {
+                // BBCode images have no alternative text.
+                self.image_nesting_level = 1;
+                let url = escape_url(&src.decode());
+                self.write(&format!("[img]{}[/img]", url));
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if self.image_nesting_level > 0 {
+            if spantype == MdSpantype::Img {
+                self.image_nesting_level -= 1;
+            }
+            return Ok(());
+        }
+        if self.table.is_some() {
+            return Ok(());
+        }
+        match spantype {
+            MdSpantype::Em => self.write("[/i]"),
+            MdSpantype::Strong => self.write("[/b]"),
+            MdSpantype::U => self.write("[/u]"),
+            MdSpantype::Del => self.write("[/s]"),
+            MdSpantype::Code
+            | MdSpantype::Latexmath
+            | MdSpantype::LatexmathDisplay => self.write("[/font]"),
+            MdSpantype::A | MdSpantype::Wikilink => {
+                self.write("[/url]")
+            }
+            MdSpantype::Img => (),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if self.image_nesting_level > 0 {
+            return Ok(());
+        }
+        if let Some(code) = self.code.as_mut() {
+            code.push_str(text);
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => self.write("\u{fffd}"),
+            MdTexttype::Br if self.table.is_some() => self.write(" "),
+            MdTexttype::Br => self.write("\n"),
+            MdTexttype::Softbr => self.write(" "),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2BbcodeRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2BbcodeRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2BbcodeRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn bbcode(userdata: T, options: Md2BbcodeOptions) -> Self {
+        Self(Md2BbcodeRenderer::new(userdata, options), PhantomData)
+    }
+}
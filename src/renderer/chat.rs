@@ -0,0 +1,506 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{Layout, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+/// Markup dialect of a chat platform.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Md2ChatDialect {
+    /// Slack mrkdwn: `*bold*`, `_italic_`, `~strike~`, `<url|text>`.
+    Slack,
+    /// Telegram MarkdownV2, where every reserved character of the text
+    /// is escaped.
+    Telegram,
+    /// Discord Markdown, with `#` headings up to level 3.
+    Discord,
+}
+
+#[derive(Clone, Debug)]
+pub struct Md2ChatOptions {
+    dialect: Md2ChatDialect,
+}
+
+impl Md2ChatOptions {
+    #[inline]
+    pub fn new(dialect: Md2ChatDialect) -> Self {
+        Self { dialect }
+    }
+}
+
+/// Characters Telegram reserves in text outside of code.
+const TELEGRAM_RESERVED: &str = "_*[]()~`>#+-=|{}.!\\";
+
+struct List {
+    next: Option<u32>,
+}
+
+/// Renders Markdown as a chat message. What the platform cannot show
+/// degrades: headings to bold text, images to links and tables to
+/// aligned columns in a code block.
+pub struct Md2ChatRenderer<T> {
+    pub options: Md2ChatOptions,
+    pub userdata: T,
+    layout: Layout,
+    lists: Vec<List>,
+    table: Option<TextTable>,
+    code: Option<(String, String)>,
+    /// URLs of the open links and images, with the start of their text.
+    open_links: Vec<(String, usize)>,
+    heading_mark: &'static str,
+    in_code_span: bool,
+}
+
+impl<T> Md2ChatRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2ChatOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            layout: Layout::new(0),
+            lists: Vec::new(),
+            table: None,
+            code: None,
+            open_links: Vec::new(),
+            heading_mark: "",
+            in_code_span: false,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn escape(&self, text: &str, line_start: bool) -> String {
+        let mut out = String::with_capacity(text.len());
+        for (idx, ch) in text.chars().enumerate() {
+            match (self.options.dialect, ch) {
+                (Md2ChatDialect::Slack, '&') => out.push_str("&amp;"),
+                (Md2ChatDialect::Slack, '<') => out.push_str("&lt;"),
+                (Md2ChatDialect::Slack, '>') => out.push_str("&gt;"),
+                (Md2ChatDialect::Telegram, ch)
+                    if TELEGRAM_RESERVED.contains(ch) =>
+                {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                (Md2ChatDialect::Discord, '\\' | '*' | '_' | '~')
+                | (Md2ChatDialect::Discord, '`' | '|') => {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                // Headings, quotes and list items start lines.
+                (Md2ChatDialect::Discord, '#' | '>' | '-')
+                    if line_start && idx == 0 =>
+                {
+                    out.push('\\');
+                    out.push(ch);
+                }
+                (_, ch) => out.push(ch),
+            }
+        }
+        out
+    }
+
+    /// Text of code spans and blocks, where only the delimiters of
+    /// code need escaping.
+    fn escape_code(&self, text: &str) -> String {
+        match self.options.dialect {
+            Md2ChatDialect::Slack => self.escape(text, false),
+            Md2ChatDialect::Telegram => {
+                text.replace('\\', "\\\\").replace('`', "\\`")
+            }
+            Md2ChatDialect::Discord => text.to_owned(),
+        }
+    }
+
+    fn write_text(&mut self, text: &str) {
+        let escaped = match self.in_code_span {
+            true => self.escape_code(text),
+            false => {
+                let inline = self.layout.inline();
+                let line_start =
+                    inline.is_empty() || inline.ends_with('\n');
+                self.escape(text, line_start)
+            }
+        };
+        self.layout.inline().push_str(&escaped);
+    }
+
+    /// Delimiters of a span, empty if the platform has no such style.
+    fn span_mark(&self, spantype: MdSpantype) -> &'static str {
+        use Md2ChatDialect::*;
+        match (self.options.dialect, spantype) {
+            (Discord, MdSpantype::Strong) => "**",
+            (_, MdSpantype::Strong) => "*",
+            (Discord, MdSpantype::Em) => "*",
+            (_, MdSpantype::Em) => "_",
+            (Discord, MdSpantype::Del) => "~~",
+            (_, MdSpantype::Del) => "~",
+            (Slack, MdSpantype::U) => "",
+            (_, MdSpantype::U) => "__",
+            (
+                _,
+                MdSpantype::Code
+                | MdSpantype::Latexmath
+                | MdSpantype::LatexmathDisplay,
+            ) => "`",
+            _ => "",
+        }
+    }
+
+    /// The bold delimiters of the headings Discord cannot show as
+    /// such, and of all headings elsewhere.
+    fn heading_mark(&self, level: u32) -> &'static str {
+        match (self.options.dialect, level) {
+            (Md2ChatDialect::Discord, 1..=3) => "",
+            (Md2ChatDialect::Discord, _) => "**",
+            _ => "*",
+        }
+    }
+
+    fn write_code_block(&mut self, lang: &str, code: &str) {
+        let fence = match self.options.dialect {
+            // Slack ignores the language, and shows it as code.
+            Md2ChatDialect::Slack => "```".to_owned(),
+            _ => format!("```{}", lang),
+        };
+        self.layout.line(&fence);
+        for line in self.escape_code(code).lines() {
+            self.layout.line(line);
+        }
+        self.layout.line("```");
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Layout::new(0);
+            }
+            MdBlockDetail::Quote => {
+                self.layout.separate();
+                match self.options.dialect {
+                    Md2ChatDialect::Telegram => {
+                        self.layout.push_prefix(">", ">")
+                    }
+                    _ => self.layout.push_prefix("> ", "> "),
+                }
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List { next: None });
+            }
+            MdBlockDetail::Ol {
+                start, is_tight, ..
+            } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List { next: Some(start) });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.layout.separate();
+                let dialect = self.options.dialect;
+                let mut marker = match self.lists.last_mut() {
+                    Some(List {
+                        next: Some(next), ..
+                    }) => {
+                        *next += 1;
+                        match dialect {
+                            Md2ChatDialect::Telegram => {
+                                format!("{}\\. ", *next - 1)
+                            }
+                            _ => format!("{}. ", *next - 1),
+                        }
+                    }
+                    _ if dialect == Md2ChatDialect::Discord => {
+                        "- ".to_owned()
+                    }
+                    _ => "• ".to_owned(),
+                };
+                if is_task {
+                    match task_mark as u8 {
+                        b' ' => marker.push_str("☐ "),
+                        _ => marker.push_str("☑ "),
+                    }
+                }
+                let rest = " ".repeat(marker.chars().count());
+                self.layout.push_prefix(&marker, &rest);
+            }
+            MdBlockDetail::Hr => {
+                self.layout.separate();
+                self.layout.line(&"─".repeat(10));
+            }
+            MdBlockDetail::H { level } => {
+                self.layout.separate();
+                self.heading_mark = self.heading_mark(level);
+                let inline = self.layout.inline();
+                if self.heading_mark.is_empty() {
+                    inline.push_str(&"#".repeat(level as usize));
+                    inline.push(' ');
+                } else {
+                    inline.push_str(self.heading_mark);
+                }
+            }
+            MdBlockDetail::Html | MdBlockDetail::P => {
+                self.layout.separate()
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                self.layout.separate();
+                self.code = Some((lang.decode(), String::new()));
+            }
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.layout.separate();
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.layout.flush(),
+            MdBlockDetail::Quote | MdBlockDetail::Li { .. } => {
+                self.layout.pop_prefix();
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.lists.pop();
+            }
+            MdBlockDetail::H { .. } => {
+                let mark = std::mem::take(&mut self.heading_mark);
+                self.layout.inline().push_str(mark);
+                self.layout.flush();
+            }
+            MdBlockDetail::Code { .. } => {
+                let (lang, code) =
+                    self.code.take().unwrap_or_default();
+                self.write_code_block(&lang, &code);
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    let lines = table.lines("  ", Some('-'));
+                    let mut text = lines.join("\n");
+                    text.push('\n');
+                    self.write_code_block("", &text);
+                }
+            }
+            _ => self.layout.flush(),
+        }
+    }
+
+    fn enter_link(&mut self, url: String) {
+        let start = self.layout.inline().len();
+        self.open_links.push((url, start));
+    }
+
+    /// Close a link or image, in the syntax of the platform. A link
+    /// whose text is its URL, as autolinks, is only the URL.
+    fn leave_link(&mut self) {
+        let (url, start) = match self.open_links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        let dialect = self.options.dialect;
+        let escaped_url = self.escape(&url, false);
+        let inline = self.layout.inline();
+        let text = inline.split_off(start.min(inline.len()));
+        let is_autolink = text.is_empty() || text == escaped_url;
+        let link = match dialect {
+            Md2ChatDialect::Slack => {
+                let url = percent_encode(&url, &[' ', '<', '>', '|']);
+                match is_autolink {
+                    true => format!("<{}>", url),
+                    false => format!("<{}|{}>", url, text),
+                }
+            }
+            Md2ChatDialect::Discord if is_autolink => url,
+            Md2ChatDialect::Telegram | Md2ChatDialect::Discord => {
+                let url = match dialect {
+                    Md2ChatDialect::Telegram => {
+                        url.replace('\\', "\\\\").replace(')', "\\)")
+                    }
+                    _ => percent_encode(
+                        &url,
+                        &[' ', '(', ')', '<', '>'],
+                    ),
+                };
+                let text = match is_autolink {
+                    true => escaped_url,
+                    false => text,
+                };
+                format!("[{}]({})", text, url)
+            }
+        };
+        self.layout.inline().push_str(&link);
+    }
+}
+
+/// Percent-encode the `chars` of `url` that would end the link
+/// syntax of a platform.
+fn percent_encode(url: &str, chars: &[char]) -> String {
+    let mut encoded = String::with_capacity(url.len());
+    for ch in url.chars() {
+        match chars.contains(&ch) {
+            true => encoded.push_str(&format!("%{:02X}", ch as u32)),
+            false => encoded.push(ch),
+        }
+    }
+    encoded
+}
+
+impl<T> MdDetailParser for Md2ChatRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        // Markup is not shown in the text of tables.
+        if self.table.is_some() {
+            return Ok(());
+        }
+        match detail {
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode())
+            }
+            _ => {
+                let mark = self.span_mark(spantype);
+                self.layout.inline().push_str(mark);
+            }
+        }
+        if let MdSpantype::Code
+        | MdSpantype::Latexmath
+        | MdSpantype::LatexmathDisplay = spantype
+        {
+            self.in_code_span = true;
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if self.table.is_some() {
+            return Ok(());
+        }
+        self.in_code_span = false;
+        match spantype {
+            MdSpantype::A | MdSpantype::Img => self.leave_link(),
+            _ => {
+                let mark = self.span_mark(spantype);
+                self.layout.inline().push_str(mark);
+            }
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some((_, code)) = self.code.as_mut() {
+            code.push_str(text);
+            return Ok(());
+        }
+        // Table cells keep their text raw, for the column widths: the
+        // table is escaped once, as a code block.
+        if let Some(cell) = self.table.as_mut().and_then(|t| t.cell())
+        {
+            match texttype {
+                MdTexttype::Html => (),
+                MdTexttype::Br | MdTexttype::Softbr => cell.push(' '),
+                MdTexttype::Entity => match entity_decode(text) {
+                    Some(decoded) => cell.push_str(&decoded),
+                    None => cell.push_str(text),
+                },
+                _ => cell.push_str(text),
+            }
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => self.write_text("\u{fffd}"),
+            MdTexttype::Br => self.layout.inline().push('\n'),
+            MdTexttype::Softbr => self.layout.inline().push(' '),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_text(&decoded),
+                None => self.write_text(text),
+            },
+            _ => self.write_text(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2ChatRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2ChatRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2ChatRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn chat(userdata: T, options: Md2ChatOptions) -> Self {
+        Self(Md2ChatRenderer::new(userdata, options), PhantomData)
+    }
+}
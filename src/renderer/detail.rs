@@ -0,0 +1,410 @@
+use crate::md4c::*;
+
+/// The callbacks of a renderer that matches on the details of the
+/// elements rather than taking one method per element type. The
+/// callback traits of [`MdParser`] are implemented on top of it by
+/// [`impl_md_parser`], which puts every element back into its
+/// `MdBlockDetail` or `MdSpanDetail`.
+pub(crate) trait MdDetailParser {
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult;
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult;
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult;
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult;
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult;
+}
+
+/// Implement `MdParser` and its callback traits for a type that
+/// implements [`MdDetailParser`], e.g.
+/// `impl_md_parser!(impl[T: Md2TextUserdata] Md2PlainRenderer<T>);`.
+macro_rules! impl_md_parser {
+    (impl[$($generics:tt)*] $renderer:ty) => {
+        impl<$($generics)*> MdEnterBlockCallback for $renderer {
+            fn enter_doc(&mut self) -> MdResult {
+                MdDetailParser::enter_block(self, MdBlockDetail::Doc)
+            }
+
+            fn enter_quote(&mut self) -> MdResult {
+                let detail = MdBlockDetail::Quote;
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_ul(
+                &mut self,
+                is_tight: bool,
+                mark: MdChar,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Ul { is_tight, mark };
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_ol(
+                &mut self,
+                start: u32,
+                is_tight: bool,
+                mark_delimiter: MdChar,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Ol {
+                    start,
+                    is_tight,
+                    mark_delimiter,
+                };
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_li(
+                &mut self,
+                is_task: bool,
+                task_mark: MdChar,
+                task_mark_offset: MdOffset,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Li {
+                    is_task,
+                    task_mark,
+                    task_mark_offset,
+                };
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_hr(&mut self) -> MdResult {
+                MdDetailParser::enter_block(self, MdBlockDetail::Hr)
+            }
+
+            fn enter_h(&mut self, level: u32) -> MdResult {
+                let detail = MdBlockDetail::H { level };
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_code(
+                &mut self,
+                info: MdAttribute,
+                lang: MdAttribute,
+                fence_char: MdChar,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Code {
+                    info,
+                    lang,
+                    fence_char,
+                };
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_html(&mut self) -> MdResult {
+                MdDetailParser::enter_block(self, MdBlockDetail::Html)
+            }
+
+            fn enter_p(&mut self) -> MdResult {
+                MdDetailParser::enter_block(self, MdBlockDetail::P)
+            }
+
+            fn enter_table(
+                &mut self,
+                col_count: u32,
+                head_row_count: u32,
+                body_row_count: u32,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Table {
+                    col_count,
+                    head_row_count,
+                    body_row_count,
+                };
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_thead(&mut self) -> MdResult {
+                let detail = MdBlockDetail::Thead;
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_tbody(&mut self) -> MdResult {
+                let detail = MdBlockDetail::Tbody;
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_tr(&mut self) -> MdResult {
+                MdDetailParser::enter_block(self, MdBlockDetail::Tr)
+            }
+
+            fn enter_th(&mut self, align: MdAlign) -> MdResult {
+                let detail = MdBlockDetail::Th { align };
+                MdDetailParser::enter_block(self, detail)
+            }
+
+            fn enter_td(&mut self, align: MdAlign) -> MdResult {
+                let detail = MdBlockDetail::Td { align };
+                MdDetailParser::enter_block(self, detail)
+            }
+        }
+
+        impl<$($generics)*> MdLeaveBlockCallback for $renderer {
+            fn leave_doc(&mut self) -> MdResult {
+                MdDetailParser::leave_block(self, MdBlockDetail::Doc)
+            }
+
+            fn leave_quote(&mut self) -> MdResult {
+                let detail = MdBlockDetail::Quote;
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_ul(
+                &mut self,
+                is_tight: bool,
+                mark: MdChar,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Ul { is_tight, mark };
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_ol(
+                &mut self,
+                start: u32,
+                is_tight: bool,
+                mark_delimiter: MdChar,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Ol {
+                    start,
+                    is_tight,
+                    mark_delimiter,
+                };
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_li(
+                &mut self,
+                is_task: bool,
+                task_mark: MdChar,
+                task_mark_offset: MdOffset,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Li {
+                    is_task,
+                    task_mark,
+                    task_mark_offset,
+                };
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_hr(&mut self) -> MdResult {
+                MdDetailParser::leave_block(self, MdBlockDetail::Hr)
+            }
+
+            fn leave_h(&mut self, level: u32) -> MdResult {
+                let detail = MdBlockDetail::H { level };
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_code(
+                &mut self,
+                info: MdAttribute,
+                lang: MdAttribute,
+                fence_char: MdChar,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Code {
+                    info,
+                    lang,
+                    fence_char,
+                };
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_html(&mut self) -> MdResult {
+                MdDetailParser::leave_block(self, MdBlockDetail::Html)
+            }
+
+            fn leave_p(&mut self) -> MdResult {
+                MdDetailParser::leave_block(self, MdBlockDetail::P)
+            }
+
+            fn leave_table(
+                &mut self,
+                col_count: u32,
+                head_row_count: u32,
+                body_row_count: u32,
+            ) -> MdResult {
+                let detail = MdBlockDetail::Table {
+                    col_count,
+                    head_row_count,
+                    body_row_count,
+                };
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_thead(&mut self) -> MdResult {
+                let detail = MdBlockDetail::Thead;
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_tbody(&mut self) -> MdResult {
+                let detail = MdBlockDetail::Tbody;
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_tr(&mut self) -> MdResult {
+                MdDetailParser::leave_block(self, MdBlockDetail::Tr)
+            }
+
+            fn leave_th(&mut self, align: MdAlign) -> MdResult {
+                let detail = MdBlockDetail::Th { align };
+                MdDetailParser::leave_block(self, detail)
+            }
+
+            fn leave_td(&mut self, align: MdAlign) -> MdResult {
+                let detail = MdBlockDetail::Td { align };
+                MdDetailParser::leave_block(self, detail)
+            }
+        }
+
+        impl<$($generics)*> MdEnterSpanCallback for $renderer {
+            fn enter_em(&mut self) -> MdResult {
+                MdDetailParser::enter_span(self, MdSpanDetail::Em)
+            }
+
+            fn enter_strong(&mut self) -> MdResult {
+                MdDetailParser::enter_span(self, MdSpanDetail::Strong)
+            }
+
+            fn enter_a(
+                &mut self,
+                href: MdAttribute,
+                title: MdAttribute,
+            ) -> MdResult {
+                let detail = MdSpanDetail::A { href, title };
+                MdDetailParser::enter_span(self, detail)
+            }
+
+            fn enter_img(
+                &mut self,
+                src: MdAttribute,
+                title: MdAttribute,
+            ) -> MdResult {
+                let detail = MdSpanDetail::Img { src, title };
+                MdDetailParser::enter_span(self, detail)
+            }
+
+            fn enter_ilcode(&mut self) -> MdResult {
+                MdDetailParser::enter_span(self, MdSpanDetail::Code)
+            }
+
+            fn enter_del(&mut self) -> MdResult {
+                MdDetailParser::enter_span(self, MdSpanDetail::Del)
+            }
+
+            fn enter_latexmath(&mut self) -> MdResult {
+                let detail = MdSpanDetail::Latexmath;
+                MdDetailParser::enter_span(self, detail)
+            }
+
+            fn enter_latexmath_display(&mut self) -> MdResult {
+                let detail = MdSpanDetail::LatexmathDisplay;
+                MdDetailParser::enter_span(self, detail)
+            }
+
+            fn enter_wikilink(
+                &mut self,
+                target: MdAttribute,
+            ) -> MdResult {
+                let detail = MdSpanDetail::Wikilink { target };
+                MdDetailParser::enter_span(self, detail)
+            }
+
+            fn enter_u(&mut self) -> MdResult {
+                MdDetailParser::enter_span(self, MdSpanDetail::U)
+            }
+        }
+
+        impl<$($generics)*> MdLeaveSpanCallback for $renderer {
+            fn leave_em(&mut self) -> MdResult {
+                MdDetailParser::leave_span(self, MdSpanDetail::Em)
+            }
+
+            fn leave_strong(&mut self) -> MdResult {
+                MdDetailParser::leave_span(self, MdSpanDetail::Strong)
+            }
+
+            fn leave_a(
+                &mut self,
+                href: MdAttribute,
+                title: MdAttribute,
+            ) -> MdResult {
+                let detail = MdSpanDetail::A { href, title };
+                MdDetailParser::leave_span(self, detail)
+            }
+
+            fn leave_img(
+                &mut self,
+                src: MdAttribute,
+                title: MdAttribute,
+            ) -> MdResult {
+                let detail = MdSpanDetail::Img { src, title };
+                MdDetailParser::leave_span(self, detail)
+            }
+
+            fn leave_ilcode(&mut self) -> MdResult {
+                MdDetailParser::leave_span(self, MdSpanDetail::Code)
+            }
+
+            fn leave_del(&mut self) -> MdResult {
+                MdDetailParser::leave_span(self, MdSpanDetail::Del)
+            }
+
+            fn leave_latexmath(&mut self) -> MdResult {
+                let detail = MdSpanDetail::Latexmath;
+                MdDetailParser::leave_span(self, detail)
+            }
+
+            fn leave_latexmath_display(&mut self) -> MdResult {
+                let detail = MdSpanDetail::LatexmathDisplay;
+                MdDetailParser::leave_span(self, detail)
+            }
+
+            fn leave_wikilink(
+                &mut self,
+                target: MdAttribute,
+            ) -> MdResult {
+                let detail = MdSpanDetail::Wikilink { target };
+                MdDetailParser::leave_span(self, detail)
+            }
+
+            fn leave_u(&mut self) -> MdResult {
+                MdDetailParser::leave_span(self, MdSpanDetail::U)
+            }
+        }
+
+        impl<$($generics)*> MdTextCallback for $renderer {
+            fn text_normal(&mut self, text: &str) -> MdResult {
+                MdDetailParser::text(self, MdTexttype::Normal, text)
+            }
+
+            fn text_nullchar(&mut self, text: &str) -> MdResult {
+                MdDetailParser::text(self, MdTexttype::Nullchar, text)
+            }
+
+            fn text_br(&mut self, text: &str) -> MdResult {
+                MdDetailParser::text(self, MdTexttype::Br, text)
+            }
+
+            fn text_softbr(&mut self, text: &str) -> MdResult {
+                MdDetailParser::text(self, MdTexttype::Softbr, text)
+            }
+
+            fn text_entity(&mut self, text: &str) -> MdResult {
+                MdDetailParser::text(self, MdTexttype::Entity, text)
+            }
+
+            fn text_code(&mut self, text: &str) -> MdResult {
+                MdDetailParser::text(self, MdTexttype::Code, text)
+            }
+
+            fn text_html(&mut self, text: &str) -> MdResult {
+                MdDetailParser::text(self, MdTexttype::Html, text)
+            }
+
+            fn text_latexmath(&mut self, text: &str) -> MdResult {
+                let texttype = MdTexttype::Latexmath;
+                MdDetailParser::text(self, texttype, text)
+            }
+        }
+
+        impl<$($generics)*> MdParser for $renderer {}
+    };
+}
+
+pub(crate) use impl_md_parser;
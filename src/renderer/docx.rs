@@ -0,0 +1,939 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::zip::Zip;
+use super::{Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+/// English Metric Units per pixel at 96 DPI.
+const EMU_PER_PIXEL: u32 = 9525;
+
+/// Widest image, in pixels: the text width of a Letter page.
+const MAX_IMAGE_WIDTH: u32 = 624;
+
+/// Font size of each heading level, in half points.
+const HEADING_SIZES: [u32; 6] = [32, 28, 26, 24, 22, 22];
+
+const NS_W: &str =
+    "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const NS_WP: &str = "http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing";
+const NS_A: &str =
+    "http://schemas.openxmlformats.org/drawingml/2006/main";
+const NS_PIC: &str =
+    "http://schemas.openxmlformats.org/drawingml/2006/picture";
+const NS_PACKAGE_RELS: &str =
+    "http://schemas.openxmlformats.org/package/2006/relationships";
+
+pub trait Md2DocxUserdata: Sized {
+    /// Receives the whole archive once the document is complete.
+    fn render_append(&mut self, data: &[u8]);
+
+    /// Content of the image at `src`, to embed it. Images without
+    /// content are left to their description.
+    #[inline]
+    fn resolve_image(&mut self, _src: &str) -> Option<Vec<u8>> {
+        None
+    }
+}
+
+impl Md2DocxUserdata for Vec<u8> {
+    #[inline]
+    fn render_append(&mut self, data: &[u8]) {
+        self.extend_from_slice(data);
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2DocxOptions {
+    title: Option<String>,
+}
+
+impl Md2DocxOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Title of the document, in its properties.
+    #[inline]
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_owned());
+        self
+    }
+}
+
+/// A list, numbered by its own `w:num` so that it restarts.
+struct List {
+    num_id: usize,
+}
+
+/// An embedded image, as `word/media/image<N>.<ext>`.
+struct Media {
+    ext: &'static str,
+    data: Vec<u8>,
+}
+
+/// Renders Markdown as a Word document: the userdata receives the
+/// `.docx` archive at the end of the document.
+pub struct Md2DocxRenderer<T> {
+    pub options: Md2DocxOptions,
+    pub userdata: T,
+    body: String,
+    /// Targets of the hyperlink relationships, `rIdLink<N>`.
+    links: Vec<String>,
+    media: Vec<Media>,
+    /// Whether each `w:num` is ordered, with its start.
+    nums: Vec<Option<u32>>,
+    lists: Vec<List>,
+    quote_depth: u32,
+    /// Task marker and numbering of the list item just started, for
+    /// its first paragraph.
+    item_start: Option<String>,
+    in_para: bool,
+    in_header_row: bool,
+    code: Option<String>,
+    strong: u32,
+    em: u32,
+    underline: u32,
+    strike: u32,
+    verbatim: u32,
+    link: u32,
+    /// Source and description of the open images.
+    images: Vec<(String, String)>,
+}
+
+impl<T> Md2DocxRenderer<T>
+where
+    T: Md2DocxUserdata,
+{
+    pub fn new(userdata: T, options: Md2DocxOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            body: String::new(),
+            links: Vec::new(),
+            media: Vec::new(),
+            nums: Vec::new(),
+            lists: Vec::new(),
+            quote_depth: 0,
+            item_start: None,
+            in_para: false,
+            in_header_row: false,
+            code: None,
+            strong: 0,
+            em: 0,
+            underline: 0,
+            strike: 0,
+            verbatim: 0,
+            link: 0,
+            images: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    /// Start a paragraph of `style`, indented for the enclosing
+    /// containers, or numbered as the first of a list item.
+    fn paragraph(&mut self, style: Option<&str>) {
+        self.end_paragraph();
+        let mut ppr = String::new();
+        let style = match (style, self.quote_depth) {
+            (Some(style), _) => Some(style),
+            (None, 0) => None,
+            (None, _) => Some("Quote"),
+        };
+        if let Some(style) = style {
+            ppr.push_str(&format!("<w:pStyle w:val=\"{}\"/>", style));
+        }
+        let task = self.item_start.take();
+        let depth = self.lists.len() as u32;
+        match (task.is_some(), self.lists.last()) {
+            (true, Some(list)) => ppr.push_str(&format!(
+                "<w:numPr><w:ilvl w:val=\"{}\"/>\
+                 <w:numId w:val=\"{}\"/></w:numPr>",
+                depth - 1,
+                list.num_id
+            )),
+            _ => {
+                let indent = 720 * (depth + self.quote_depth);
+                if indent > 0 {
+                    ppr.push_str(&format!(
+                        "<w:ind w:left=\"{}\"/>",
+                        indent
+                    ));
+                }
+            }
+        }
+        self.body.push_str("<w:p>");
+        if !ppr.is_empty() {
+            self.body.push_str(&format!("<w:pPr>{}</w:pPr>", ppr));
+        }
+        self.in_para = true;
+        if let Some(task) = task.filter(|task| !task.is_empty()) {
+            self.run(&task);
+        }
+    }
+
+    fn end_paragraph(&mut self) {
+        if std::mem::take(&mut self.in_para) {
+            self.body.push_str("</w:p>");
+        }
+    }
+
+    /// Open a paragraph for inline content if none is, as in the
+    /// items of tight lists.
+    fn inline(&mut self) {
+        if !self.in_para {
+            self.paragraph(None);
+        }
+    }
+
+    /// Properties of the runs in the current spans.
+    fn run_properties(&self) -> String {
+        let mut rpr = String::new();
+        if self.verbatim > 0 {
+            rpr.push_str("<w:rStyle w:val=\"VerbatimChar\"/>");
+        } else if self.link > 0 {
+            rpr.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+        }
+        if self.strong > 0 {
+            rpr.push_str("<w:b/>");
+        }
+        if self.em > 0 {
+            rpr.push_str("<w:i/>");
+        }
+        if self.strike > 0 {
+            rpr.push_str("<w:strike/>");
+        }
+        if self.underline > 0 {
+            rpr.push_str("<w:u w:val=\"single\"/>");
+        }
+        rpr
+    }
+
+    /// Append a run of `text`, whose line breaks become `w:br`.
+    fn run(&mut self, text: &str) {
+        if let Some((_, alt)) = self.images.last_mut() {
+            alt.push_str(text);
+            return;
+        }
+        self.inline();
+        let rpr = self.run_properties();
+        self.body.push_str("<w:r>");
+        if !rpr.is_empty() {
+            self.body.push_str(&format!("<w:rPr>{}</w:rPr>", rpr));
+        }
+        for (idx, line) in text.split('\n').enumerate() {
+            if idx > 0 {
+                self.body.push_str("<w:br/>");
+            }
+            if !line.is_empty() {
+                self.body.push_str(&format!(
+                    "<w:t xml:space=\"preserve\">{}</w:t>",
+                    escape(line)
+                ));
+            }
+        }
+        self.body.push_str("</w:r>");
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.body.clear();
+                self.links.clear();
+                self.media.clear();
+                self.nums.clear();
+            }
+            MdBlockDetail::Quote => {
+                self.end_paragraph();
+                self.quote_depth += 1;
+            }
+            MdBlockDetail::Ul { .. } => {
+                self.end_paragraph();
+                self.nums.push(None);
+                let num_id = self.nums.len();
+                self.lists.push(List { num_id });
+            }
+            MdBlockDetail::Ol { start, .. } => {
+                self.end_paragraph();
+                self.nums.push(Some(start));
+                let num_id = self.nums.len();
+                self.lists.push(List { num_id });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.end_paragraph();
+                // Word has no task lists, but for content controls.
+                let task = match (is_task, task_mark as u8) {
+                    (false, _) => "",
+                    (true, b' ') => "☐ ",
+                    (true, _) => "☒ ",
+                };
+                self.item_start = Some(task.to_owned());
+            }
+            MdBlockDetail::Hr => {
+                self.end_paragraph();
+                self.body.push_str(
+                    "<w:p><w:pPr><w:pBdr><w:bottom w:val=\"single\" \
+                     w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/>\
+                     </w:pBdr></w:pPr></w:p>",
+                );
+            }
+            MdBlockDetail::H { level } => {
+                let style = format!("Heading{}", level);
+                self.paragraph(Some(&style));
+            }
+            MdBlockDetail::P => self.paragraph(None),
+            MdBlockDetail::Html => self.end_paragraph(),
+            MdBlockDetail::Code { .. } => {
+                self.end_paragraph();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { col_count, .. } => {
+                self.end_paragraph();
+                let grid = "<w:gridCol/>".repeat(col_count as usize);
+                self.body.push_str(&format!(
+                    "<w:tbl><w:tblPr><w:tblStyle w:val=\"Table\"/>\
+                     <w:tblW w:w=\"0\" w:type=\"auto\"/></w:tblPr>\
+                     <w:tblGrid>{}</w:tblGrid>",
+                    grid
+                ));
+            }
+            MdBlockDetail::Thead => self.in_header_row = true,
+            MdBlockDetail::Tbody => self.in_header_row = false,
+            MdBlockDetail::Tr => match self.in_header_row {
+                true => self.body.push_str(
+                    "<w:tr><w:trPr><w:tblHeader/></w:trPr>",
+                ),
+                false => self.body.push_str("<w:tr>"),
+            },
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                self.body.push_str("<w:tc><w:p>");
+                let jc = match align {
+                    MdAlign::Default => "",
+                    MdAlign::Left => "left",
+                    MdAlign::Center => "center",
+                    MdAlign::Right => "right",
+                };
+                if !jc.is_empty() {
+                    self.body.push_str(&format!(
+                        "<w:pPr><w:jc w:val=\"{}\"/></w:pPr>",
+                        jc
+                    ));
+                }
+                self.in_para = true;
+                if self.in_header_row {
+                    self.strong += 1;
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.end_paragraph();
+                let docx = self.archive();
+                self.userdata.render_append(&docx);
+            }
+            MdBlockDetail::Quote => {
+                self.end_paragraph();
+                self.quote_depth -= 1;
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.end_paragraph();
+                self.lists.pop();
+            }
+            MdBlockDetail::Code { .. } => {
+                let code = self.code.take().unwrap_or_default();
+                self.paragraph(Some("SourceCode"));
+                self.verbatim += 1;
+                self.run(code.strip_suffix('\n').unwrap_or(&code));
+                self.verbatim -= 1;
+                self.end_paragraph();
+            }
+            MdBlockDetail::Table { .. } => {
+                self.body.push_str("</w:tbl>")
+            }
+            MdBlockDetail::Tr => self.body.push_str("</w:tr>"),
+            MdBlockDetail::Th { .. } | MdBlockDetail::Td { .. } => {
+                if self.in_header_row {
+                    self.strong -= 1;
+                }
+                self.body.push_str("</w:p></w:tc>");
+                self.in_para = false;
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            _ => self.end_paragraph(),
+        }
+    }
+
+    fn enter_link(&mut self, url: &str) {
+        self.inline();
+        match url.strip_prefix('#') {
+            Some(anchor) => self.body.push_str(&format!(
+                "<w:hyperlink w:anchor=\"{}\">",
+                escape(anchor)
+            )),
+            None => {
+                self.links.push(url.to_owned());
+                self.body.push_str(&format!(
+                    "<w:hyperlink r:id=\"rIdLink{}\">",
+                    self.links.len()
+                ));
+            }
+        }
+        self.link += 1;
+    }
+
+    fn leave_link(&mut self) {
+        self.link -= 1;
+        self.body.push_str("</w:hyperlink>");
+    }
+
+    /// Embed the image just closed, or leave its description.
+    fn leave_image(&mut self) {
+        let (src, alt) = match self.images.pop() {
+            Some(image) => image,
+            None => return,
+        };
+        let data = match self.userdata.resolve_image(&src) {
+            Some(data) => data,
+            None => return self.run(&alt),
+        };
+        let ext = image_extension(&data, &src);
+        let (width, height) = image_size(&data).unwrap_or((480, 360));
+        let (cx, cy) = match width > MAX_IMAGE_WIDTH {
+            true => (
+                MAX_IMAGE_WIDTH,
+                height * MAX_IMAGE_WIDTH / width.max(1),
+            ),
+            false => (width, height),
+        };
+        let (cx, cy) = (cx * EMU_PER_PIXEL, cy * EMU_PER_PIXEL);
+        self.media.push(Media { ext, data });
+        let id = self.media.len();
+        self.inline();
+        self.body.push_str(&format!(
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" \
+             distL=\"0\" distR=\"0\"><wp:extent cx=\"{cx}\" cy=\"{cy}\"/>\
+             <wp:docPr id=\"{id}\" name=\"Picture {id}\" descr=\"{alt}\"/>\
+             <a:graphic xmlns:a=\"{ns_a}\"><a:graphicData uri=\"{ns_pic}\">\
+             <pic:pic xmlns:pic=\"{ns_pic}\"><pic:nvPicPr>\
+             <pic:cNvPr id=\"{id}\" name=\"image{id}.{ext}\"/>\
+             <pic:cNvPicPr/></pic:nvPicPr><pic:blipFill>\
+             <a:blip r:embed=\"rIdImage{id}\"/>\
+             <a:stretch><a:fillRect/></a:stretch></pic:blipFill>\
+             <pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/>\
+             <a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm>\
+             <a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom>\
+             </pic:spPr></pic:pic></a:graphicData></a:graphic>\
+             </wp:inline></w:drawing></w:r>",
+            alt = escape(&alt),
+            cx = cx,
+            cy = cy,
+            id = id,
+            ns_a = NS_A,
+            ns_pic = NS_PIC,
+            ext = ext
+        ));
+    }
+
+    /// The `.docx` archive of the document rendered.
+    fn archive(&mut self) -> Vec<u8> {
+        let mut zip = Zip::new();
+        zip.add(
+            "[Content_Types].xml",
+            self.content_types().as_bytes(),
+        );
+        zip.add("_rels/.rels", self.package_rels().as_bytes());
+        zip.add(
+            "word/_rels/document.xml.rels",
+            self.document_rels().as_bytes(),
+        );
+        let document = format!(
+            "{xml_decl}<w:document xmlns:w=\"{ns_w}\" xmlns:r=\"{ns_r}\" \
+             xmlns:wp=\"{ns_wp}\"><w:body>{}<w:sectPr>\
+             <w:pgSz w:w=\"12240\" w:h=\"15840\"/>\
+             <w:pgMar w:top=\"1440\" w:right=\"1440\" \
+             w:bottom=\"1440\" w:left=\"1440\" w:header=\"720\" \
+             w:footer=\"720\" w:gutter=\"0\"/></w:sectPr>\
+             </w:body></w:document>",
+            std::mem::take(&mut self.body),
+            xml_decl = XML_DECL,
+            ns_w = NS_W,
+            ns_r = NS_R,
+            ns_wp = NS_WP
+        );
+        zip.add("word/document.xml", document.as_bytes());
+        zip.add("word/styles.xml", styles().as_bytes());
+        zip.add("word/numbering.xml", self.numbering().as_bytes());
+        if let Some(title) = self.options.title.as_deref() {
+            let core = format!(
+                "{xml_decl}<cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" \
+                 xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\
+                 <dc:title>{}</dc:title></cp:coreProperties>",
+                escape(title),
+                xml_decl = XML_DECL
+            );
+            zip.add("docProps/core.xml", core.as_bytes());
+        }
+        for (idx, media) in self.media.iter().enumerate() {
+            let name =
+                format!("word/media/image{}.{}", idx + 1, media.ext);
+            zip.add(&name, &media.data);
+        }
+        zip.finish()
+    }
+
+    fn content_types(&self) -> String {
+        let mut types = format!(
+            "{xml_decl}<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+             <Default Extension=\"rels\" \
+             ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+             <Default Extension=\"xml\" ContentType=\"application/xml\"/>",
+            xml_decl = XML_DECL
+        );
+        let mut exts: Vec<&str> =
+            self.media.iter().map(|media| media.ext).collect();
+        exts.sort_unstable();
+        exts.dedup();
+        for ext in exts {
+            types.push_str(&format!(
+                "<Default Extension=\"{}\" ContentType=\"{}\"/>",
+                ext,
+                image_content_type(ext)
+            ));
+        }
+        let parts = [
+            ("/word/document.xml", "document.main"),
+            ("/word/styles.xml", "styles"),
+            ("/word/numbering.xml", "numbering"),
+        ];
+        for (name, kind) in parts {
+            types.push_str(&format!(
+                "<Override PartName=\"{}\" ContentType=\"application/\
+                 vnd.openxmlformats-officedocument.wordprocessingml.\
+                 {}+xml\"/>",
+                name, kind
+            ));
+        }
+        if self.options.title.is_some() {
+            types.push_str(
+                "<Override PartName=\"/docProps/core.xml\" \
+                 ContentType=\"application/vnd.openxmlformats-package.\
+                 core-properties+xml\"/>",
+            );
+        }
+        types.push_str("</Types>");
+        types
+    }
+
+    fn package_rels(&self) -> String {
+        let mut rels = format!(
+            "{xml_decl}<Relationships xmlns=\"{ns_package_rels}\">\
+             <Relationship Id=\"rId1\" Type=\"{ns_r}/officeDocument\" \
+             Target=\"word/document.xml\"/>",
+            xml_decl = XML_DECL,
+            ns_package_rels = NS_PACKAGE_RELS,
+            ns_r = NS_R
+        );
+        if self.options.title.is_some() {
+            rels.push_str(
+                "<Relationship Id=\"rId2\" Type=\"http://schemas.\
+                 openxmlformats.org/package/2006/relationships/\
+                 metadata/core-properties\" \
+                 Target=\"docProps/core.xml\"/>",
+            );
+        }
+        rels.push_str("</Relationships>");
+        rels
+    }
+
+    fn document_rels(&self) -> String {
+        let mut rels = format!(
+            "{xml_decl}<Relationships xmlns=\"{ns_package_rels}\">\
+             <Relationship Id=\"rIdStyles\" Type=\"{ns_r}/styles\" \
+             Target=\"styles.xml\"/>\
+             <Relationship Id=\"rIdNumbering\" Type=\"{ns_r}/numbering\" \
+             Target=\"numbering.xml\"/>",
+            xml_decl = XML_DECL,
+            ns_package_rels = NS_PACKAGE_RELS,
+            ns_r = NS_R
+        );
+        for (idx, url) in self.links.iter().enumerate() {
+            rels.push_str(&format!(
+                "<Relationship Id=\"rIdLink{}\" Type=\"{ns_r}/hyperlink\" \
+                 Target=\"{}\" TargetMode=\"External\"/>",
+                idx + 1,
+                escape(url),
+                ns_r = NS_R
+            ));
+        }
+        for (idx, media) in self.media.iter().enumerate() {
+            rels.push_str(&format!(
+                "<Relationship Id=\"rIdImage{id}\" Type=\"{ns_r}/image\" \
+                 Target=\"media/image{id}.{}\"/>",
+                media.ext,
+                id = idx + 1,
+                ns_r = NS_R
+            ));
+        }
+        rels.push_str("</Relationships>");
+        rels
+    }
+
+    /// One abstract numbering for bullets and one for numbers, and a
+    /// numbering per list, restarting at its start.
+    fn numbering(&self) -> String {
+        let mut numbering = format!(
+            "{xml_decl}<w:numbering xmlns:w=\"{ns_w}\">",
+            xml_decl = XML_DECL,
+            ns_w = NS_W
+        );
+        for (id, ordered) in [(0, false), (1, true)] {
+            numbering.push_str(&format!(
+                "<w:abstractNum w:abstractNumId=\"{}\">\
+                 <w:multiLevelType w:val=\"hybridMultilevel\"/>",
+                id
+            ));
+            for level in 0..9 {
+                let (format, text) = match ordered {
+                    true => ("decimal", format!("%{}.", level + 1)),
+                    false => ("bullet", "•".to_owned()),
+                };
+                numbering.push_str(&format!(
+                    "<w:lvl w:ilvl=\"{level}\"><w:start w:val=\"1\"/>\
+                     <w:numFmt w:val=\"{format}\"/>\
+                     <w:lvlText w:val=\"{text}\"/><w:lvlJc w:val=\"left\"/>\
+                     <w:pPr><w:ind w:left=\"{}\" w:hanging=\"360\"/>\
+                     </w:pPr></w:lvl>",
+                    720 * (level + 1),
+                    level = level,
+                    format = format,
+                    text = text
+                ));
+            }
+            numbering.push_str("</w:abstractNum>");
+        }
+        for (idx, start) in self.nums.iter().enumerate() {
+            numbering.push_str(&format!(
+                "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>",
+                idx + 1,
+                start.is_some() as u8
+            ));
+            // The level of a list is its depth, unknown here: all
+            // restart.
+            if let Some(start) = start {
+                for level in 0..9 {
+                    numbering.push_str(&format!(
+                        "<w:lvlOverride w:ilvl=\"{}\">\
+                         <w:startOverride w:val=\"{}\"/></w:lvlOverride>",
+                        level, start
+                    ));
+                }
+            }
+            numbering.push_str("</w:num>");
+        }
+        numbering.push_str("</w:numbering>");
+        numbering
+    }
+}
+
+const XML_DECL: &str =
+    "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n";
+
+/// Styles of the document: the headings, quotes, code and links.
+fn styles() -> String {
+    let mut styles = format!(
+        "{xml_decl}<w:styles xmlns:w=\"{ns_w}\"><w:docDefaults>\
+         <w:rPrDefault><w:rPr><w:rFonts w:ascii=\"Calibri\" \
+         w:hAnsi=\"Calibri\" w:eastAsia=\"Calibri\" w:cs=\"Calibri\"/>\
+         <w:sz w:val=\"22\"/></w:rPr></w:rPrDefault>\
+         <w:pPrDefault><w:pPr><w:spacing w:after=\"160\"/></w:pPr>\
+         </w:pPrDefault></w:docDefaults>\
+         <w:style w:type=\"paragraph\" w:default=\"1\" w:styleId=\"Normal\">\
+         <w:name w:val=\"Normal\"/></w:style>",
+        xml_decl = XML_DECL,
+        ns_w = NS_W
+    );
+    for (idx, size) in HEADING_SIZES.iter().enumerate() {
+        styles.push_str(&format!(
+            "<w:style w:type=\"paragraph\" w:styleId=\"Heading{level}\">\
+             <w:name w:val=\"heading {level}\"/><w:basedOn w:val=\"Normal\"/>\
+             <w:next w:val=\"Normal\"/><w:qFormat/><w:pPr><w:keepNext/>\
+             <w:spacing w:before=\"240\" w:after=\"80\"/>\
+             <w:outlineLvl w:val=\"{idx}\"/></w:pPr>\
+             <w:rPr><w:b/><w:sz w:val=\"{size}\"/></w:rPr></w:style>",
+            level = idx + 1,
+            idx = idx,
+            size = size
+        ));
+    }
+    styles.push_str(
+        "<w:style w:type=\"paragraph\" w:styleId=\"Quote\">\
+         <w:name w:val=\"Quote\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:qFormat/><w:pPr><w:ind w:left=\"720\"/></w:pPr>\
+         <w:rPr><w:i/><w:color w:val=\"404040\"/></w:rPr></w:style>\
+         <w:style w:type=\"paragraph\" w:styleId=\"SourceCode\">\
+         <w:name w:val=\"Source Code\"/><w:basedOn w:val=\"Normal\"/>\
+         <w:pPr><w:shd w:val=\"clear\" w:color=\"auto\" \
+         w:fill=\"F2F2F2\"/><w:spacing w:after=\"160\" w:line=\"240\" \
+         w:lineRule=\"auto\"/></w:pPr></w:style>\
+         <w:style w:type=\"character\" w:styleId=\"VerbatimChar\">\
+         <w:name w:val=\"Verbatim Char\"/><w:rPr><w:rFonts \
+         w:ascii=\"Courier New\" w:hAnsi=\"Courier New\" \
+         w:cs=\"Courier New\"/><w:sz w:val=\"20\"/></w:rPr></w:style>\
+         <w:style w:type=\"character\" w:styleId=\"Hyperlink\">\
+         <w:name w:val=\"Hyperlink\"/><w:rPr><w:color w:val=\"0563C1\"/>\
+         <w:u w:val=\"single\"/></w:rPr></w:style>\
+         <w:style w:type=\"table\" w:styleId=\"Table\">\
+         <w:name w:val=\"Table\"/><w:tblPr><w:tblBorders>\
+         <w:top w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+         <w:left w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+         <w:bottom w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+         <w:right w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+         <w:insideH w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+         <w:insideV w:val=\"single\" w:sz=\"4\" w:space=\"0\" w:color=\"auto\"/>\
+         </w:tblBorders><w:tblCellMar><w:left w:w=\"108\" w:type=\"dxa\"/>\
+         <w:right w:w=\"108\" w:type=\"dxa\"/></w:tblCellMar></w:tblPr>\
+         </w:style></w:styles>",
+    );
+    styles
+}
+
+/// Escape `text` for XML, dropping the characters XML cannot hold.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\t' | '\n' | '\r' => escaped.push(ch),
+            ch if ch.is_control() => (),
+            ch => escaped.push(ch),
+        }
+    }
+    escaped
+}
+
+/// Extension of an image, from its signature or else its source.
+fn image_extension(data: &[u8], src: &str) -> &'static str {
+    if data.starts_with(b"\x89PNG") {
+        return "png";
+    }
+    if data.starts_with(b"\xff\xd8") {
+        return "jpeg";
+    }
+    if data.starts_with(b"GIF8") {
+        return "gif";
+    }
+    let ext = src.rsplit('.').next().unwrap_or("").to_lowercase();
+    match ext.as_str() {
+        "bmp" => "bmp",
+        "tif" | "tiff" => "tiff",
+        "svg" => "svg",
+        "emf" => "emf",
+        "wmf" => "wmf",
+        _ => "png",
+    }
+}
+
+fn image_content_type(ext: &str) -> &'static str {
+    match ext {
+        "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "bmp" => "image/bmp",
+        "tiff" => "image/tiff",
+        "svg" => "image/svg+xml",
+        "emf" => "image/x-emf",
+        "wmf" => "image/x-wmf",
+        _ => "image/png",
+    }
+}
+
+/// Width and height in pixels of a PNG, GIF or JPEG image.
+fn image_size(data: &[u8]) -> Option<(u32, u32)> {
+    let be16 = |at: usize| -> Option<u32> {
+        Some(u16::from_be_bytes(
+            data.get(at..at + 2)?.try_into().ok()?,
+        ) as u32)
+    };
+    if data.starts_with(b"\x89PNG") {
+        let be32 = |at: usize| -> Option<u32> {
+            Some(u32::from_be_bytes(
+                data.get(at..at + 4)?.try_into().ok()?,
+            ))
+        };
+        return Some((be32(16)?, be32(20)?));
+    }
+    if data.starts_with(b"GIF8") {
+        let le16 = |at: usize| -> Option<u32> {
+            let bytes = data.get(at..at + 2)?.try_into().ok()?;
+            Some(u16::from_le_bytes(bytes) as u32)
+        };
+        return Some((le16(6)?, le16(8)?));
+    }
+    if data.starts_with(b"\xff\xd8") {
+        // Walk the segments up to a start of frame.
+        let mut at = 2;
+        while *data.get(at)? == 0xff {
+            let marker = *data.get(at + 1)?;
+            let is_frame = (0xc0..=0xcf).contains(&marker)
+                && !matches!(marker, 0xc4 | 0xc8 | 0xcc);
+            if is_frame {
+                return Some((be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+impl<T> MdDetailParser for Md2DocxRenderer<T>
+where
+    T: Md2DocxUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        if !self.images.is_empty() {
+            // Only the text of spans goes in the description.
+            if let MdSpanDetail::Img { src, .. } = detail {
+                self.images.push((src.decode(), String::new()));
+            }
+            return Ok(());
+        }
+        match detail {
+            MdSpanDetail::Em => self.em += 1,
+            MdSpanDetail::Strong => self.strong += 1,
+            MdSpanDetail::U => self.underline += 1,
+            MdSpanDetail::Del => self.strike += 1,
+            MdSpanDetail::Code
+            | MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => self.verbatim += 1,
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(&href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.images.push((src.decode(), String::new()))
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(&target.decode())
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if !self.images.is_empty() {
+            if spantype == MdSpantype::Img {
+                match self.images.len() {
+                    1 => self.leave_image(),
+                    _ => {
+                        // Nested images only keep their description.
+                        let (_, alt) = self.images.pop().unwrap();
+                        if let Some((_, outer)) =
+                            self.images.last_mut()
+                        {
+                            outer.push_str(&alt);
+                        }
+                    }
+                }
+            }
+            return Ok(());
+        }
+        match spantype {
+            MdSpantype::Em => self.em -= 1,
+            MdSpantype::Strong => self.strong -= 1,
+            MdSpantype::U => self.underline -= 1,
+            MdSpantype::Del => self.strike -= 1,
+            MdSpantype::Code
+            | MdSpantype::Latexmath
+            | MdSpantype::LatexmathDisplay => self.verbatim -= 1,
+            MdSpantype::A | MdSpantype::Wikilink => self.leave_link(),
+            MdSpantype::Img => (),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(code) = self.code.as_mut() {
+            code.push_str(text);
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => self.run("\u{fffd}"),
+            MdTexttype::Br => self.run("\n"),
+            MdTexttype::Softbr => self.run(" "),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.run(&decoded),
+                None => self.run(text),
+            },
+            _ => self.run(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2DocxUserdata] Md2DocxRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2DocxRenderer<T>
+where
+    T: Md2DocxUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2DocxRenderer<T>>
+where
+    T: Md2DocxUserdata,
+{
+    pub fn docx(userdata: T, options: Md2DocxOptions) -> Self {
+        Self(Md2DocxRenderer::new(userdata, options), PhantomData)
+    }
+}
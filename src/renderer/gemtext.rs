@@ -0,0 +1,354 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{self, Layout, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2GemtextOptions {
+    links_at_end: bool,
+}
+
+impl Md2GemtextOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Put the link lines of the whole document at its end, instead of
+    /// after the paragraph of each link.
+    #[inline]
+    pub fn links_at_end(mut self) -> Self {
+        self.links_at_end = true;
+        self
+    }
+}
+
+/// Renders Markdown as Gemtext, the line-oriented format of Gemini.
+/// Inline markup is dropped; links, which can only be lines of their
+/// own, follow the block they appear in.
+pub struct Md2GemtextRenderer<T> {
+    pub options: Md2GemtextOptions,
+    pub userdata: T,
+    layout: Layout,
+    table: Option<TextTable>,
+    code: Option<String>,
+    /// Links waiting for the end of their block, as URL and text.
+    links: Vec<(String, String)>,
+    open_links: Vec<(String, usize)>,
+    quote_depth: usize,
+}
+
+impl<T> Md2GemtextRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2GemtextOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            layout: Self::layout(),
+            table: None,
+            code: None,
+            links: Vec::new(),
+            open_links: Vec::new(),
+            quote_depth: 0,
+        }
+    }
+
+    fn layout() -> Layout {
+        let mut layout = Layout::new(0);
+        layout.line_filter = Some(escape_line_start);
+        layout
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn sink(&mut self) -> &mut String {
+        layout::sink(
+            self.code.as_mut(),
+            self.table.as_mut(),
+            self.layout.inline(),
+        )
+    }
+
+    /// Output the pending link lines, once out of any quote, where they
+    /// would be quoted text.
+    fn write_links(&mut self) {
+        if self.quote_depth > 0 || self.options.links_at_end {
+            return;
+        }
+        for (url, text) in std::mem::take(&mut self.links) {
+            self.layout.line(&link_line(&url, &text));
+        }
+    }
+
+    fn preformatted(&mut self, alt: &str, text: &str) {
+        self.layout.line(&format!("```{}", alt));
+        for line in text.lines() {
+            match line.starts_with("```") {
+                true => self.layout.line(&escape_line_start(line)),
+                false => self.layout.line(line),
+            }
+        }
+        self.layout.line("```");
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Self::layout();
+                self.links.clear();
+            }
+            MdBlockDetail::Quote => {
+                self.layout.separate();
+                self.quote_depth += 1;
+                self.layout.push_prefix("> ", "> ");
+            }
+            MdBlockDetail::Ul { is_tight, .. }
+            | MdBlockDetail::Ol { is_tight, .. } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.layout.separate();
+                // Gemtext lists have no nesting nor numbers.
+                let marker = match (is_task, task_mark as u8) {
+                    (false, _) => "* ",
+                    (true, b' ') => "* [ ] ",
+                    (true, _) => "* [x] ",
+                };
+                self.layout.push_prefix(marker, "");
+            }
+            MdBlockDetail::Hr
+            | MdBlockDetail::H { .. }
+            | MdBlockDetail::Html
+            | MdBlockDetail::P => self.layout.separate(),
+            MdBlockDetail::Code { .. } => {
+                self.layout.separate();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.layout.separate();
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout.flush();
+                if !self.links.is_empty() {
+                    self.layout.blank();
+                    for (url, text) in std::mem::take(&mut self.links)
+                    {
+                        self.layout.line(&link_line(&url, &text));
+                    }
+                }
+            }
+            MdBlockDetail::Quote => {
+                self.layout.pop_prefix();
+                self.quote_depth -= 1;
+                self.write_links();
+            }
+            MdBlockDetail::Li { .. } => {
+                self.layout.flush();
+                self.write_links();
+                self.layout.pop_prefix();
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+            }
+            MdBlockDetail::Hr => self.layout.line(&"─".repeat(10)),
+            MdBlockDetail::H { level } => {
+                let marks = "#".repeat(level.min(3) as usize);
+                let text = std::mem::take(self.layout.inline());
+                self.layout.line(&format!(
+                    "{} {}",
+                    marks,
+                    text.trim()
+                ));
+                self.write_links();
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                let code = self.code.take().unwrap_or_default();
+                self.preformatted(&lang.decode(), &code);
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    let lines = table.lines("  ", Some('-'));
+                    self.preformatted("", &lines.join("\n"));
+                }
+                self.write_links();
+            }
+            MdBlockDetail::P => {
+                self.layout.flush();
+                self.write_links();
+            }
+            _ => self.layout.flush(),
+        }
+    }
+
+    fn enter_link(&mut self, url: String) {
+        let start = self.sink().len();
+        self.open_links.push((url, start));
+    }
+
+    fn leave_link(&mut self) {
+        if let Some((url, start)) = self.open_links.pop() {
+            let text = self.sink().get(start..).unwrap_or("").trim();
+            let text = text.to_owned();
+            self.links.push((url, text));
+        }
+    }
+}
+
+/// Keep a line of text from being read as a line of another type, e.g.
+/// a link line, with a zero-width space at its start: Gemtext has no
+/// escapes.
+fn escape_line_start(line: &str) -> String {
+    match ["=>", "```", "#", "* ", ">"]
+        .iter()
+        .any(|start| line.starts_with(start))
+    {
+        true => format!("\u{200b}{}", line),
+        false => line.to_owned(),
+    }
+}
+
+/// A link line, with the text of the link unless it is the URL itself,
+/// as with autolinks.
+fn link_line(url: &str, text: &str) -> String {
+    if text.is_empty() || text == url {
+        format!("=> {}", url)
+    } else {
+        format!("=> {} {}", url, text)
+    }
+}
+
+impl<T> MdDetailParser for Md2GemtextRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        match detail {
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode())
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(target.decode())
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if let MdSpantype::A
+        | MdSpantype::Img
+        | MdSpantype::Wikilink = spantype
+        {
+            self.leave_link();
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        let in_cell = self.code.is_none() && self.table.is_some();
+        let decoded;
+        let text = match texttype {
+            MdTexttype::Html => return Ok(()),
+            MdTexttype::Nullchar => "\u{fffd}",
+            MdTexttype::Br if in_cell => " ",
+            MdTexttype::Br => "\n",
+            MdTexttype::Softbr if self.code.is_none() => " ",
+            MdTexttype::Entity => {
+                decoded = entity_decode(text);
+                decoded.as_deref().unwrap_or(text)
+            }
+            _ => text,
+        };
+        self.sink().push_str(text);
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2GemtextRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2GemtextRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2GemtextRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn gemtext(userdata: T, options: Md2GemtextOptions) -> Self {
+        Self(Md2GemtextRenderer::new(userdata, options), PhantomData)
+    }
+}
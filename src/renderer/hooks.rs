@@ -0,0 +1,248 @@
+use crate::md4c::*;
+
+/// Closure overriding the output of a single block type. It receives
+/// the renderer it was registered on and the typed detail of the
+/// block.
+pub type MdBlockHook<R> =
+    Box<dyn FnMut(&mut R, &MdBlockDetail) -> MdResult>;
+
+/// Closure overriding the output of a single span type.
+pub type MdSpanHook<R> =
+    Box<dyn FnMut(&mut R, &MdSpanDetail) -> MdResult>;
+
+/// Closure overriding the output of text inside overridden elements.
+pub type MdTextHook<R> =
+    Box<dyn FnMut(&mut R, MdTexttype, &str) -> MdResult>;
+
+/// Runtime registry of per-element overrides, keyed by block and span
+/// type. At most one closure is kept per element and direction; a new
+/// registration replaces the previous one.
+///
+/// An element is overridden as soon as it has a closure for either
+/// direction. The text hook, if any, sees the text nested in
+/// overridden elements only.
+pub struct MdHooks<R> {
+    enter_block: [Option<MdBlockHook<R>>; 16],
+    leave_block: [Option<MdBlockHook<R>>; 16],
+    enter_span: [Option<MdSpanHook<R>>; 10],
+    leave_span: [Option<MdSpanHook<R>>; 10],
+    text: Option<MdTextHook<R>>,
+    depth: usize,
+}
+
+impl<R> MdHooks<R> {
+    pub fn new() -> Self {
+        Self {
+            enter_block: Default::default(),
+            leave_block: Default::default(),
+            enter_span: Default::default(),
+            leave_span: Default::default(),
+            text: None,
+            depth: 0,
+        }
+    }
+
+    pub fn set_enter_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        hook: MdBlockHook<R>,
+    ) {
+        self.enter_block[blocktype as usize] = Some(hook);
+    }
+
+    pub fn set_leave_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        hook: MdBlockHook<R>,
+    ) {
+        self.leave_block[blocktype as usize] = Some(hook);
+    }
+
+    pub fn set_enter_span(
+        &mut self,
+        spantype: MdSpantype,
+        hook: MdSpanHook<R>,
+    ) {
+        self.enter_span[spantype as usize] = Some(hook);
+    }
+
+    pub fn set_leave_span(
+        &mut self,
+        spantype: MdSpantype,
+        hook: MdSpanHook<R>,
+    ) {
+        self.leave_span[spantype as usize] = Some(hook);
+    }
+
+    pub fn set_text(&mut self, hook: MdTextHook<R>) {
+        self.text = Some(hook);
+    }
+
+    pub fn remove_text(&mut self) {
+        self.text = None;
+    }
+
+    /// Remove every override for the given block type.
+    pub fn remove_block(&mut self, blocktype: MdBlocktype) {
+        self.enter_block[blocktype as usize] = None;
+        self.leave_block[blocktype as usize] = None;
+    }
+
+    /// Remove every override for the given span type.
+    pub fn remove_span(&mut self, spantype: MdSpantype) {
+        self.enter_span[spantype as usize] = None;
+        self.leave_span[spantype as usize] = None;
+    }
+
+    pub fn has_enter_block(&self, blocktype: MdBlocktype) -> bool {
+        self.enter_block[blocktype as usize].is_some()
+    }
+
+    pub fn has_leave_block(&self, blocktype: MdBlocktype) -> bool {
+        self.leave_block[blocktype as usize].is_some()
+    }
+
+    pub fn has_enter_span(&self, spantype: MdSpantype) -> bool {
+        self.enter_span[spantype as usize].is_some()
+    }
+
+    pub fn has_leave_span(&self, spantype: MdSpantype) -> bool {
+        self.leave_span[spantype as usize].is_some()
+    }
+
+    pub fn has_text(&self) -> bool {
+        self.text.is_some()
+    }
+
+    /// Forget the overridden elements left open by a previous render,
+    /// which a hook error may have aborted.
+    pub(crate) fn reset_depth(&mut self) {
+        self.depth = 0;
+    }
+
+    /// Bit `1 << blocktype` is set for every overridden block type.
+    pub fn block_mask(&self) -> u32 {
+        (0..self.enter_block.len())
+            .filter(|&slot| self.is_block_hooked(slot))
+            .fold(0, |mask, slot| mask | 1 << slot)
+    }
+
+    /// Bit `1 << spantype` is set for every overridden span type.
+    pub fn span_mask(&self) -> u32 {
+        (0..self.enter_span.len())
+            .filter(|&slot| self.is_span_hooked(slot))
+            .fold(0, |mask, slot| mask | 1 << slot)
+    }
+
+    #[inline]
+    fn is_block_hooked(&self, slot: usize) -> bool {
+        self.enter_block[slot].is_some()
+            || self.leave_block[slot].is_some()
+    }
+
+    #[inline]
+    fn is_span_hooked(&self, slot: usize) -> bool {
+        self.enter_span[slot].is_some() || self.leave_span[slot].is_some()
+    }
+}
+
+impl<R> Default for MdHooks<R> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Access to the hook registry owned by a renderer. The `call_*`
+/// helpers temporarily move the closure out of the registry, so that
+/// it can borrow the renderer mutably while it runs. They return
+/// `None` when the default output should be rendered instead.
+pub(crate) trait MdHooked: Sized {
+    fn hooks(&mut self) -> &mut MdHooks<Self>;
+
+    fn call_enter_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut std::os::raw::c_void,
+    ) -> Option<MdResult> {
+        let slot = blocktype as usize;
+        let hooks = self.hooks();
+        if !hooks.is_block_hooked(slot) {
+            return None;
+        }
+        hooks.depth += 1;
+        let mut hook = hooks.enter_block[slot].take()?;
+        let detail = MdBlockDetail::from_raw(blocktype, detail);
+        let res = hook(self, &detail);
+        self.hooks().enter_block[slot].get_or_insert(hook);
+        Some(res)
+    }
+
+    fn call_leave_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut std::os::raw::c_void,
+    ) -> Option<MdResult> {
+        let slot = blocktype as usize;
+        let hooks = self.hooks();
+        if !hooks.is_block_hooked(slot) {
+            return None;
+        }
+        hooks.depth = hooks.depth.saturating_sub(1);
+        let mut hook = hooks.leave_block[slot].take()?;
+        let detail = MdBlockDetail::from_raw(blocktype, detail);
+        let res = hook(self, &detail);
+        self.hooks().leave_block[slot].get_or_insert(hook);
+        Some(res)
+    }
+
+    fn call_enter_span(
+        &mut self,
+        spantype: MdSpantype,
+        detail: *mut std::os::raw::c_void,
+    ) -> Option<MdResult> {
+        let slot = spantype as usize;
+        let hooks = self.hooks();
+        if !hooks.is_span_hooked(slot) {
+            return None;
+        }
+        hooks.depth += 1;
+        let mut hook = hooks.enter_span[slot].take()?;
+        let detail = MdSpanDetail::from_raw(spantype, detail);
+        let res = hook(self, &detail);
+        self.hooks().enter_span[slot].get_or_insert(hook);
+        Some(res)
+    }
+
+    fn call_leave_span(
+        &mut self,
+        spantype: MdSpantype,
+        detail: *mut std::os::raw::c_void,
+    ) -> Option<MdResult> {
+        let slot = spantype as usize;
+        let hooks = self.hooks();
+        if !hooks.is_span_hooked(slot) {
+            return None;
+        }
+        hooks.depth = hooks.depth.saturating_sub(1);
+        let mut hook = hooks.leave_span[slot].take()?;
+        let detail = MdSpanDetail::from_raw(spantype, detail);
+        let res = hook(self, &detail);
+        self.hooks().leave_span[slot].get_or_insert(hook);
+        Some(res)
+    }
+
+    fn call_text(
+        &mut self,
+        texttype: MdTexttype,
+        text: &str,
+    ) -> Option<MdResult> {
+        let hooks = self.hooks();
+        if hooks.depth == 0 {
+            return None;
+        }
+        let mut hook = hooks.text.take()?;
+        let res = hook(self, texttype, text);
+        self.hooks().text.get_or_insert(hook);
+        Some(res)
+    }
+}
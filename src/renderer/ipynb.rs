@@ -0,0 +1,274 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::json::Json;
+use super::sourcepos::block_ranges;
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug)]
+pub struct Md2IpynbOptions {
+    language: String,
+    kernel_name: String,
+    display_name: String,
+}
+
+impl Default for Md2IpynbOptions {
+    fn default() -> Self {
+        Self {
+            language: "python".to_owned(),
+            kernel_name: "python3".to_owned(),
+            display_name: "Python 3".to_owned(),
+        }
+    }
+}
+
+impl Md2IpynbOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Language of the kernel, `python` by default. Fenced code
+    /// blocks of this language, compared case-insensitively, become
+    /// code cells; the others stay in the Markdown cells.
+    #[inline]
+    pub fn language(mut self, language: &str) -> Self {
+        self.language = language.to_owned();
+        self
+    }
+
+    /// Kernel to run the notebook with, `python3` and `Python 3` by
+    /// default.
+    #[inline]
+    pub fn kernel(mut self, name: &str, display_name: &str) -> Self {
+        self.kernel_name = name.to_owned();
+        self.display_name = display_name.to_owned();
+        self
+    }
+}
+
+/// Converts a document to a Jupyter notebook (nbformat 4): its
+/// top-level fenced code blocks in the kernel language become code
+/// cells, and the input between them Markdown cells, as written.
+pub struct Md2IpynbRenderer<T> {
+    pub options: Md2IpynbOptions,
+    pub userdata: T,
+    blocks: Vec<Range<usize>>,
+    next_block: usize,
+    depth: usize,
+    /// Input ranges and sources of the code cells, the last one
+    /// possibly still being read.
+    code_cells: Vec<(Range<usize>, String)>,
+    in_code_cell: bool,
+}
+
+impl<T> Md2IpynbRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2IpynbOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            blocks: Vec::new(),
+            next_block: 0,
+            depth: 0,
+            code_cells: Vec::new(),
+            in_code_cell: false,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        // The BOM would be taken for text, hiding a fence after it.
+        let input = input.strip_prefix('\u{feff}').unwrap_or(input);
+        self.blocks = block_ranges(input, parser_flags)?;
+        self.next_block = 0;
+        self.depth = 0;
+        self.code_cells.clear();
+        self.parse(input, parser_flags)?;
+
+        let mut cells = Vec::new();
+        let mut cursor = 0;
+        for (range, source) in std::mem::take(&mut self.code_cells) {
+            markdown_cell(&mut cells, &input[cursor..range.start]);
+            // Past the closing fence, to the next line.
+            cursor = match input[range.end..].find('\n') {
+                Some(newline) => range.end + newline + 1,
+                None => input.len(),
+            };
+            let source = source.strip_suffix('\n').unwrap_or(&source);
+            let cell = Json::object()
+                .with("cell_type", "code")
+                .with("execution_count", Json::Null)
+                .with("id", cell_id(&cells))
+                .with("metadata", Json::object())
+                .with("outputs", Vec::new())
+                .with("source", source_lines(source));
+            cells.push(cell);
+        }
+        markdown_cell(&mut cells, &input[cursor..]);
+        let out = self.notebook(cells);
+        self.userdata.render_append(&out);
+        Ok(())
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        let range = self.blocks.get(self.next_block).cloned();
+        self.next_block += 1;
+        self.depth += 1;
+        if let MdBlockDetail::Code {
+            lang, fence_char, ..
+        } = detail
+        {
+            // Top-level blocks are the children of the document.
+            let is_cell = self.depth == 2
+                && fence_char != 0
+                && lang
+                    .decode()
+                    .eq_ignore_ascii_case(&self.options.language);
+            let after = self.code_cells.last().map_or(0, |c| c.0.end);
+            if let (true, Some(range)) = (is_cell, range) {
+                if range.start >= after {
+                    self.code_cells.push((range, String::new()));
+                    self.in_code_cell = true;
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+        self.in_code_cell = false;
+    }
+
+    /// The notebook, indented as Jupyter writes it.
+    fn notebook(&self, cells: Vec<Json>) -> String {
+        let language = self.options.language.as_str();
+        let kernelspec = Json::object()
+            .with("display_name", self.options.display_name.as_str())
+            .with("language", language)
+            .with("name", self.options.kernel_name.as_str());
+        let metadata =
+            Json::object().with("kernelspec", kernelspec).with(
+                "language_info",
+                Json::object().with("name", language),
+            );
+        let notebook = Json::object()
+            .with("cells", cells)
+            .with("metadata", metadata)
+            .with("nbformat", 4u32)
+            .with("nbformat_minor", 5u32);
+        let mut out = notebook.dump(1);
+        out.push('\n');
+        out
+    }
+}
+
+/// Add the Markdown cell of `prose`, without its leading and trailing
+/// blank lines, unless it is blank.
+fn markdown_cell(cells: &mut Vec<Json>, prose: &str) {
+    let prose = prose.trim_end();
+    let blank = prose.len() - prose.trim_start().len();
+    let start = prose[..blank].rfind('\n').map_or(0, |nl| nl + 1);
+    let prose = &prose[start..];
+    if prose.is_empty() {
+        return;
+    }
+    let cell = Json::object()
+        .with("cell_type", "markdown")
+        .with("id", cell_id(cells))
+        .with("metadata", Json::object())
+        .with("source", source_lines(prose));
+    cells.push(cell);
+}
+
+/// Id of the next cell, which nbformat 4.5 requires unique.
+fn cell_id(cells: &[Json]) -> String {
+    format!("cell-{}", cells.len() + 1)
+}
+
+/// The lines of a cell source, each but the last with its newline.
+fn source_lines(source: &str) -> Vec<Json> {
+    source.split_inclusive('\n').map(Json::from).collect()
+}
+
+impl<T> MdDetailParser for Md2IpynbRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, _detail: MdBlockDetail) -> MdResult {
+        self.leave();
+        Ok(())
+    }
+
+    fn enter_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        Ok(())
+    }
+
+    fn leave_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        Ok(())
+    }
+
+    fn text(
+        &mut self,
+        _texttype: MdTexttype,
+        text: &str,
+    ) -> MdResult {
+        if let (true, Some((_, source))) =
+            (self.in_code_cell, self.code_cells.last_mut())
+        {
+            source.push_str(text);
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2IpynbRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2IpynbRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2IpynbRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn ipynb(userdata: T, options: Md2IpynbOptions) -> Self {
+        Self(Md2IpynbRenderer::new(userdata, options), PhantomData)
+    }
+}
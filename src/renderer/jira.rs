@@ -0,0 +1,378 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::Layout;
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2JiraOptions {
+    default_lang: Option<String>,
+}
+
+impl Md2JiraOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Highlight code blocks without a language as `lang`. They are
+    /// `{noformat}` blocks otherwise, as `{code}` would take them for
+    /// Java.
+    #[inline]
+    pub fn default_lang(mut self, lang: &str) -> Self {
+        self.default_lang = Some(lang.to_owned());
+        self
+    }
+}
+
+/// Renders Markdown as the wiki markup of Jira and Confluence.
+pub struct Md2JiraRenderer<T> {
+    pub options: Md2JiraOptions,
+    pub userdata: T,
+    layout: Layout,
+    /// Marker of each open list, `#` or `*`, the innermost last.
+    lists: Vec<char>,
+    code: Option<String>,
+    /// Closing tag of the current code block.
+    code_end: &'static str,
+    in_table: bool,
+    /// Whether the cells of the current row are header cells.
+    in_header_row: bool,
+    open_links: Vec<(String, usize)>,
+}
+
+impl<T> Md2JiraRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2JiraOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            layout: Layout::new(0),
+            lists: Vec::new(),
+            code: None,
+            code_end: "",
+            in_table: false,
+            in_header_row: false,
+            open_links: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn write_escaped(&mut self, text: &str) {
+        let inline = self.layout.inline();
+        let mut prev = inline.chars().last();
+        let mut escaped = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        let is_boundary = |ch: Option<char>| {
+            !ch.is_some_and(|ch| ch.is_alphanumeric())
+        };
+        while let Some(ch) = chars.next() {
+            let next = chars.peek().copied();
+            let line_start = matches!(prev, None | Some('\n'));
+            // Effects open before and close after a non-space.
+            let could_open = is_boundary(prev)
+                && next.is_some_and(|ch| !ch.is_whitespace());
+            let could_close = is_boundary(next)
+                && prev.is_some_and(|ch| !ch.is_whitespace());
+            let escape = match ch {
+                '{' | '}' | '[' | ']' | '|' | '!' => true,
+                // List items and numbered list items.
+                '*' | '#' | '-' if line_start => true,
+                '*' | '_' | '-' | '+' | '^' | '~' => {
+                    could_open || could_close
+                }
+                '?' => next == Some('?') || prev == Some('?'),
+                _ => false,
+            };
+            if escape {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+            prev = Some(ch);
+        }
+        inline.push_str(&escaped);
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Layout::new(0);
+            }
+            MdBlockDetail::Quote => {
+                self.layout.separate();
+                self.layout.line("{quote}");
+                // Not to start the quote with a blank line.
+                self.layout.push_prefix("", "");
+            }
+            MdBlockDetail::Ul { .. } => {
+                self.layout.separate();
+                // A list item is a single line, and a blank line would
+                // end the list: lists are all tight.
+                self.layout.push_list(true);
+                self.lists.push('*');
+            }
+            MdBlockDetail::Ol { .. } => {
+                self.layout.separate();
+                self.layout.push_list(true);
+                self.lists.push('#');
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.layout.flush();
+                let mut marker: String = self.lists.iter().collect();
+                marker.push(' ');
+                // Wiki markup has no task lists.
+                match (is_task, task_mark as u8) {
+                    (false, _) => (),
+                    (true, b' ') => marker.push_str("☐ "),
+                    (true, _) => marker.push_str("☑ "),
+                }
+                self.layout.push_prefix(&marker, "");
+            }
+            MdBlockDetail::Hr => {
+                self.layout.separate();
+                self.layout.line("----");
+            }
+            MdBlockDetail::H { level } => {
+                self.layout.separate();
+                let prefix = format!("h{}. ", level);
+                self.layout.inline().push_str(&prefix);
+            }
+            MdBlockDetail::Html | MdBlockDetail::P => {
+                self.layout.separate()
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                self.layout.separate();
+                let lang = match lang.decode() {
+                    lang if lang.is_empty() => {
+                        self.options.default_lang.clone()
+                    }
+                    lang => Some(lang),
+                };
+                match lang {
+                    Some(lang) => {
+                        self.layout
+                            .line(&format!("{{code:{}}}", lang));
+                        self.code_end = "{code}";
+                    }
+                    None => {
+                        self.layout.line("{noformat}");
+                        self.code_end = "{noformat}";
+                    }
+                }
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { .. } => {
+                self.layout.separate();
+                self.in_table = true;
+            }
+            MdBlockDetail::Thead => self.in_header_row = true,
+            MdBlockDetail::Tbody => self.in_header_row = false,
+            MdBlockDetail::Tr => (),
+            MdBlockDetail::Th { .. } | MdBlockDetail::Td { .. } => {
+                let sep = match self.in_header_row {
+                    true => "||",
+                    false => "|",
+                };
+                self.layout.inline().push_str(sep);
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.layout.flush(),
+            MdBlockDetail::Quote => {
+                self.layout.pop_prefix();
+                self.layout.line("{quote}");
+            }
+            MdBlockDetail::Li { .. } => self.layout.pop_prefix(),
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.lists.pop();
+            }
+            MdBlockDetail::Code { .. } => {
+                let code = self.code.take().unwrap_or_default();
+                for line in code.lines() {
+                    self.layout.line(line);
+                }
+                self.layout.line(self.code_end);
+            }
+            MdBlockDetail::Table { .. } => self.in_table = false,
+            MdBlockDetail::Tr => {
+                let sep = match self.in_header_row {
+                    true => "||",
+                    false => "|",
+                };
+                self.layout.inline().push_str(sep);
+                self.layout.flush();
+            }
+            MdBlockDetail::Thead
+            | MdBlockDetail::Tbody
+            | MdBlockDetail::Th { .. }
+            | MdBlockDetail::Td { .. } => (),
+            _ => self.layout.flush(),
+        }
+    }
+
+    fn enter_link(&mut self, url: String) {
+        let start = self.layout.inline().len();
+        self.open_links.push((url, start));
+    }
+
+    /// Close a link or image, whose text is in the inline text since
+    /// its start.
+    fn leave_link(&mut self, is_image: bool) {
+        let (url, start) = match self.open_links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        let inline = self.layout.inline();
+        let text = inline.split_off(start.min(inline.len()));
+        let link = if is_image {
+            // Images have no alternative text in wiki markup.
+            format!("!{}!", url)
+        } else if text.is_empty() || text == url {
+            format!("[{}]", url)
+        } else {
+            format!("[{}|{}]", text, url)
+        };
+        inline.push_str(&link);
+    }
+}
+
+impl<T> MdDetailParser for Md2JiraRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let inline = self.layout.inline();
+        match detail {
+            MdSpanDetail::Em => inline.push('_'),
+            MdSpanDetail::Strong => inline.push('*'),
+            MdSpanDetail::Del => inline.push('-'),
+            MdSpanDetail::U => inline.push('+'),
+            MdSpanDetail::Code
+            | MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => inline.push_str("{{"),
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode())
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(target.decode())
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        let inline = self.layout.inline();
+        match spantype {
+            MdSpantype::Em => inline.push('_'),
+            MdSpantype::Strong => inline.push('*'),
+            MdSpantype::Del => inline.push('-'),
+            MdSpantype::U => inline.push('+'),
+            MdSpantype::Code
+            | MdSpantype::Latexmath
+            | MdSpantype::LatexmathDisplay => inline.push_str("}}"),
+            MdSpantype::A | MdSpantype::Wikilink => {
+                self.leave_link(false)
+            }
+            MdSpantype::Img => self.leave_link(true),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(code) = self.code.as_mut() {
+            code.push_str(text);
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => self.write_escaped("\u{fffd}"),
+            // A line break would end the table row or list item.
+            MdTexttype::Br
+                if self.in_table || !self.lists.is_empty() =>
+            {
+                self.layout.inline().push_str(" \\\\ ")
+            }
+            MdTexttype::Br => self.layout.inline().push('\n'),
+            MdTexttype::Softbr => self.layout.inline().push(' '),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2JiraRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2JiraRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2JiraRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn jira(userdata: T, options: Md2JiraOptions) -> Self {
+        Self(Md2JiraRenderer::new(userdata, options), PhantomData)
+    }
+}
@@ -0,0 +1,212 @@
+/// A JSON value, built by the JSON renderers and written out once the
+/// document is complete. Objects keep the order of their keys.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Int(i64),
+    Str(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn object() -> Self {
+        Json::Object(Vec::new())
+    }
+
+    /// Object with the `key`/`value` pairs.
+    pub fn with<V: Into<Json>>(
+        mut self,
+        key: &str,
+        value: V,
+    ) -> Self {
+        self.set(key, value);
+        self
+    }
+
+    /// Set `key` of an object, replacing its value if already set.
+    pub fn set<V: Into<Json>>(&mut self, key: &str, value: V) {
+        if let Json::Object(pairs) = self {
+            let value = value.into();
+            match pairs.iter_mut().find(|(k, _)| k == key) {
+                Some((_, v)) => *v = value,
+                None => pairs.push((key.to_owned(), value)),
+            }
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(pairs) => {
+                pairs.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Json> {
+        match self {
+            Json::Object(pairs) => pairs
+                .iter_mut()
+                .find(|(k, _)| k == key)
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Append to an array.
+    pub fn push<V: Into<Json>>(&mut self, value: V) {
+        if let Json::Array(items) = self {
+            items.push(value.into());
+        }
+    }
+
+    /// The text of the value, `indent` spaces per level, or on a single
+    /// line with 0.
+    pub fn dump(&self, indent: usize) -> String {
+        let mut out = String::new();
+        self.write(&mut out, indent, 0);
+        out
+    }
+
+    fn write(&self, out: &mut String, indent: usize, level: usize) {
+        let newline = |out: &mut String, level: usize| {
+            if indent > 0 {
+                out.push('\n');
+                out.push_str(&" ".repeat(indent * level));
+            }
+        };
+        match self {
+            Json::Null => out.push_str("null"),
+            Json::Bool(b) => {
+                out.push_str(if *b { "true" } else { "false" })
+            }
+            Json::Int(n) => out.push_str(&n.to_string()),
+            Json::Str(s) => write_str(out, s),
+            Json::Array(items) if items.is_empty() => {
+                out.push_str("[]")
+            }
+            Json::Array(items) => {
+                out.push('[');
+                for (idx, item) in items.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    newline(out, level + 1);
+                    item.write(out, indent, level + 1);
+                }
+                newline(out, level);
+                out.push(']');
+            }
+            Json::Object(pairs) if pairs.is_empty() => {
+                out.push_str("{}")
+            }
+            Json::Object(pairs) => {
+                out.push('{');
+                for (idx, (key, value)) in pairs.iter().enumerate() {
+                    if idx > 0 {
+                        out.push(',');
+                    }
+                    newline(out, level + 1);
+                    write_str(out, key);
+                    out.push(':');
+                    if indent > 0 {
+                        out.push(' ');
+                    }
+                    value.write(out, indent, level + 1);
+                }
+                newline(out, level);
+                out.push('}');
+            }
+        }
+    }
+}
+
+fn write_str(out: &mut String, s: &str) {
+    out.push('"');
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => {
+                out.push_str(&format!("\\u{:04x}", ch as u32))
+            }
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+}
+
+impl From<bool> for Json {
+    fn from(b: bool) -> Self {
+        Json::Bool(b)
+    }
+}
+
+impl From<i64> for Json {
+    fn from(n: i64) -> Self {
+        Json::Int(n)
+    }
+}
+
+impl From<u32> for Json {
+    fn from(n: u32) -> Self {
+        Json::Int(n as i64)
+    }
+}
+
+impl From<usize> for Json {
+    fn from(n: usize) -> Self {
+        Json::Int(n as i64)
+    }
+}
+
+impl From<&str> for Json {
+    fn from(s: &str) -> Self {
+        Json::Str(s.to_owned())
+    }
+}
+
+impl From<String> for Json {
+    fn from(s: String) -> Self {
+        Json::Str(s)
+    }
+}
+
+impl From<Vec<Json>> for Json {
+    fn from(items: Vec<Json>) -> Self {
+        Json::Array(items)
+    }
+}
+
+impl<T: Into<Json>> From<Option<T>> for Json {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Json::Null, Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write() {
+        let value = Json::object()
+            .with("a", "x\"\n\u{1}")
+            .with("b", vec![Json::from(1u32), Json::Null])
+            .with("c", Json::object());
+        assert_eq!(
+            value.dump(0),
+            r#"{"a":"x\"\n\u0001","b":[1,null],"c":{}}"#
+        );
+        assert_eq!(
+            value.dump(2),
+            "{\n  \"a\": \"x\\\"\\n\\u0001\",\n  \"b\": [\n    1,\n    \
+             null\n  ],\n  \"c\": {}\n}"
+        );
+    }
+}
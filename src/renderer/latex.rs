@@ -0,0 +1,450 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::TextTable;
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2LatexOptions {
+    listings: bool,
+    standalone: bool,
+}
+
+impl Md2LatexOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Output code blocks as `lstlisting` with the language of their
+    /// info string, instead of `verbatim`.
+    #[inline]
+    pub fn listings(mut self) -> Self {
+        self.listings = true;
+        self
+    }
+
+    /// Output a complete document, with the preamble loading the
+    /// packages the body needs, instead of a fragment to `\input`.
+    #[inline]
+    pub fn standalone(mut self) -> Self {
+        self.standalone = true;
+        self
+    }
+}
+
+const PREAMBLE: &str = "\\documentclass{article}\n\
+                        \\usepackage[T1]{fontenc}\n\
+                        \\usepackage[utf8]{inputenc}\n\
+                        \\usepackage{amsmath,amssymb}\n\
+                        \\usepackage{graphicx}\n\
+                        \\usepackage[normalem]{ulem}\n\
+                        \\usepackage{listings}\n\
+                        \\usepackage{hyperref}\n\n\
+                        \\begin{document}\n\n";
+
+const SECTIONS: [&str; 6] = [
+    "section",
+    "subsection",
+    "subsubsection",
+    "paragraph",
+    "subparagraph",
+    "subparagraph",
+];
+
+/// Counters of the nested `enumerate` levels.
+const ENUM_COUNTERS: [&str; 4] =
+    ["enumi", "enumii", "enumiii", "enumiv"];
+
+/// Renders Markdown as LaTeX, either a fragment of a document body or,
+/// with [`Md2LatexOptions::standalone`], a whole document.
+pub struct Md2LatexRenderer<T> {
+    pub options: Md2LatexOptions,
+    pub userdata: T,
+    /// The table being output, whose column specification is only
+    /// known once the cells of the first row are.
+    table: Option<TextTable>,
+    list_depth: usize,
+    enumerate_depth: usize,
+    in_code: bool,
+    in_code_span: bool,
+    in_math: bool,
+    image_nesting_level: usize,
+}
+
+impl<T> Md2LatexRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2LatexOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            table: None,
+            list_depth: 0,
+            enumerate_depth: 0,
+            in_code: false,
+            in_code_span: false,
+            in_math: false,
+            image_nesting_level: 0,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn write(&mut self, text: &str) {
+        match self.table.as_mut().and_then(TextTable::cell) {
+            Some(cell) => cell.push_str(text),
+            None => self.userdata.render_append(text),
+        }
+    }
+
+    fn write_escaped(&mut self, text: &str) {
+        let mut escaped = String::with_capacity(text.len());
+        for ch in text.chars() {
+            match ch {
+                '\\' => escaped.push_str("\\textbackslash{}"),
+                '^' => escaped.push_str("\\textasciicircum{}"),
+                '~' => escaped.push_str("\\textasciitilde{}"),
+                '{' | '}' | '$' | '&' | '#' | '%' | '_' => {
+                    escaped.push('\\');
+                    escaped.push(ch);
+                }
+                _ => escaped.push(ch),
+            }
+        }
+        self.write(&escaped);
+    }
+
+    /// Write a URL as an argument of `\href` or `\includegraphics`,
+    /// where `&` would also end a table cell.
+    fn write_url(&mut self, url: &str) {
+        let mut escaped = String::with_capacity(url.len());
+        for ch in url.chars() {
+            if let '#' | '%' | '&' | '_' | '\\' | '{' | '}' = ch {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+        }
+        self.write(&escaped);
+    }
+
+    /// Start a list, a nested one on a line of its own after the text
+    /// of its item.
+    fn enter_list(&mut self) {
+        if self.list_depth > 0 {
+            self.write("\n");
+        }
+        self.list_depth += 1;
+    }
+
+    /// End a list, a nested one followed by the end of its item.
+    fn leave_list(&mut self) {
+        self.list_depth -= 1;
+        if self.list_depth == 0 {
+            self.write("\n\n");
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                if self.options.standalone {
+                    self.write(PREAMBLE);
+                }
+            }
+            MdBlockDetail::Quote => self.write("\\begin{quote}\n"),
+            MdBlockDetail::Ul { .. } => {
+                self.enter_list();
+                self.write("\\begin{itemize}\n");
+            }
+            MdBlockDetail::Ol { start, .. } => {
+                self.enter_list();
+                self.write("\\begin{enumerate}\n");
+                let counter = ENUM_COUNTERS.get(self.enumerate_depth);
+                self.enumerate_depth += 1;
+                if let (Some(counter), true) = (counter, start != 1) {
+                    let set = format!(
+                        "\\setcounter{{{}}}{{{}}}\n",
+                        counter,
+                        start as i64 - 1
+                    );
+                    self.write(&set);
+                }
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => match (is_task, task_mark as u8) {
+                (false, _) => self.write("\\item "),
+                (true, b' ') => self.write("\\item[$\\square$] "),
+                (true, _) => self.write("\\item[$\\boxtimes$] "),
+            },
+            MdBlockDetail::Hr => {
+                self.write("\\noindent\\rule{\\linewidth}{0.4pt}\n\n")
+            }
+            MdBlockDetail::H { level } => {
+                let section = SECTIONS[(level as usize - 1).min(5)];
+                self.write(&format!("\\{}{{", section));
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                self.in_code = true;
+                if !self.options.listings {
+                    self.write("\\begin{verbatim}\n");
+                    return;
+                }
+                let lang = lang.as_str().unwrap_or("");
+                match lang {
+                    "" => self.write("\\begin{lstlisting}\n"),
+                    lang => self.write(&format!(
+                        "\\begin{{lstlisting}}[language={}]\n",
+                        lang
+                    )),
+                }
+            }
+            MdBlockDetail::Html | MdBlockDetail::P => (),
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+                if let MdBlockDetail::Th { .. } = detail {
+                    self.write("\\textbf{");
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                if self.options.standalone {
+                    self.write("\\end{document}\n");
+                }
+            }
+            MdBlockDetail::Quote => self.write("\\end{quote}\n\n"),
+            MdBlockDetail::Ul { .. } => {
+                self.write("\\end{itemize}");
+                self.leave_list();
+            }
+            MdBlockDetail::Ol { .. } => {
+                self.enumerate_depth -= 1;
+                self.write("\\end{enumerate}");
+                self.leave_list();
+            }
+            MdBlockDetail::Li { .. } => self.write("\n"),
+            MdBlockDetail::Hr
+            | MdBlockDetail::Thead
+            | MdBlockDetail::Tbody => (),
+            MdBlockDetail::H { .. } => self.write("}\n\n"),
+            MdBlockDetail::Code { .. } => {
+                self.in_code = false;
+                match self.options.listings {
+                    true => self.write("\\end{lstlisting}\n\n"),
+                    false => self.write("\\end{verbatim}\n\n"),
+                }
+            }
+            MdBlockDetail::Html => (),
+            MdBlockDetail::P => self.write("\n\n"),
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    self.write_table(table);
+                }
+            }
+            MdBlockDetail::Th { .. } => self.write("}"),
+            MdBlockDetail::Tr | MdBlockDetail::Td { .. } => (),
+        }
+    }
+
+    fn write_table(&mut self, table: TextTable) {
+        let spec: String = table
+            .aligns()
+            .iter()
+            .map(|align| match align {
+                MdAlign::Center => 'c',
+                MdAlign::Right => 'r',
+                MdAlign::Left | MdAlign::Default => 'l',
+            })
+            .collect();
+        let mut out = format!("\\begin{{tabular}}{{{}}}\n", spec);
+        for (idx, row) in table.rows().iter().enumerate() {
+            out.push_str(&row.join(" & "));
+            out.push_str(" \\\\\n");
+            if idx + 1 == table.head_rows() {
+                out.push_str("\\hline\n");
+            }
+        }
+        out.push_str("\\end{tabular}\n\n");
+        self.write(&out);
+    }
+}
+
+impl<T> MdDetailParser for Md2LatexRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if self.image_nesting_level > 0 {
+            if spantype == MdSpantype::Img {
+                self.image_nesting_level += 1;
+            }
+            return Ok(());
+        }
+        match detail {
+            MdSpanDetail::Em => self.write("\\emph{"),
+            MdSpanDetail::Strong => self.write("\\textbf{"),
+            MdSpanDetail::U => self.write("\\underline{"),
+            MdSpanDetail::Del => self.write("\\sout{"),
+            MdSpanDetail::Code => {
+                self.in_code_span = true;
+                self.write("\\texttt{");
+            }
+            MdSpanDetail::A { href, .. } => {
+                self.write("\\href{");
+                self.write_url(&href.decode());
+                self.write("}{");
+            }
+            MdSpanDetail::Img { src, .. } => {
+                // The alternative text has no place in the output.
+                self.image_nesting_level = 1;
+                self.write("\\includegraphics{");
+                self.write_url(&src.decode());
+                self.write("}");
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.write("\\href{");
+                self.write_url(&target.decode());
+                self.write("}{");
+            }
+            MdSpanDetail::Latexmath => {
+                self.in_math = true;
+                self.write("$");
+            }
+            MdSpanDetail::LatexmathDisplay => {
+                self.in_math = true;
+                self.write("\\[");
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if self.image_nesting_level > 0 {
+            if spantype == MdSpantype::Img {
+                self.image_nesting_level -= 1;
+            }
+            return Ok(());
+        }
+        match spantype {
+            MdSpantype::Latexmath => {
+                self.in_math = false;
+                self.write("$");
+            }
+            MdSpantype::LatexmathDisplay => {
+                self.in_math = false;
+                self.write("\\]");
+            }
+            MdSpantype::Code => {
+                self.in_code_span = false;
+                self.write("}");
+            }
+            MdSpantype::Img => (),
+            _ => self.write("}"),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if self.image_nesting_level > 0 {
+            return Ok(());
+        }
+        if self.in_code || self.in_math {
+            self.write(text);
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => self.write("\u{fffd}"),
+            MdTexttype::Br if self.table.is_some() => self.write(" "),
+            MdTexttype::Br => self.write("\\\\\n"),
+            MdTexttype::Softbr if self.in_code_span => {
+                self.write(" ")
+            }
+            MdTexttype::Softbr => self.write("\n"),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2LatexRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2LatexRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2LatexRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn latex(userdata: T, options: Md2LatexOptions) -> Self {
+        Self(Md2LatexRenderer::new(userdata, options), PhantomData)
+    }
+}
@@ -0,0 +1,477 @@
+use super::Md2TextUserdata;
+use crate::md4c::MdAlign;
+
+/// Display width of `ch` in a monospace terminal. Wide East Asian
+/// characters take two columns, combining marks and controls none.
+pub(crate) fn char_width(ch: char) -> usize {
+    match ch as u32 {
+        0..=0x1f | 0x7f..=0x9f => 0,
+        0x300..=0x36f | 0x200b..=0x200f | 0xfe00..=0xfe0f => 0,
+        0x1100..=0x115f
+        | 0x2e80..=0xa4cf
+        | 0xac00..=0xd7a3
+        | 0xf900..=0xfaff
+        | 0xfe30..=0xfe4f
+        | 0xff00..=0xff60
+        | 0xffe0..=0xffe6
+        | 0x1f300..=0x1f64f
+        | 0x1f900..=0x1f9ff
+        | 0x20000..=0x3fffd => 2,
+        _ => 1,
+    }
+}
+
+/// Display width of `text`, not counting ANSI escape sequences.
+pub(crate) fn str_width(text: &str) -> usize {
+    let mut width = 0;
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' && chars.peek() == Some(&']') {
+            // OSC sequences, e.g. hyperlinks, end with BEL or `ESC \`.
+            while let Some(ch) = chars.next() {
+                if ch == '\x07' || ch == '\x1b' {
+                    chars.next_if_eq(&'\\');
+                    break;
+                }
+            }
+        } else if ch == '\x1b' {
+            // CSI sequences end with a letter.
+            for ch in chars.by_ref() {
+                if ch.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += char_width(ch);
+        }
+    }
+    width
+}
+
+/// Greedy word wrap of `text` to `width` columns; `width == 0` only
+/// splits at the hard line breaks (`'\n'`). Words longer than `width`
+/// get a line of their own.
+pub(crate) fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for hard in text.split('\n') {
+        if width == 0 {
+            lines.push(hard.trim().to_owned());
+            continue;
+        }
+        let mut line = String::new();
+        let mut line_width = 0;
+        for word in hard.split(' ').filter(|w| !w.is_empty()) {
+            let word_width = str_width(word);
+            if line_width > 0 && line_width + 1 + word_width > width {
+                lines.push(std::mem::take(&mut line));
+                line_width = 0;
+            }
+            if line_width > 0 {
+                line.push(' ');
+                line_width += 1;
+            }
+            line.push_str(word);
+            line_width += word_width;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Slot of the attribute that the SGR parameter `param` sets, and
+/// whether it sets or clears it.
+fn sgr_slot(param: &str) -> Option<(usize, bool)> {
+    Some(match param.parse::<u32>().ok()? {
+        1 | 2 => (0, true),
+        22 => (0, false),
+        3 => (1, true),
+        23 => (1, false),
+        4 => (2, true),
+        24 => (2, false),
+        9 => (3, true),
+        29 => (3, false),
+        30..=37 | 90..=97 => (4, true),
+        39 => (4, false),
+        40..=47 | 100..=107 => (5, true),
+        49 => (5, false),
+        _ => return None,
+    })
+}
+
+/// End every wrapped line whose text is still styled with an SGR reset
+/// and start the next one with the active SGR attributes again, so that
+/// the prefixes of the containers stay unstyled and do not reset the
+/// style of the text either.
+pub(crate) fn carry_sgr(lines: &mut [String]) {
+    let mut active: [Option<String>; 6] = Default::default();
+    for line in lines.iter_mut() {
+        let params: Vec<&str> =
+            active.iter().flatten().map(String::as_str).collect();
+        let carried = match params.is_empty() {
+            true => String::new(),
+            false => format!("\x1b[{}m", params.join(";")),
+        };
+        let mut rest = line.as_str();
+        while let Some(start) = rest.find("\x1b[") {
+            let seq = &rest[start + 2..];
+            let end =
+                match seq.find(|c: char| c.is_ascii_alphabetic()) {
+                    Some(end) => end,
+                    None => break,
+                };
+            if seq[end..].starts_with('m') {
+                for param in seq[..end].split(';') {
+                    if param.is_empty() || param == "0" {
+                        active = Default::default();
+                    } else if let Some((slot, set)) = sgr_slot(param)
+                    {
+                        active[slot] = set.then(|| param.to_owned());
+                    }
+                }
+            }
+            rest = &seq[end + 1..];
+        }
+        if active.iter().any(Option::is_some) {
+            line.push_str("\x1b[0m");
+        }
+        line.insert_str(0, &carried);
+    }
+}
+
+/// Pad `text` with spaces to `width` columns according to `align`.
+pub(crate) fn pad(
+    text: &str,
+    width: usize,
+    align: MdAlign,
+) -> String {
+    let fill = width.saturating_sub(str_width(text));
+    let (left, right) = match align {
+        MdAlign::Right => (fill, 0),
+        MdAlign::Center => (fill / 2, fill - fill / 2),
+        MdAlign::Left | MdAlign::Default => (0, fill),
+    };
+    format!("{}{}{}", " ".repeat(left), text, " ".repeat(right))
+}
+
+struct Prefix {
+    first: String,
+    rest: String,
+    used: bool,
+}
+
+/// Block layout: collects the inline text of the current paragraph,
+/// wraps it to the width left by the enclosing containers, and puts
+/// blank lines between blocks. Finished lines accumulate in `out`.
+pub(crate) struct Layout {
+    width: usize,
+    prefixes: Vec<Prefix>,
+    inline: String,
+    blank: bool,
+    started: bool,
+    /// Whether each enclosing list is tight, with the number of
+    /// prefixes outside it, innermost last.
+    lists: Vec<(bool, usize)>,
+    pub out: String,
+    /// Applied to every wrapped line of inline text.
+    pub line_filter: Option<fn(&str) -> String>,
+}
+
+impl Layout {
+    pub fn new(width: usize) -> Self {
+        Self {
+            width,
+            prefixes: Vec::new(),
+            inline: String::new(),
+            blank: false,
+            started: false,
+            lists: Vec::new(),
+            out: String::new(),
+            line_filter: None,
+        }
+    }
+
+    /// Enter a container whose first line starts with `first` and
+    /// whose other lines with `rest`, e.g. a list item marker.
+    pub fn push_prefix(&mut self, first: &str, rest: &str) {
+        self.flush();
+        self.prefixes.push(Prefix {
+            first: first.to_owned(),
+            rest: rest.to_owned(),
+            used: false,
+        });
+    }
+
+    /// Leave the innermost container, giving it a line of its own if
+    /// it had no content.
+    pub fn pop_prefix(&mut self) {
+        self.flush();
+        if matches!(self.prefixes.last(), Some(p) if !p.used) {
+            self.line("");
+        }
+        self.prefixes.pop();
+    }
+
+    /// The inline text of the current paragraph.
+    pub fn inline(&mut self) -> &mut String {
+        &mut self.inline
+    }
+
+    /// Ask for a blank line before the next line, unless it is the
+    /// first line of a container.
+    pub fn blank(&mut self) {
+        self.flush();
+        if !matches!(self.prefixes.last(), Some(p) if !p.used) {
+            self.blank = true;
+        }
+    }
+
+    /// Enter a list, whose blocks are separated by blank lines unless
+    /// it is tight.
+    pub fn push_list(&mut self, is_tight: bool) {
+        self.lists.push((is_tight, self.prefixes.len()));
+    }
+
+    pub fn pop_list(&mut self) {
+        self.lists.pop();
+    }
+
+    /// Start a block, separated from the previous one by a blank line
+    /// unless both are in the same tight list item, and not in a
+    /// container inside it such as a quote.
+    pub fn separate(&mut self) {
+        match self.lists.last() {
+            Some(&(true, outside))
+                if self.prefixes.len() <= outside + 1 =>
+            {
+                self.flush()
+            }
+            _ => self.blank(),
+        }
+    }
+
+    /// Columns left for text by the enclosing containers, 0 for no
+    /// limit.
+    pub fn text_width(&self) -> usize {
+        match self.width {
+            0 => 0,
+            width => {
+                width.saturating_sub(self.prefix_width()).max(10)
+            }
+        }
+    }
+
+    /// Wrap and output the pending inline text, if any.
+    pub fn flush(&mut self) {
+        if self.inline.trim().is_empty() {
+            self.inline.clear();
+            return;
+        }
+        let inline = std::mem::take(&mut self.inline);
+        let mut lines = wrap(&inline, self.text_width());
+        carry_sgr(&mut lines);
+        for line in lines {
+            match self.line_filter {
+                Some(filter) => self.line(&filter(&line)),
+                None => self.line(&line),
+            }
+        }
+    }
+
+    /// Output one line as is, only prefixed.
+    pub fn line(&mut self, text: &str) {
+        if self.blank && self.started {
+            // Containers yet to start stay out of the blank line.
+            let rest: String = self
+                .prefixes
+                .iter()
+                .filter(|p| p.used)
+                .map(|p| p.rest.as_str())
+                .collect();
+            self.out.push_str(rest.trim_end());
+            self.out.push('\n');
+        }
+        self.blank = false;
+        self.started = true;
+
+        let mut line = String::new();
+        for prefix in self.prefixes.iter_mut() {
+            if prefix.used {
+                line.push_str(&prefix.rest);
+            } else {
+                line.push_str(&prefix.first);
+                prefix.used = true;
+            }
+        }
+        if text.is_empty() {
+            self.out.push_str(line.trim_end());
+        } else {
+            self.out.push_str(&line);
+            self.out.push_str(text);
+        }
+        self.out.push('\n');
+    }
+
+    /// Take the lines finished so far.
+    pub fn take(&mut self) -> String {
+        std::mem::take(&mut self.out)
+    }
+
+    /// Hand the lines finished so far to `userdata`.
+    pub fn emit(&mut self, userdata: &mut impl Md2TextUserdata) {
+        if !self.out.is_empty() {
+            userdata.render_append(&self.take());
+        }
+    }
+
+    fn prefix_width(&self) -> usize {
+        self.prefixes.iter().map(|p| str_width(&p.rest)).sum()
+    }
+}
+
+/// Where inline text goes: the `raw` text being collected, e.g. of a
+/// code block, else the current cell of `table`, else `inline`.
+pub(crate) fn sink<'a>(
+    raw: Option<&'a mut String>,
+    table: Option<&'a mut TextTable>,
+    inline: &'a mut String,
+) -> &'a mut String {
+    match raw.or_else(|| table.and_then(TextTable::cell)) {
+        Some(sink) => sink,
+        None => inline,
+    }
+}
+
+/// Cells of a table, laid out in aligned columns once complete.
+#[derive(Default)]
+pub(crate) struct TextTable {
+    aligns: Vec<MdAlign>,
+    rows: Vec<Vec<String>>,
+    head_rows: usize,
+}
+
+impl TextTable {
+    pub fn new(head_rows: usize) -> Self {
+        Self {
+            head_rows,
+            ..Default::default()
+        }
+    }
+
+    pub fn start_row(&mut self) {
+        self.rows.push(Vec::new());
+    }
+
+    /// Start a cell; its text is then appended to `cell()`.
+    pub fn start_cell(&mut self, align: MdAlign) {
+        if self.rows.is_empty() {
+            self.start_row();
+        }
+        let row = self.rows.last_mut().unwrap();
+        if self.aligns.len() <= row.len() {
+            self.aligns.push(align);
+        }
+        row.push(String::new());
+    }
+
+    pub fn cell(&mut self) -> Option<&mut String> {
+        self.rows.last_mut()?.last_mut()
+    }
+
+    /// The cells as collected, row by row.
+    pub fn rows(&self) -> &[Vec<String>] {
+        &self.rows
+    }
+
+    pub fn aligns(&self) -> &[MdAlign] {
+        &self.aligns
+    }
+
+    pub fn head_rows(&self) -> usize {
+        self.head_rows
+    }
+
+    /// Width of every column, at least `min`.
+    pub fn widths(&self, min: usize) -> Vec<usize> {
+        let mut widths = vec![min; self.aligns.len()];
+        for row in self.rows.iter() {
+            for (col, cell) in row.iter().enumerate() {
+                widths[col] = widths[col].max(str_width(cell.trim()));
+            }
+        }
+        widths
+    }
+
+    /// Every row with its cells padded to the column widths.
+    pub fn padded_rows(&self, min: usize) -> Vec<Vec<String>> {
+        let widths = self.widths(min);
+        self.rows
+            .iter()
+            .map(|row| {
+                (0..widths.len())
+                    .map(|col| {
+                        let cell =
+                            row.get(col).map_or("", |c| c.trim());
+                        pad(cell, widths[col], self.aligns[col])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Rows joined by `sep`, with a `rule` line under the header rows.
+    pub fn lines(
+        &self,
+        sep: &str,
+        rule: Option<char>,
+    ) -> Vec<String> {
+        let widths = self.widths(1);
+        let mut lines = Vec::new();
+        for (idx, row) in self.padded_rows(1).iter().enumerate() {
+            lines.push(row.join(sep).trim_end().to_owned());
+            // Also after a header without body rows.
+            if idx + 1 == self.head_rows {
+                if let Some(rule) = rule {
+                    let rule: Vec<String> = widths
+                        .iter()
+                        .map(|&w| rule.to_string().repeat(w))
+                        .collect();
+                    lines.push(rule.join(sep));
+                }
+            }
+        }
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wrap_and_width() {
+        assert_eq!(str_width("日本語 \x1b[1mbold\x1b[0m"), 11);
+        assert_eq!(
+            str_width("\x1b]8;;http://a.b\x1b\\ab\x1b]8;;\x1b\\"),
+            2
+        );
+        assert_eq!(
+            wrap("the quick brown fox\njumps", 10),
+            vec!["the quick", "brown fox", "jumps"]
+        );
+        assert_eq!(pad("ab", 5, MdAlign::Center), " ab  ");
+        let mut lines = vec![
+            "\x1b[1;35mquick".to_owned(),
+            "brown \x1b[3mfox".to_owned(),
+            "jumps\x1b[0m".to_owned(),
+        ];
+        carry_sgr(&mut lines);
+        assert_eq!(
+            lines,
+            vec![
+                "\x1b[1;35mquick\x1b[0m",
+                "\x1b[1;35mbrown \x1b[3mfox\x1b[0m",
+                "\x1b[1;3;35mjumps\x1b[0m",
+            ]
+        );
+    }
+}
@@ -0,0 +1,741 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{self, Layout, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug)]
+pub struct Md2MarkdownOptions {
+    width: usize,
+    bullet: char,
+    fence: char,
+}
+
+impl Md2MarkdownOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            width: 0,
+            bullet: '-',
+            fence: '`',
+        }
+    }
+
+    /// Re-wrap paragraphs to `width` columns. With 0, the default, the
+    /// line breaks of the input are kept.
+    #[inline]
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Marker of bullet lists: `-` (the default), `*` or `+`.
+    #[inline]
+    pub fn bullet(mut self, bullet: char) -> Self {
+        self.bullet = bullet;
+        self
+    }
+
+    /// Fence of code blocks: `` ` `` (the default) or `~`.
+    #[inline]
+    pub fn fence(mut self, fence: char) -> Self {
+        self.fence = fence;
+        self
+    }
+}
+
+impl Default for Md2MarkdownOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Stands for a space the wrapping must not break at, e.g. inside a
+/// code span, where a line break could start a new block.
+const KEEP: char = '\u{1}';
+
+struct List {
+    next: Option<u32>,
+    marker: char,
+    started: bool,
+}
+
+/// Renders the md4c events back to normalized Markdown: ATX headings,
+/// fenced code blocks, one list marker, inline links and aligned
+/// tables. The output parses to the same document as the input.
+pub struct Md2MarkdownRenderer<T> {
+    pub options: Md2MarkdownOptions,
+    pub userdata: T,
+    layout: Layout,
+    lists: Vec<List>,
+    /// Marker of the list closed right before, which a following list
+    /// must not reuse, or both would merge.
+    last_list: Option<char>,
+    table: Option<TextTable>,
+    raw: Option<String>,
+    code_info: Option<String>,
+    code_span: Option<String>,
+    pending: String,
+    /// Delimiters of the open emphasis spans.
+    emphasis: Vec<char>,
+    links: Vec<usize>,
+    heading: bool,
+    latexmath: bool,
+}
+
+impl<T> Md2MarkdownRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2MarkdownOptions) -> Self {
+        Self {
+            layout: Self::layout(&options),
+            options,
+            userdata,
+            lists: Vec::new(),
+            last_list: None,
+            table: None,
+            raw: None,
+            code_info: None,
+            code_span: None,
+            pending: String::new(),
+            emphasis: Vec::new(),
+            links: Vec::new(),
+            heading: false,
+            latexmath: false,
+        }
+    }
+
+    fn layout(options: &Md2MarkdownOptions) -> Layout {
+        let mut layout = Layout::new(options.width);
+        layout.line_filter = Some(escape_line_start);
+        layout
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        let latexmath =
+            MdParserFlags::new().permissive_latexmath_spans();
+        self.latexmath = parser_flags.0 & latexmath.0 != 0;
+        self.parse(input, parser_flags)
+    }
+
+    fn sink(&mut self) -> &mut String {
+        layout::sink(None, self.table.as_mut(), self.layout.inline())
+    }
+
+    /// A space that survives re-wrapping.
+    fn keep(&self) -> char {
+        if self.table.is_some() || self.heading {
+            ' '
+        } else {
+            KEEP
+        }
+    }
+
+    /// Escape and output the text collected so far. Text is escaped
+    /// in runs, as md4c may split e.g. an escaped `\&amp;` in pieces.
+    fn flush_text(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let text = std::mem::take(&mut self.pending);
+        let mut escaped = String::with_capacity(text.len());
+        for (off, ch) in text.char_indices() {
+            let escape = match ch {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '~'
+                | '|' => true,
+                '#' => self.heading,
+                '$' => self.latexmath,
+                '&' => is_entity(&text[off..]),
+                _ => false,
+            };
+            if escape {
+                escaped.push('\\');
+            }
+            escaped.push(ch);
+        }
+        self.sink().push_str(&escaped);
+    }
+
+    /// Start a block, after a blank line unless it goes on the tight
+    /// list item being output and can interrupt its text.
+    fn separate(&mut self, can_interrupt: bool) {
+        match can_interrupt {
+            true => self.layout.separate(),
+            false => self.layout.blank(),
+        }
+    }
+
+    fn emit(&mut self) {
+        let out = self.layout.take();
+        if !out.is_empty() {
+            self.userdata.render_append(&out.replace(KEEP, " "));
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        let last_list = self.last_list.take();
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Self::layout(&self.options);
+                self.lists.clear();
+            }
+            MdBlockDetail::Quote => {
+                self.separate(true);
+                self.layout.push_prefix("> ", "> ");
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                self.separate(true);
+                let bullet = self.options.bullet;
+                let marker = match last_list {
+                    Some(last) if last == bullet => {
+                        if bullet == '*' {
+                            '-'
+                        } else {
+                            '*'
+                        }
+                    }
+                    _ => bullet,
+                };
+                self.layout.push_list(is_tight);
+                self.lists.push(List {
+                    next: None,
+                    marker,
+                    started: false,
+                });
+            }
+            MdBlockDetail::Ol {
+                start, is_tight, ..
+            } => {
+                // Only lists starting at 1 may interrupt a paragraph.
+                self.separate(start == 1);
+                let marker = match last_list {
+                    Some('.') => ')',
+                    _ => '.',
+                };
+                self.layout.push_list(is_tight);
+                self.lists.push(List {
+                    next: Some(start),
+                    marker,
+                    started: false,
+                });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                // The list itself is separated from what comes before;
+                // a blank line there would make an outer list loose.
+                match self.lists.last_mut() {
+                    Some(list) if !list.started => {
+                        list.started = true
+                    }
+                    _ => self.separate(true),
+                }
+                let marker = match self.lists.last_mut() {
+                    Some(List {
+                        next: Some(next),
+                        marker,
+                        ..
+                    }) => {
+                        *next += 1;
+                        format!("{}{} ", *next - 1, marker)
+                    }
+                    Some(list) => format!("{} ", list.marker),
+                    None => format!("{} ", self.options.bullet),
+                };
+                let rest = " ".repeat(marker.len());
+                let mut first = marker;
+                if is_task {
+                    first.push('[');
+                    first.push(task_mark as u8 as char);
+                    first.push_str("] ");
+                }
+                self.layout.push_prefix(&first, &rest);
+            }
+            MdBlockDetail::Hr => {
+                self.separate(true);
+                self.layout.line("***");
+            }
+            MdBlockDetail::H { .. } => {
+                self.separate(true);
+                self.heading = true;
+            }
+            MdBlockDetail::Code { info, .. } => {
+                self.separate(true);
+                self.raw = Some(String::new());
+                self.code_info =
+                    Some(info.as_str().unwrap_or("").to_owned());
+            }
+            MdBlockDetail::Html => {
+                self.separate(true);
+                self.raw = Some(String::new());
+            }
+            MdBlockDetail::P => self.separate(true),
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.separate(false);
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        self.last_list = None;
+        match detail {
+            MdBlockDetail::Quote | MdBlockDetail::Li { .. } => {
+                self.layout.pop_prefix();
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.last_list = self.lists.pop().map(|l| l.marker);
+            }
+            MdBlockDetail::H { level } => {
+                self.heading = false;
+                let text = std::mem::take(self.layout.inline());
+                let text =
+                    text.replace("\\\n", " ").replace('\n', " ");
+                let mut line = "#".repeat(level as usize);
+                if !text.trim().is_empty() {
+                    line.push(' ');
+                    line.push_str(text.trim());
+                }
+                self.layout.line(&line);
+            }
+            MdBlockDetail::Code { .. } => {
+                let code = self.raw.take().unwrap_or_default();
+                let info = self.code_info.take().unwrap_or_default();
+                let fence = match self.options.fence {
+                    '`' if info.contains('`') => '~',
+                    fence => fence,
+                };
+                let fence = fence
+                    .to_string()
+                    .repeat(3.max(longest_run(&code, fence) + 1));
+                self.layout.line(&format!("{}{}", fence, info));
+                for line in code.lines() {
+                    self.layout.line(line);
+                }
+                self.layout.line(&fence);
+            }
+            MdBlockDetail::Html => {
+                let html = self.raw.take().unwrap_or_default();
+                for line in html.lines() {
+                    self.layout.line(line);
+                }
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    self.table_lines(&table);
+                }
+            }
+            _ => self.layout.flush(),
+        }
+    }
+
+    fn table_lines(&mut self, table: &TextTable) {
+        let widths = table.widths(3);
+        for (idx, row) in table.padded_rows(3).iter().enumerate() {
+            self.layout.line(&format!("| {} |", row.join(" | ")));
+            // Also after a header without body rows.
+            if idx + 1 == table.head_rows() {
+                let delim: Vec<String> = widths
+                    .iter()
+                    .zip(table.aligns())
+                    .map(|(&w, align)| match align {
+                        MdAlign::Left => {
+                            format!(":{}", "-".repeat(w - 1))
+                        }
+                        MdAlign::Right => {
+                            format!("{}:", "-".repeat(w - 1))
+                        }
+                        MdAlign::Center => {
+                            format!(":{}:", "-".repeat(w - 2))
+                        }
+                        MdAlign::Default => "-".repeat(w),
+                    })
+                    .collect();
+                self.layout
+                    .line(&format!("| {} |", delim.join(" | ")));
+            }
+        }
+    }
+
+    fn enter_inline(&mut self, detail: MdSpanDetail) {
+        match detail {
+            MdSpanDetail::Em => self.enter_emphasis(1),
+            MdSpanDetail::Strong => self.enter_emphasis(2),
+            MdSpanDetail::Del => self.sink().push_str("~~"),
+            MdSpanDetail::U => self.sink().push('_'),
+            MdSpanDetail::Code => {
+                self.code_span = Some(String::new())
+            }
+            MdSpanDetail::A { .. } => {
+                let sink = self.sink();
+                // Text `!` right before would make it an image.
+                if sink.ends_with('!') && !sink.ends_with("\\!") {
+                    sink.insert(sink.len() - 1, '\\');
+                }
+                let start = sink.len();
+                sink.push('[');
+                self.links.push(start);
+            }
+            MdSpanDetail::Img { .. } => {
+                let start = self.sink().len();
+                self.sink().push_str("![");
+                self.links.push(start);
+            }
+            MdSpanDetail::Latexmath => self.sink().push('$'),
+            MdSpanDetail::LatexmathDisplay => {
+                self.sink().push_str("$$")
+            }
+            MdSpanDetail::Wikilink { .. } => {
+                let start = self.sink().len();
+                self.sink().push_str("[[");
+                self.links.push(start);
+            }
+        }
+    }
+
+    fn leave_inline(&mut self, detail: MdSpanDetail) {
+        match detail {
+            MdSpanDetail::Em => self.leave_emphasis(1),
+            MdSpanDetail::Strong => self.leave_emphasis(2),
+            MdSpanDetail::Del => self.sink().push_str("~~"),
+            MdSpanDetail::U => self.sink().push('_'),
+            MdSpanDetail::Code => {
+                let code = self.code_span.take().unwrap_or_default();
+                self.code_span(&code);
+            }
+            MdSpanDetail::A { href, title } => {
+                let start = self.links.pop().unwrap_or(0);
+                let href = href.as_str().unwrap_or("");
+                let label = unescape(&self.sink()[start + 1..]);
+                let is_autolink = !label.is_empty()
+                    && !label.contains([' ', '<', '>'])
+                    && (label == href
+                        || (href.strip_prefix("mailto:")
+                            == Some(&label)
+                            && label.contains('@')));
+                if is_autolink && title.is_empty() {
+                    let sink = self.sink();
+                    sink.truncate(start);
+                    sink.push('<');
+                    sink.push_str(&label);
+                    sink.push('>');
+                } else {
+                    let target = self.target(href, &title);
+                    self.sink().push_str(&target);
+                }
+            }
+            MdSpanDetail::Img { src, title } => {
+                self.links.pop();
+                let target =
+                    self.target(src.as_str().unwrap_or(""), &title);
+                self.sink().push_str(&target);
+            }
+            MdSpanDetail::Latexmath => self.sink().push('$'),
+            MdSpanDetail::LatexmathDisplay => {
+                self.sink().push_str("$$")
+            }
+            MdSpanDetail::Wikilink { target } => {
+                let start = self.links.pop().unwrap_or(0) + 2;
+                let target = target.as_str().unwrap_or("");
+                let label = self.sink().split_off(start);
+                self.sink().push_str(target);
+                if unescape(&label) != target {
+                    self.sink().push('|');
+                    self.sink().push_str(&label);
+                }
+                self.sink().push_str("]]");
+            }
+        }
+    }
+
+    /// Open an emphasis with `*`, but an `<em>` right after another
+    /// `*` with `_`: `**` would be read as `<strong>` and `***` as
+    /// the `<em>` being the outer span.
+    fn enter_emphasis(&mut self, count: usize) {
+        let delim = match count == 1 && self.sink().ends_with('*') {
+            true => '_',
+            false => '*',
+        };
+        self.emphasis.push(delim);
+        self.sink().push_str(&delim.to_string().repeat(count));
+    }
+
+    fn leave_emphasis(&mut self, count: usize) {
+        let delim = self.emphasis.pop().unwrap_or('*');
+        self.sink().push_str(&delim.to_string().repeat(count));
+    }
+
+    fn code_span(&mut self, code: &str) {
+        let ticks = "`".repeat(longest_run(code, '`') + 1);
+        let pad = !code.bytes().all(|b| b == b' ')
+            && (code.starts_with([' ', '`'])
+                || code.ends_with([' ', '`']));
+        let keep = self.keep();
+        let sink = self.sink();
+        // Three backticks or more at the start of a line open a fence.
+        if ticks.len() >= 3 && sink.ends_with(' ') {
+            sink.pop();
+            sink.push(keep);
+        }
+        sink.push_str(&ticks);
+        if pad {
+            sink.push(' ');
+        }
+        sink.extend(code.chars().map(|ch| {
+            if ch == ' ' {
+                keep
+            } else {
+                ch
+            }
+        }));
+        if pad {
+            sink.push(' ');
+        }
+        sink.push_str(&ticks);
+    }
+
+    /// The `](destination "title")` part of a link or an image.
+    fn target(&self, dest: &str, title: &MdAttribute) -> String {
+        let keep = self.keep();
+        let mut target = String::from("](");
+        if dest.is_empty()
+            || dest
+                .contains(|ch: char| ch.is_whitespace() || ch == '<')
+        {
+            target.push('<');
+            for ch in dest.chars() {
+                match ch {
+                    '<' | '>' | '\\' => target.push('\\'),
+                    ' ' => {
+                        target.push(keep);
+                        continue;
+                    }
+                    _ => (),
+                }
+                target.push(ch);
+            }
+            target.push('>');
+        } else {
+            for ch in dest.chars() {
+                if let '(' | ')' | '\\' = ch {
+                    target.push('\\');
+                }
+                target.push(ch);
+            }
+        }
+        if let Some(title) = title.clone().as_str() {
+            target.push(keep);
+            target.push('"');
+            for ch in title.chars() {
+                match ch {
+                    '"' | '\\' => target.push('\\'),
+                    ' ' => {
+                        target.push(keep);
+                        continue;
+                    }
+                    _ => (),
+                }
+                target.push(ch);
+            }
+            target.push('"');
+        }
+        target.push(')');
+        target
+    }
+}
+
+/// Whether `text`, starting with `&`, would be read as an entity.
+fn is_entity(text: &str) -> bool {
+    let name: String = text[1..]
+        .chars()
+        .take_while(|ch| ch.is_ascii_alphanumeric() || *ch == '#')
+        .collect();
+    !name.is_empty() && text[1 + name.len()..].starts_with(';')
+}
+
+/// Length of the longest run of `ch` in `text`.
+fn longest_run(text: &str, ch: char) -> usize {
+    text.split(|c| c != ch)
+        .map(|run| run.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Remove the backslash escapes added by `flush_text`.
+fn unescape(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(mut ch) = chars.next() {
+        if ch == '\\' {
+            if let Some(&next) = chars.peek() {
+                if next.is_ascii_punctuation() {
+                    ch = next;
+                    chars.next();
+                }
+            }
+        }
+        res.push(ch);
+    }
+    res
+}
+
+/// Escape what would make a wrapped line of text start a block: a
+/// heading, quote, list item, thematic break or setext underline.
+fn escape_line_start(line: &str) -> String {
+    let bytes = line.as_bytes();
+    match bytes.first() {
+        Some(b'#' | b'>' | b'+' | b'-' | b'=') => {
+            format!("\\{}", line)
+        }
+        Some(b'0'..=b'9') => {
+            let digits =
+                bytes.iter().take_while(|b| b.is_ascii_digit());
+            let digits = digits.count();
+            match bytes.get(digits) {
+                Some(b'.' | b')') if digits <= 9 => {
+                    format!(
+                        "{}\\{}",
+                        &line[..digits],
+                        &line[digits..]
+                    )
+                }
+                _ => line.to_owned(),
+            }
+        }
+        _ => line.to_owned(),
+    }
+}
+
+impl<T> MdDetailParser for Md2MarkdownRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.flush_text();
+        self.enter(detail);
+        self.emit();
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.flush_text();
+        self.leave(detail);
+        self.emit();
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        self.flush_text();
+        self.enter_inline(detail);
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        self.flush_text();
+        self.leave_inline(detail);
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(raw) = self.raw.as_mut() {
+            raw.push_str(text);
+            return Ok(());
+        }
+        if let Some(code) = self.code_span.as_mut() {
+            match texttype {
+                MdTexttype::Softbr | MdTexttype::Br => code.push(' '),
+                _ => code.push_str(text),
+            }
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Normal => self.pending.push_str(text),
+            MdTexttype::Nullchar => self.pending.push('\u{fffd}'),
+            _ => {
+                self.flush_text();
+                let keep = self.keep();
+                let rewrap = self.options.width > 0 || self.heading;
+                let sink = self.sink();
+                match texttype {
+                    MdTexttype::Br => sink.push_str("\\\n"),
+                    MdTexttype::Softbr if rewrap => sink.push(' '),
+                    MdTexttype::Softbr => sink.push('\n'),
+                    MdTexttype::Html => {
+                        // Raw HTML starting a line could start an HTML
+                        // block instead.
+                        if sink.ends_with(' ') {
+                            sink.pop();
+                            sink.push(keep);
+                        }
+                        sink.push_str(text);
+                    }
+                    _ => sink.push_str(text),
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2MarkdownRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2MarkdownRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2MarkdownRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn markdown(
+        userdata: T,
+        options: Md2MarkdownOptions,
+    ) -> Self {
+        Self(Md2MarkdownRenderer::new(userdata, options), PhantomData)
+    }
+}
@@ -12,6 +12,11 @@ impl Md2HtmlFlags {
     const SKIP_UTF8_BOM: u32 = sys::MD_HTML_FLAG_SKIP_UTF8_BOM;
     const XHTML: u32 = sys::MD_HTML_FLAG_XHTML;
 
+    // Honoured by the Rust renderer only; md4c_html ignores them.
+    const SOURCEPOS: u32 = 0x0100;
+    const SOURCE_MAP: u32 = 0x0200;
+    const INTERACTIVE_TASKS: u32 = 0x0400;
+
     #[inline]
     pub fn new() -> Self {
         Md2HtmlFlags(0)
@@ -60,6 +65,46 @@ impl Md2HtmlFlags {
     pub fn has_xhtml(self) -> bool {
         self.0 & Self::XHTML != 0
     }
+
+    /// Add cmark-compatible `data-sourcepos="L:C-L:C"` attributes to
+    /// the opening tag of every block element.
+    #[inline]
+    pub fn sourcepos(mut self) -> Self {
+        self.0 |= Self::SOURCEPOS;
+        self
+    }
+
+    #[inline]
+    pub fn has_sourcepos(self) -> bool {
+        self.0 & Self::SOURCEPOS != 0
+    }
+
+    /// Record which input ranges every block and text was rendered
+    /// from, see `Md2HtmlRenderer::source_map`.
+    #[inline]
+    pub fn source_map(mut self) -> Self {
+        self.0 |= Self::SOURCE_MAP;
+        self
+    }
+
+    #[inline]
+    pub fn has_source_map(self) -> bool {
+        self.0 & Self::SOURCE_MAP != 0
+    }
+
+    /// Render task list checkboxes enabled, with the offset of their
+    /// mark in the source as `data-task-offset`, to be passed to
+    /// [`toggle_task`](crate::ootb::toggle_task).
+    #[inline]
+    pub fn interactive_tasks(mut self) -> Self {
+        self.0 |= Self::INTERACTIVE_TASKS;
+        self
+    }
+
+    #[inline]
+    pub fn has_interactive_tasks(self) -> bool {
+        self.0 & Self::INTERACTIVE_TASKS != 0
+    }
 }
 
 impl Default for Md2HtmlFlags {
@@ -1,5 +1,7 @@
 use super::{
-    md2htmlflags::Md2HtmlFlags, MdResult, Renderer, SpecifiedRenderer,
+    hooks::{MdHooked, MdHooks},
+    md2htmlflags::Md2HtmlFlags,
+    MdResult, Renderer, SpecifiedRenderer,
 };
 use std::{
     marker::PhantomData, num::NonZeroI32, os::raw::*,
@@ -10,7 +12,10 @@ mod sys {
     pub use crate::md4c_sys::md4c_html::*;
 }
 
-use crate::md4c::MdParserFlags;
+use crate::md4c::{
+    MdBlockDetail, MdBlocktype, MdParserFlags, MdSpanDetail,
+    MdSpantype, MdTexttype,
+};
 
 use self::sys::md_html;
 
@@ -28,13 +33,107 @@ unsafe extern "C" fn cb_process_output<T: Md2HtmlUserdata>(
     userdata.render_append(text);
 }
 
+#[inline]
+unsafe fn hook_result(
+    renderer: &mut Md2HtmlRenderer<impl Md2HtmlUserdata>,
+    res: Option<MdResult>,
+    use_default: *mut c_int,
+) -> c_int {
+    *use_default = match res {
+        Some(_) => renderer.use_default as c_int,
+        None => 1,
+    };
+    match res {
+        Some(Err(errcode)) => errcode.into(),
+        _ => 0,
+    }
+}
+
+unsafe extern "C" fn cb_hook_enter_block<T: Md2HtmlUserdata>(
+    blocktype: sys::MD_BLOCKTYPE,
+    detail: *mut c_void,
+    use_default: *mut c_int,
+    userdata: *mut c_void,
+) -> c_int {
+    let renderer = userdata as *mut Md2HtmlRenderer<T>;
+    let renderer = renderer.as_mut().unwrap_unchecked();
+    renderer.use_default = false;
+    let res = renderer.call_enter_block(blocktype.into(), detail);
+    hook_result(renderer, res, use_default)
+}
+
+unsafe extern "C" fn cb_hook_leave_block<T: Md2HtmlUserdata>(
+    blocktype: sys::MD_BLOCKTYPE,
+    detail: *mut c_void,
+    use_default: *mut c_int,
+    userdata: *mut c_void,
+) -> c_int {
+    let renderer = userdata as *mut Md2HtmlRenderer<T>;
+    let renderer = renderer.as_mut().unwrap_unchecked();
+    renderer.use_default = false;
+    let res = renderer.call_leave_block(blocktype.into(), detail);
+    hook_result(renderer, res, use_default)
+}
+
+unsafe extern "C" fn cb_hook_enter_span<T: Md2HtmlUserdata>(
+    spantype: sys::MD_SPANTYPE,
+    detail: *mut c_void,
+    use_default: *mut c_int,
+    userdata: *mut c_void,
+) -> c_int {
+    let renderer = userdata as *mut Md2HtmlRenderer<T>;
+    let renderer = renderer.as_mut().unwrap_unchecked();
+    renderer.use_default = false;
+    let res = renderer.call_enter_span(spantype.into(), detail);
+    hook_result(renderer, res, use_default)
+}
+
+unsafe extern "C" fn cb_hook_leave_span<T: Md2HtmlUserdata>(
+    spantype: sys::MD_SPANTYPE,
+    detail: *mut c_void,
+    use_default: *mut c_int,
+    userdata: *mut c_void,
+) -> c_int {
+    let renderer = userdata as *mut Md2HtmlRenderer<T>;
+    let renderer = renderer.as_mut().unwrap_unchecked();
+    renderer.use_default = false;
+    let res = renderer.call_leave_span(spantype.into(), detail);
+    hook_result(renderer, res, use_default)
+}
+
+unsafe extern "C" fn cb_hook_text<T: Md2HtmlUserdata>(
+    texttype: sys::MD_TEXTTYPE,
+    text: *const sys::MD_CHAR,
+    size: sys::MD_SIZE,
+    use_default: *mut c_int,
+    userdata: *mut c_void,
+) -> c_int {
+    let renderer = userdata as *mut Md2HtmlRenderer<T>;
+    let renderer = renderer.as_mut().unwrap_unchecked();
+    let text = slice_from_raw_parts(text as *const u8, size as usize);
+    let text = text.as_ref().unwrap_unchecked();
+    let text = std::str::from_utf8_unchecked(text);
+    renderer.use_default = false;
+    let res = renderer.call_text(texttype.into(), text);
+    hook_result(renderer, res, use_default)
+}
+
 pub trait Md2HtmlUserdata: Sized {
     fn render_append(&mut self, text: &str);
 }
 
+impl Md2HtmlUserdata for String {
+    #[inline]
+    fn render_append(&mut self, text: &str) {
+        self.push_str(text);
+    }
+}
+
 pub struct Md2HtmlRenderer<T> {
     renderer_flags: Md2HtmlFlags,
     pub userdata: T,
+    pub hooks: MdHooks<Self>,
+    use_default: bool,
 }
 
 impl<T> Md2HtmlRenderer<T>
@@ -45,30 +144,92 @@ where
         Self {
             renderer_flags,
             userdata,
+            hooks: MdHooks::new(),
+            use_default: false,
         }
     }
 
+    #[inline]
+    pub fn render_append(&mut self, text: &str) {
+        self.userdata.render_append(text)
+    }
+
+    /// Append `text` with the characters special to HTML escaped, the
+    /// way md4c escapes normal text.
+    pub fn render_html_escaped(&mut self, text: &str) {
+        let mut beg = 0;
+        for (off, ch) in text.char_indices() {
+            let esc = match ch {
+                '&' => "&amp;",
+                '<' => "&lt;",
+                '>' => "&gt;",
+                '"' => "&quot;",
+                _ => continue,
+            };
+            if off > beg {
+                self.render_append(&text[beg..off]);
+            }
+            self.render_append(esc);
+            beg = off + 1;
+        }
+        if beg < text.len() {
+            self.render_append(&text[beg..]);
+        }
+    }
+
+    /// Called from a hook, let md4c render the element (or text) as
+    /// usual once the hook returns, after whatever the hook output.
+    #[inline]
+    pub fn render_default(&mut self) {
+        self.use_default = true;
+    }
+
     pub fn render(
         &mut self,
         input: &str,
         parser_flags: &MdParserFlags,
     ) -> MdResult {
+        // md_html_ex() starts with no hooked element open, so must the
+        // hooks, even after a render aborted by a hook error.
+        self.hooks.reset_depth();
         let input_size = input.len() as sys::MD_SIZE;
         let input = input.as_ptr() as *const sys::MD_CHAR;
         let userdata = self as *mut Self as *mut c_void;
         let parser_flags = parser_flags.0;
         let renderer_flags = self.renderer_flags.0;
 
+        let block_mask = self.hooks.block_mask();
+        let span_mask = self.hooks.span_mask();
+        let hooks = sys::MD_HTML_HOOKS {
+            block_mask,
+            span_mask,
+            enter_block: Some(cb_hook_enter_block::<T>),
+            leave_block: Some(cb_hook_leave_block::<T>),
+            enter_span: Some(cb_hook_enter_span::<T>),
+            leave_span: Some(cb_hook_leave_span::<T>),
+            text: if self.hooks.has_text() {
+                Some(cb_hook_text::<T>)
+            } else {
+                None
+            },
+        };
+        let hooks = if block_mask == 0 && span_mask == 0 {
+            std::ptr::null()
+        } else {
+            &hooks as *const sys::MD_HTML_HOOKS
+        };
+
         let res;
 
         unsafe {
-            res = sys::md_html(
+            res = sys::md_html_ex(
                 input,
                 input_size,
                 Some(cb_process_output::<T>),
                 userdata,
                 parser_flags,
                 renderer_flags,
+                hooks,
             )
         };
 
@@ -80,6 +241,13 @@ where
     }
 }
 
+impl<T> MdHooked for Md2HtmlRenderer<T> {
+    #[inline]
+    fn hooks(&mut self) -> &mut MdHooks<Self> {
+        &mut self.hooks
+    }
+}
+
 impl<T> SpecifiedRenderer for Md2HtmlRenderer<T>
 where
     T: Md2HtmlUserdata,
@@ -111,13 +279,81 @@ where
     T: Md2HtmlUserdata,
 {
     pub fn html_c(userdata: T, renderer_flags: Md2HtmlFlags) -> Self {
-        Self(
-            Md2HtmlRenderer {
-                renderer_flags,
-                userdata,
-            },
-            PhantomData,
-        )
+        Self(Md2HtmlRenderer::new(userdata, renderer_flags), PhantomData)
+    }
+
+    /// Take over the opening of every block of `blocktype` while md4c
+    /// keeps rendering everything else natively. Nothing is rendered
+    /// for the block unless the closure outputs it or calls
+    /// [`Md2HtmlRenderer::render_default`].
+    pub fn on_enter_block<F>(
+        &mut self,
+        blocktype: MdBlocktype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdBlockDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_enter_block(blocktype, Box::new(hook));
+        self
+    }
+
+    /// Take over the closing of every block of `blocktype`.
+    pub fn on_leave_block<F>(
+        &mut self,
+        blocktype: MdBlocktype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdBlockDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_leave_block(blocktype, Box::new(hook));
+        self
+    }
+
+    /// Take over the opening of every span of `spantype`.
+    pub fn on_enter_span<F>(
+        &mut self,
+        spantype: MdSpantype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdSpanDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_enter_span(spantype, Box::new(hook));
+        self
+    }
+
+    /// Take over the closing of every span of `spantype`.
+    pub fn on_leave_span<F>(
+        &mut self,
+        spantype: MdSpantype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdSpanDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_leave_span(spantype, Box::new(hook));
+        self
+    }
+
+    /// Take over the text nested in the elements taken over above,
+    /// e.g. to highlight the contents of code blocks.
+    pub fn on_text<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: FnMut(
+                &mut Md2HtmlRenderer<T>,
+                MdTexttype,
+                &str,
+            ) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_text(Box::new(hook));
+        self
     }
 }
 
@@ -1,5 +1,6 @@
 use std::marker::PhantomData;
 
+use super::hooks::{MdHooked, MdHooks};
 use super::md2htmlflags::Md2HtmlFlags;
 use super::{Renderer, SpecifiedRenderer};
 use crate::md4c::*;
@@ -17,10 +18,18 @@ use utils::*;
 
 mod autoimpl;
 
+pub(crate) mod sourcepos;
+use sourcepos::SourcePosTracker;
+pub use sourcepos::{MdSourceMap, MdSourceMapEntry};
+
 pub struct Md2HtmlRenderer<T> {
     pub image_nesting_level: usize,
     pub flags: Md2HtmlFlags,
     pub userdata: T,
+    pub hooks: MdHooks<Self>,
+    sourcepos: Option<SourcePosTracker>,
+    source_map: Option<MdSourceMap>,
+    skipped_bom: usize,
 }
 
 #[allow(clippy::needless_range_loop)]
@@ -31,14 +40,45 @@ where
 {
     #[inline]
     pub fn render_append(&mut self, text: &str) {
+        if let Some(tracker) = self.sourcepos.as_mut() {
+            tracker.out_len += text.len();
+            if let Some((at, attr)) = tracker.take_pending(text) {
+                tracker.out_len += attr.len();
+                self.userdata.render_append(&text[..at]);
+                self.userdata.render_append(&attr);
+                self.userdata.render_append(&text[at..]);
+                return;
+            }
+        }
         self.userdata.render_append(text)
     }
 
     #[inline]
     pub fn render_verbatim(&mut self, data: &[u8]) {
+        if let Some(tracker) = self.sourcepos.as_mut() {
+            tracker.out_len += data.len();
+        }
         self.userdata.render_verbatim(data)
     }
 
+    /// The source map of the last `render` call, if the renderer has
+    /// [`Md2HtmlFlags::source_map`] set.
+    pub fn source_map(&self) -> Option<&MdSourceMap> {
+        self.source_map.as_ref()
+    }
+
+    pub fn take_source_map(&mut self) -> Option<MdSourceMap> {
+        self.source_map.take()
+    }
+
+    /// Turn an offset reported by md4c, e.g. `task_mark_offset`, into
+    /// an offset in the string given to `render`, which differ by the
+    /// UTF-8 BOM if it was skipped.
+    #[inline]
+    pub fn source_offset(&self, offset: MdOffset) -> usize {
+        offset as usize + self.skipped_bom
+    }
+
     pub fn render_html_escaped(&mut self, data: &[u8]) {
         let mut beg: usize = 0;
         let mut off: usize = 0;
@@ -222,6 +262,13 @@ where
     }
 }
 
+impl<T> MdHooked for Md2HtmlRenderer<T> {
+    #[inline]
+    fn hooks(&mut self) -> &mut MdHooks<Self> {
+        &mut self.hooks
+    }
+}
+
 impl<T> SpecifiedRenderer for Md2HtmlRenderer<T>
 where
     T: Md2HtmlUserdata,
@@ -253,14 +300,86 @@ where
     T: Md2HtmlUserdata,
 {
     pub fn html_rs(userdata: T, flags: Md2HtmlFlags) -> Self {
-        Self(
-            Md2HtmlRenderer {
-                image_nesting_level: 0,
-                flags,
-                userdata,
-            },
-            PhantomData,
-        )
+        Self(Md2HtmlRenderer::new(userdata, flags), PhantomData)
+    }
+
+    #[inline]
+    pub fn source_map(&self) -> Option<&MdSourceMap> {
+        self.0.source_map()
+    }
+
+    /// Override the opening of every block of `blocktype`. The closure
+    /// replaces [`Md2HtmlUserdata`]'s output for that block; call
+    /// [`Md2HtmlRenderer::enter_block_default`] from it to fall back.
+    pub fn on_enter_block<F>(
+        &mut self,
+        blocktype: MdBlocktype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdBlockDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_enter_block(blocktype, Box::new(hook));
+        self
+    }
+
+    /// Override the closing of every block of `blocktype`.
+    pub fn on_leave_block<F>(
+        &mut self,
+        blocktype: MdBlocktype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdBlockDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_leave_block(blocktype, Box::new(hook));
+        self
+    }
+
+    /// Override the opening of every span of `spantype`. Spans nested
+    /// in an image are not reported, as with the default output.
+    pub fn on_enter_span<F>(
+        &mut self,
+        spantype: MdSpantype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdSpanDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_enter_span(spantype, Box::new(hook));
+        self
+    }
+
+    /// Override the closing of every span of `spantype`.
+    pub fn on_leave_span<F>(
+        &mut self,
+        spantype: MdSpantype,
+        hook: F,
+    ) -> &mut Self
+    where
+        F: FnMut(&mut Md2HtmlRenderer<T>, &MdSpanDetail) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_leave_span(spantype, Box::new(hook));
+        self
+    }
+
+    /// Override the text nested in overridden elements, e.g. the
+    /// contents of a code block whose enter and leave are hooked.
+    pub fn on_text<F>(&mut self, hook: F) -> &mut Self
+    where
+        F: FnMut(
+                &mut Md2HtmlRenderer<T>,
+                MdTexttype,
+                &str,
+            ) -> MdResult
+            + 'static,
+    {
+        self.0.hooks.set_text(Box::new(hook));
+        self
     }
 
     pub fn render_with_logger(
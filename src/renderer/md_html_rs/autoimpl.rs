@@ -4,6 +4,20 @@ impl<T> MdEnterBlockCallback for Md2HtmlRenderer<T>
 where
     T: Md2HtmlUserdata,
 {
+    fn enter_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut std::os::raw::c_void,
+    ) -> MdResult {
+        if let Some(tracker) = self.sourcepos.as_mut() {
+            tracker.enter_block(blocktype);
+        }
+        match self.call_enter_block(blocktype, detail) {
+            Some(res) => res,
+            None => self.enter_block_fork(blocktype, detail),
+        }
+    }
+
     fn enter_doc(&mut self) -> MdResult {
         T::enter_doc(self)
     }
@@ -98,6 +112,21 @@ impl<T> MdLeaveBlockCallback for Md2HtmlRenderer<T>
 where
     T: Md2HtmlUserdata,
 {
+    fn leave_block(
+        &mut self,
+        blocktype: MdBlocktype,
+        detail: *mut std::os::raw::c_void,
+    ) -> MdResult {
+        let res = match self.call_leave_block(blocktype, detail) {
+            Some(res) => res,
+            None => self.leave_block_fork(blocktype, detail),
+        };
+        if let Some(tracker) = self.sourcepos.as_mut() {
+            tracker.leave_block();
+        }
+        res
+    }
+
     fn leave_doc(&mut self) -> MdResult {
         T::leave_doc(self)
     }
@@ -198,10 +227,24 @@ where
         detail: *mut std::os::raw::c_void,
     ) -> MdResult {
         if self.image_nesting_level > 0 {
-            Ok(())
-        } else {
-            self.enter_span_fork(spantype, detail)
+            // Count nested images, for leave_span to find the one
+            // closing the label.
+            if spantype == MdSpantype::Img {
+                self.image_nesting_level += 1;
+            }
+            return Ok(());
         }
+
+        let res = match self.call_enter_span(spantype, detail) {
+            Some(res) => res,
+            None => self.enter_span_fork(spantype, detail),
+        };
+
+        if spantype == MdSpantype::Img {
+            self.image_nesting_level += 1;
+        }
+
+        res
     }
 
     fn enter_em(&mut self) -> MdResult {
@@ -225,9 +268,7 @@ where
         src: MdAttribute,
         title: MdAttribute,
     ) -> MdResult {
-       let res = T::enter_img(self, src, title);
-       self.image_nesting_level += 1;
-       res
+        T::enter_img(self, src, title)
     }
 
     fn enter_ilcode(&mut self) -> MdResult {
@@ -264,18 +305,22 @@ where
         spantype: MdSpantype,
         detail: *mut std::os::raw::c_void,
     ) -> MdResult {
-        if self.image_nesting_level > 0 {
-            if self.image_nesting_level == 1
-                && spantype == MdSpantype::Img
-            {
-                self.leave_span_fork(spantype, detail)
-            } else {
-                Ok(())
+        // Spans inside an image label were neither rendered nor
+        // hooked on enter; only the image which opened it closes it.
+        let is_img = spantype == MdSpantype::Img;
+        if self.image_nesting_level > is_img as usize {
+            if is_img {
+                self.image_nesting_level -= 1;
             }
-        } else if spantype != MdSpantype::Img {
-            self.leave_span_fork(spantype, detail)
-        } else {
-            Ok(())
+            return Ok(());
+        }
+        if is_img {
+            self.image_nesting_level -= 1;
+        }
+
+        match self.call_leave_span(spantype, detail) {
+            Some(res) => res,
+            None => self.leave_span_fork(spantype, detail),
         }
     }
 
@@ -300,7 +345,6 @@ where
         src: MdAttribute,
         title: MdAttribute,
     ) -> MdResult {
-        self.image_nesting_level -= 1;
         T::leave_img(self, src, title)
     }
 
@@ -342,6 +386,20 @@ impl<T> MdTextCallback for Md2HtmlRenderer<T>
 where
     T: Md2HtmlUserdata,
 {
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        let out_start = self.sourcepos.as_ref().map(|t| t.out_len);
+        let res = match self.call_text(texttype, text) {
+            Some(res) => res,
+            None => self.text_fork(text, texttype),
+        };
+        if let (Some(tracker), Some(out_start)) =
+            (self.sourcepos.as_mut(), out_start)
+        {
+            tracker.text(text, out_start);
+        }
+        res
+    }
+
     fn text_normal(&mut self, text: &str) -> MdResult {
         T::text_normal(self, text)
     }
@@ -384,34 +442,268 @@ where
             image_nesting_level: 0,
             flags,
             userdata,
+            hooks: MdHooks::new(),
+            sourcepos: None,
+            source_map: None,
+            skipped_bom: 0,
+        }
+    }
+
+    /// Render the opening of a block exactly as it would be without
+    /// any override. Meant to be called from a hook.
+    pub fn enter_block_default(
+        &mut self,
+        detail: &MdBlockDetail,
+    ) -> MdResult {
+        match detail.clone() {
+            MdBlockDetail::Doc => T::enter_doc(self),
+            MdBlockDetail::Quote => T::enter_quote(self),
+            MdBlockDetail::Ul { is_tight, mark } => {
+                T::enter_ul(self, is_tight, mark)
+            }
+            MdBlockDetail::Ol {
+                start,
+                is_tight,
+                mark_delimiter,
+            } => T::enter_ol(self, start, is_tight, mark_delimiter),
+            MdBlockDetail::Li {
+                is_task,
+                task_mark,
+                task_mark_offset,
+            } => T::enter_li(
+                self,
+                is_task,
+                task_mark,
+                task_mark_offset,
+            ),
+            MdBlockDetail::Hr => T::enter_hr(self),
+            MdBlockDetail::H { level } => T::enter_h(self, level),
+            MdBlockDetail::Code {
+                info,
+                lang,
+                fence_char,
+            } => T::enter_code(self, info, lang, fence_char),
+            MdBlockDetail::Html => T::enter_html(self),
+            MdBlockDetail::P => T::enter_p(self),
+            MdBlockDetail::Table {
+                col_count,
+                head_row_count,
+                body_row_count,
+            } => T::enter_table(
+                self,
+                col_count,
+                head_row_count,
+                body_row_count,
+            ),
+            MdBlockDetail::Thead => T::enter_thead(self),
+            MdBlockDetail::Tbody => T::enter_tbody(self),
+            MdBlockDetail::Tr => T::enter_tr(self),
+            MdBlockDetail::Th { align } => T::enter_th(self, align),
+            MdBlockDetail::Td { align } => T::enter_td(self, align),
+        }
+    }
+
+    /// Render the closing of a block exactly as it would be without
+    /// any override. Meant to be called from a hook.
+    pub fn leave_block_default(
+        &mut self,
+        detail: &MdBlockDetail,
+    ) -> MdResult {
+        match detail.clone() {
+            MdBlockDetail::Doc => T::leave_doc(self),
+            MdBlockDetail::Quote => T::leave_quote(self),
+            MdBlockDetail::Ul { is_tight, mark } => {
+                T::leave_ul(self, is_tight, mark)
+            }
+            MdBlockDetail::Ol {
+                start,
+                is_tight,
+                mark_delimiter,
+            } => T::leave_ol(self, start, is_tight, mark_delimiter),
+            MdBlockDetail::Li {
+                is_task,
+                task_mark,
+                task_mark_offset,
+            } => T::leave_li(
+                self,
+                is_task,
+                task_mark,
+                task_mark_offset,
+            ),
+            MdBlockDetail::Hr => T::leave_hr(self),
+            MdBlockDetail::H { level } => T::leave_h(self, level),
+            MdBlockDetail::Code {
+                info,
+                lang,
+                fence_char,
+            } => T::leave_code(self, info, lang, fence_char),
+            MdBlockDetail::Html => T::leave_html(self),
+            MdBlockDetail::P => T::leave_p(self),
+            MdBlockDetail::Table {
+                col_count,
+                head_row_count,
+                body_row_count,
+            } => T::leave_table(
+                self,
+                col_count,
+                head_row_count,
+                body_row_count,
+            ),
+            MdBlockDetail::Thead => T::leave_thead(self),
+            MdBlockDetail::Tbody => T::leave_tbody(self),
+            MdBlockDetail::Tr => T::leave_tr(self),
+            MdBlockDetail::Th { align } => T::leave_th(self, align),
+            MdBlockDetail::Td { align } => T::leave_td(self, align),
+        }
+    }
+
+    /// Render text exactly as it would be without any override. Meant
+    /// to be called from a hook.
+    pub fn text_default(
+        &mut self,
+        texttype: MdTexttype,
+        text: &str,
+    ) -> MdResult {
+        self.text_fork(text, texttype)
+    }
+
+    /// Render the opening of a span exactly as it would be without any
+    /// override. Meant to be called from a hook.
+    pub fn enter_span_default(
+        &mut self,
+        detail: &MdSpanDetail,
+    ) -> MdResult {
+        match detail.clone() {
+            MdSpanDetail::Em => T::enter_em(self),
+            MdSpanDetail::Strong => T::enter_strong(self),
+            MdSpanDetail::A { href, title } => {
+                T::enter_a(self, href, title)
+            }
+            MdSpanDetail::Img { src, title } => {
+                T::enter_img(self, src, title)
+            }
+            MdSpanDetail::Code => T::enter_ilcode(self),
+            MdSpanDetail::Del => T::enter_del(self),
+            MdSpanDetail::Latexmath => T::enter_latexmath(self),
+            MdSpanDetail::LatexmathDisplay => {
+                T::enter_latexmath_display(self)
+            }
+            MdSpanDetail::Wikilink { target } => {
+                T::enter_wikilink(self, target)
+            }
+            MdSpanDetail::U => T::enter_u(self),
         }
     }
 
+    /// Render the closing of a span exactly as it would be without any
+    /// override. Meant to be called from a hook.
+    pub fn leave_span_default(
+        &mut self,
+        detail: &MdSpanDetail,
+    ) -> MdResult {
+        match detail.clone() {
+            MdSpanDetail::Em => T::leave_em(self),
+            MdSpanDetail::Strong => T::leave_strong(self),
+            MdSpanDetail::A { href, title } => {
+                T::leave_a(self, href, title)
+            }
+            MdSpanDetail::Img { src, title } => {
+                T::leave_img(self, src, title)
+            }
+            MdSpanDetail::Code => T::leave_ilcode(self),
+            MdSpanDetail::Del => T::leave_del(self),
+            MdSpanDetail::Latexmath => T::leave_latexmath(self),
+            MdSpanDetail::LatexmathDisplay => {
+                T::leave_latexmath_display(self)
+            }
+            MdSpanDetail::Wikilink { target } => {
+                T::leave_wikilink(self, target)
+            }
+            MdSpanDetail::U => T::leave_u(self),
+        }
+    }
+
+    /// Set up the `data-sourcepos` attributes and the source map for
+    /// `input`, BOM already skipped, if the flags ask for them.
+    fn track_sourcepos(
+        &mut self,
+        input: &str,
+        bom: usize,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.source_map = None;
+        self.sourcepos = None;
+        let sourcepos = self.flags.has_sourcepos();
+        let source_map = self.flags.has_source_map();
+        if sourcepos || source_map {
+            self.sourcepos = Some(SourcePosTracker::new(
+                input,
+                bom,
+                parser_flags,
+                sourcepos,
+                source_map,
+            )?);
+        }
+        Ok(())
+    }
+
+    fn finish_sourcepos(&mut self, input: &str) {
+        if let Some(tracker) = self.sourcepos.take() {
+            self.source_map = tracker.into_source_map(input);
+        }
+    }
+
+    /// Start afresh, even after a render aborted by a hook error.
+    fn reset(&mut self) {
+        self.image_nesting_level = 0;
+        self.hooks.reset_depth();
+    }
+
     fn skip_bom(&mut self, input: &mut &str) {
+        self.skipped_bom = 0;
         if self.flags.has_skip_utf8_bom() {
             let data = input.as_bytes();
             static BOM: &[u8; 3] = &[0xef, 0xbb, 0xbf];
             if data.len() > BOM.len() && &data[0..3] == BOM {
                 *input = &input[3..];
+                self.skipped_bom = 3;
             }
         }
     }
 
     pub fn render(
         &mut self,
-        mut input: &str,
+        input: &str,
         parser_flags: &MdParserFlags,
     ) -> MdResult {
-        self.skip_bom(&mut input);
-        self.parse(input, parser_flags)
+        self.reset();
+        let mut body = input;
+        self.skip_bom(&mut body);
+        self.track_sourcepos(
+            body,
+            input.len() - body.len(),
+            parser_flags,
+        )?;
+        let res = self.parse(body, parser_flags);
+        self.finish_sourcepos(input);
+        res
     }
 
     pub fn render_with_logger(
         &mut self,
-        mut input: &str,
+        input: &str,
         parser_flags: &MdParserFlags,
     ) -> MdResult {
-        self.skip_bom(&mut input);
-        self.parse_with_logger(input, parser_flags)
+        self.reset();
+        let mut body = input;
+        self.skip_bom(&mut body);
+        self.track_sourcepos(
+            body,
+            input.len() - body.len(),
+            parser_flags,
+        )?;
+        let res = self.parse_with_logger(body, parser_flags);
+        self.finish_sourcepos(input);
+        res
     }
 }
@@ -0,0 +1,178 @@
+use crate::md4c::*;
+use crate::renderer::sourcepos::{
+    block_ranges, line_col, line_starts,
+};
+use std::num::NonZeroI32;
+use std::ops::Range;
+
+/// One link between a byte range of the rendered output and the byte
+/// range of the input it was rendered from. Block entries cover the
+/// whole element, text entries a single text slice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MdSourceMapEntry {
+    pub output: Range<usize>,
+    pub input: Range<usize>,
+    /// `None` for text entries.
+    pub blocktype: Option<MdBlocktype>,
+}
+
+/// Output-to-input mapping produced by the Rust HTML renderer when
+/// [`Md2HtmlFlags::source_map`](super::Md2HtmlFlags::source_map) is
+/// set. Offsets are in bytes, the input ones relative to the string
+/// given to `render` (a skipped BOM included), the output ones
+/// relative to the first byte rendered by that call.
+#[derive(Debug, Clone, Default)]
+pub struct MdSourceMap {
+    entries: Vec<MdSourceMapEntry>,
+    line_starts: Vec<usize>,
+}
+
+impl MdSourceMap {
+    /// All entries, ordered by the end of their output range.
+    pub fn entries(&self) -> &[MdSourceMapEntry] {
+        &self.entries
+    }
+
+    /// The innermost entry whose output range contains `offset`.
+    pub fn find(&self, offset: usize) -> Option<&MdSourceMapEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.output.contains(&offset))
+            .min_by_key(|entry| entry.output.len())
+    }
+
+    /// 1-based line and byte column of an input offset.
+    pub fn line_col(&self, offset: usize) -> (usize, usize) {
+        line_col(&self.line_starts, offset)
+    }
+}
+
+/// Render-time state of the `data-sourcepos` attributes and of the
+/// source map.
+pub(crate) struct SourcePosTracker {
+    input: (usize, usize),
+    bom: usize,
+    blocks: Vec<Range<usize>>,
+    next_block: usize,
+    open: Vec<(usize, MdBlocktype, usize)>,
+    line_starts: Vec<usize>,
+    sourcepos: bool,
+    map: Option<Vec<MdSourceMapEntry>>,
+    pending: Option<String>,
+    pub out_len: usize,
+}
+
+impl SourcePosTracker {
+    /// Run the first pass over `input`, which is what is left of the
+    /// string passed to `render` once `bom` bytes were skipped.
+    pub fn new(
+        input: &str,
+        bom: usize,
+        parser_flags: &MdParserFlags,
+        sourcepos: bool,
+        source_map: bool,
+    ) -> Result<Self, NonZeroI32> {
+        Ok(Self {
+            input: (input.as_ptr() as usize, input.len()),
+            bom,
+            blocks: block_ranges(input, parser_flags)?,
+            next_block: 0,
+            open: Vec::new(),
+            line_starts: line_starts(input),
+            sourcepos,
+            map: source_map.then(Vec::new),
+            pending: None,
+            out_len: 0,
+        })
+    }
+
+    pub fn enter_block(&mut self, blocktype: MdBlocktype) {
+        let slot = self.next_block;
+        self.next_block += 1;
+        self.open.push((slot, blocktype, self.out_len));
+        self.pending = None;
+
+        let range = match self.blocks.get(slot) {
+            Some(range) => range.clone(),
+            None => return,
+        };
+        if self.sourcepos
+            && !matches!(
+                blocktype,
+                MdBlocktype::Doc | MdBlocktype::Html
+            )
+        {
+            let (l1, c1) = line_col(&self.line_starts, range.start);
+            let (l2, c2) = line_col(
+                &self.line_starts,
+                range.end.max(range.start + 1) - 1,
+            );
+            let c1 = if l1 == 1 { c1 + self.bom } else { c1 };
+            let c2 = if l2 == 1 { c2 + self.bom } else { c2 };
+            self.pending = Some(format!(
+                " data-sourcepos=\"{}:{}-{}:{}\"",
+                l1, c1, l2, c2
+            ));
+        }
+    }
+
+    pub fn leave_block(&mut self) {
+        self.pending = None;
+        if let Some((slot, blocktype, out_start)) = self.open.pop() {
+            let (map, range) =
+                match (&mut self.map, self.blocks.get(slot)) {
+                    (Some(map), Some(range)) => (map, range),
+                    _ => return,
+                };
+            map.push(MdSourceMapEntry {
+                output: out_start..self.out_len,
+                input: range.start + self.bom..range.end + self.bom,
+                blocktype: Some(blocktype),
+            });
+        }
+    }
+
+    pub fn text(&mut self, text: &str, out_start: usize) {
+        self.pending = None;
+        let (base, len) = self.input;
+        let ptr = text.as_ptr() as usize;
+        if let Some(map) = &mut self.map {
+            if ptr >= base && ptr + text.len() <= base + len {
+                let offset = ptr - base + self.bom;
+                map.push(MdSourceMapEntry {
+                    output: out_start..self.out_len,
+                    input: offset..offset + text.len(),
+                    blocktype: None,
+                });
+            }
+        }
+    }
+
+    /// Where to insert the pending attribute into `text`, i.e. after
+    /// the name of its leading tag.
+    pub fn take_pending(
+        &mut self,
+        text: &str,
+    ) -> Option<(usize, String)> {
+        let bytes = text.as_bytes();
+        if self.pending.is_none()
+            || bytes.len() < 2
+            || bytes[0] != b'<'
+            || !bytes[1].is_ascii_alphabetic()
+        {
+            return None;
+        }
+        let at = 1 + bytes[1..]
+            .iter()
+            .take_while(|b| b.is_ascii_alphanumeric())
+            .count();
+        self.pending.take().map(|attr| (at, attr))
+    }
+
+    pub fn into_source_map(self, input: &str) -> Option<MdSourceMap> {
+        self.map.map(|entries| MdSourceMap {
+            entries,
+            line_starts: line_starts(input),
+        })
+    }
+}
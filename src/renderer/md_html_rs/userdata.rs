@@ -54,7 +54,16 @@ pub trait Md2HtmlUserdata: Sized {
         task_mark_offset: MdOffset,
     ) -> MdResult {
         if is_task {
-            renderer.render_append("<li class=\"task-list-item\"><input type=\"checkbox\" class=\"task-list-item-checkbox\" disabled");
+            renderer.render_append("<li class=\"task-list-item\"><input type=\"checkbox\" class=\"task-list-item-checkbox\"");
+            if renderer.flags.has_interactive_tasks() {
+                let offset = renderer.source_offset(task_mark_offset);
+                renderer.render_append(&format!(
+                    " data-task-offset=\"{}\"",
+                    offset
+                ));
+            } else {
+                renderer.render_append(" disabled");
+            }
             if task_mark as u8 == b'x' || task_mark as u8 == b'X' {
                 renderer.render_append(" checked");
             }
@@ -550,3 +559,10 @@ pub trait Md2HtmlUserdata: Sized {
         // Do nothing
     }
 }
+
+impl Md2HtmlUserdata for String {
+    #[inline]
+    fn render_append(&mut self, text: &str) {
+        self.push_str(text);
+    }
+}
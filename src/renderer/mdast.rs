@@ -0,0 +1,539 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::json::Json;
+use super::sourcepos::{block_ranges, line_char_col, line_starts};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2MdastOptions {
+    position: bool,
+    pretty: bool,
+}
+
+impl Md2MdastOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give every node its `position` in the input. Block positions
+    /// come from a first pass over the input, as md4c does not report
+    /// them; inline containers span the content of their children.
+    #[inline]
+    pub fn position(mut self) -> Self {
+        self.position = true;
+        self
+    }
+
+    /// Indent the JSON output, instead of writing it on one line.
+    #[inline]
+    pub fn pretty(mut self) -> Self {
+        self.pretty = true;
+        self
+    }
+}
+
+/// A node being built, and its children with their input ranges.
+struct Node {
+    json: Json,
+    children: Vec<(Json, Option<Range<usize>>)>,
+    /// Text of the literal nodes, e.g. `code`.
+    value: Option<String>,
+    range: Option<Range<usize>>,
+    /// A paragraph of a tight list item, which md4c does not report.
+    implicit: bool,
+}
+
+impl Node {
+    fn new(kind: &str) -> Self {
+        Self {
+            json: Json::object().with("type", kind),
+            children: Vec::new(),
+            value: None,
+            range: None,
+            implicit: false,
+        }
+    }
+
+    fn literal(kind: &str) -> Self {
+        Self {
+            value: Some(String::new()),
+            ..Self::new(kind)
+        }
+    }
+
+    fn kind(&self) -> &Json {
+        self.json.get("type").unwrap_or(&Json::Null)
+    }
+}
+
+/// Serializes the document as JSON in the mdast format of the unified
+/// ecosystem, e.g. for remark plugins.
+pub struct Md2MdastRenderer<T> {
+    pub options: Md2MdastOptions,
+    pub userdata: T,
+    stack: Vec<Node>,
+    blocks: Vec<Range<usize>>,
+    next_block: usize,
+    line_starts: Vec<usize>,
+    /// The input, whose characters the columns count.
+    source: String,
+    /// Address and length of the input, to find the text slices in it.
+    input: (usize, usize),
+}
+
+impl<T> Md2MdastRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2MdastOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            stack: Vec::new(),
+            blocks: Vec::new(),
+            next_block: 0,
+            line_starts: Vec::new(),
+            source: String::new(),
+            input: (0, 0),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.stack.clear();
+        self.next_block = 0;
+        if self.options.position {
+            self.blocks = block_ranges(input, parser_flags)?;
+            self.line_starts = line_starts(input);
+            self.source = input.to_owned();
+            self.input = (input.as_ptr() as usize, input.len());
+        }
+        self.parse(input, parser_flags)
+    }
+
+    fn push(&mut self, mut node: Node, is_block: bool) {
+        if is_block && self.options.position {
+            node.range = self.blocks.get(self.next_block).cloned();
+            self.next_block += 1;
+        }
+        self.stack.push(node);
+    }
+
+    /// Finish the innermost node and add it to its parent, or output it
+    /// if it is the root.
+    fn pop(&mut self) {
+        let mut node = match self.stack.pop() {
+            Some(node) => node,
+            None => return,
+        };
+        let range = node.range.take().or_else(|| {
+            node.children
+                .iter()
+                .fold(None, |acc, c| union(acc, c.1.clone()))
+        });
+        if let Some(value) = node.value.take() {
+            node.json.set("value", value);
+        } else if node.kind() == &Json::from("image") {
+            let alt = plain_text(node.children.iter().map(|c| &c.0));
+            node.json.set("alt", alt);
+        } else if node.kind() == &Json::from("thematicBreak") {
+            // A void node, as `break`.
+        } else {
+            let children: Vec<Json> = node
+                .children
+                .into_iter()
+                .map(|(mut child, range)| {
+                    // Text and break nodes have no position yet.
+                    if let (Some(range), None) =
+                        (range, child.get("position"))
+                    {
+                        child.set("position", self.position(&range));
+                    }
+                    child
+                })
+                .collect();
+            node.json.set("children", children);
+        }
+        if let Some(range) = &range {
+            node.json.set("position", self.position(range));
+        }
+        match self.stack.last_mut() {
+            Some(parent) => parent.children.push((node.json, range)),
+            None => {
+                let indent = if self.options.pretty { 2 } else { 0 };
+                let mut out = node.json.dump(indent);
+                out.push('\n');
+                self.userdata.render_append(&out);
+            }
+        }
+    }
+
+    fn position(&self, range: &Range<usize>) -> Json {
+        let point = |offset: usize| {
+            let (line, column) = line_char_col(
+                &self.source,
+                &self.line_starts,
+                offset,
+            );
+            Json::object()
+                .with("line", line)
+                .with("column", column)
+                .with("offset", offset)
+        };
+        Json::object()
+            .with("start", point(range.start))
+            .with("end", point(range.end))
+    }
+
+    /// Input range of `text`, if it is a slice of the input.
+    fn text_range(&self, text: &str) -> Option<Range<usize>> {
+        let (base, len) = self.input;
+        let ptr = text.as_ptr() as usize;
+        if self.options.position
+            && ptr >= base
+            && ptr + text.len() <= base + len
+        {
+            Some(ptr - base..ptr - base + text.len())
+        } else {
+            None
+        }
+    }
+
+    /// Add text to the innermost node, extending its last child if of
+    /// the same `kind`, e.g. consecutive text slices.
+    fn add_text(
+        &mut self,
+        kind: &str,
+        text: &str,
+        range: Option<Range<usize>>,
+    ) {
+        let node = match self.stack.last_mut() {
+            Some(node) => node,
+            None => return,
+        };
+        if let Some((last, last_range)) = node.children.last_mut() {
+            if last.get("type") == Some(&Json::from(kind)) {
+                if let Some(Json::Str(value)) = last.get_mut("value")
+                {
+                    value.push_str(text);
+                }
+                *last_range = union(last_range.take(), range);
+                return;
+            }
+        }
+        let json =
+            Json::object().with("type", kind).with("value", text);
+        node.children.push((json, range));
+    }
+
+    /// Wrap the inline content of tight list items in a paragraph, as
+    /// mdast always does.
+    fn inline_parent(&mut self) {
+        let in_item = matches!(
+            self.stack.last(),
+            Some(node) if node.kind() == &Json::from("listItem")
+        );
+        if in_item {
+            let mut paragraph = Node::new("paragraph");
+            paragraph.implicit = true;
+            self.stack.push(paragraph);
+        }
+    }
+
+    fn close_implicit(&mut self) {
+        if matches!(self.stack.last(), Some(node) if node.implicit) {
+            self.pop();
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        self.close_implicit();
+        let node = match detail {
+            MdBlockDetail::Doc => Node::new("root"),
+            MdBlockDetail::Quote => Node::new("blockquote"),
+            MdBlockDetail::Ul { is_tight, .. } => {
+                let mut node = Node::new("list");
+                node.json.set("ordered", false);
+                node.json.set("start", Json::Null);
+                node.json.set("spread", !is_tight);
+                node
+            }
+            MdBlockDetail::Ol {
+                start, is_tight, ..
+            } => {
+                let mut node = Node::new("list");
+                node.json.set("ordered", true);
+                node.json.set("start", start);
+                node.json.set("spread", !is_tight);
+                node
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                let spread = self
+                    .stack
+                    .last()
+                    .and_then(|list| list.json.get("spread"))
+                    .cloned()
+                    .unwrap_or(Json::Bool(false));
+                let checked =
+                    is_task.then_some(task_mark as u8 != b' ');
+                let mut node = Node::new("listItem");
+                node.json.set("spread", spread);
+                node.json.set("checked", checked);
+                node
+            }
+            MdBlockDetail::Hr => Node::new("thematicBreak"),
+            MdBlockDetail::H { level } => {
+                let mut node = Node::new("heading");
+                node.json.set("depth", level);
+                node
+            }
+            MdBlockDetail::Code { info, lang, .. } => {
+                let info = info.decode();
+                let lang = lang.decode();
+                let meta = info[lang.len().min(info.len())..].trim();
+                let mut node = Node::literal("code");
+                node.json.set(
+                    "lang",
+                    (!lang.is_empty()).then(|| lang.clone()),
+                );
+                node.json
+                    .set("meta", (!meta.is_empty()).then_some(meta));
+                node
+            }
+            MdBlockDetail::Html => Node::literal("html"),
+            MdBlockDetail::P => Node::new("paragraph"),
+            MdBlockDetail::Table { .. } => {
+                let mut node = Node::new("table");
+                node.json.set("align", Vec::new());
+                node
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => {
+                // No node of their own; keep the block positions in step.
+                self.next_block += 1;
+                return;
+            }
+            MdBlockDetail::Tr => Node::new("tableRow"),
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                self.add_align(align);
+                Node::new("tableCell")
+            }
+        };
+        self.push(node, true);
+    }
+
+    /// Record the alignment of a cell of the first row in its table.
+    fn add_align(&mut self, align: MdAlign) {
+        let depth = self.stack.len();
+        if depth < 2 || !self.stack[depth - 2].children.is_empty() {
+            return;
+        }
+        let align = match align {
+            MdAlign::Default => Json::Null,
+            MdAlign::Left => Json::from("left"),
+            MdAlign::Center => Json::from("center"),
+            MdAlign::Right => Json::from("right"),
+        };
+        if let Some(aligns) =
+            self.stack[depth - 2].json.get_mut("align")
+        {
+            aligns.push(align);
+        }
+    }
+
+    fn leave(&mut self, blocktype: MdBlocktype) {
+        self.close_implicit();
+        match blocktype {
+            MdBlocktype::Thead | MdBlocktype::Tbody => return,
+            MdBlocktype::Code | MdBlocktype::Html => {
+                // The value does not end with a line break in mdast.
+                if let Some(value) = self
+                    .stack
+                    .last_mut()
+                    .and_then(|n| n.value.as_mut())
+                {
+                    if value.ends_with('\n') {
+                        value.pop();
+                    }
+                }
+            }
+            _ => (),
+        }
+        self.pop();
+    }
+}
+
+fn union(
+    a: Option<Range<usize>>,
+    b: Option<Range<usize>>,
+) -> Option<Range<usize>> {
+    match (a, b) {
+        (Some(a), Some(b)) => {
+            Some(a.start.min(b.start)..a.end.max(b.end))
+        }
+        (a, b) => a.or(b),
+    }
+}
+
+/// Concatenated text of `nodes`, as the alternative text of images.
+fn plain_text<'a>(nodes: impl Iterator<Item = &'a Json>) -> String {
+    let mut text = String::new();
+    for node in nodes {
+        if let Some(Json::Str(value)) = node.get("value") {
+            text.push_str(value);
+        }
+        if let Some(Json::Array(children)) = node.get("children") {
+            text.push_str(&plain_text(children.iter()));
+        }
+        if let Some(Json::Str(alt)) = node.get("alt") {
+            text.push_str(alt);
+        }
+    }
+    text
+}
+
+impl<T> MdDetailParser for Md2MdastRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        let blocktype = detail.blocktype();
+        self.leave(blocktype);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        self.inline_parent();
+        let node = match detail {
+            MdSpanDetail::Em => Node::new("emphasis"),
+            MdSpanDetail::Strong => Node::new("strong"),
+            MdSpanDetail::U => Node::new("underline"),
+            MdSpanDetail::Del => Node::new("delete"),
+            MdSpanDetail::Code => Node::literal("inlineCode"),
+            MdSpanDetail::Latexmath => Node::literal("inlineMath"),
+            MdSpanDetail::LatexmathDisplay => Node::literal("math"),
+            MdSpanDetail::A { href, title } => {
+                let mut node = Node::new("link");
+                node.json.set("url", href.decode());
+                let title =
+                    (!title.is_empty()).then(|| title.decode());
+                node.json.set("title", title);
+                node
+            }
+            MdSpanDetail::Img { src, title } => {
+                let mut node = Node::new("image");
+                node.json.set("url", src.decode());
+                let title =
+                    (!title.is_empty()).then(|| title.decode());
+                node.json.set("title", title);
+                node
+            }
+            MdSpanDetail::Wikilink { target } => {
+                let mut node = Node::new("wikiLink");
+                node.json.set("value", target.decode());
+                node
+            }
+        };
+        self.push(node, false);
+        Ok(())
+    }
+
+    fn leave_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        self.pop();
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        self.inline_parent();
+        let range = self.text_range(text);
+        if let Some(node) = self.stack.last_mut() {
+            let is_code_span =
+                node.kind() == &Json::from("inlineCode");
+            if let Some(value) = node.value.as_mut() {
+                match texttype {
+                    MdTexttype::Softbr if is_code_span => {
+                        value.push(' ')
+                    }
+                    MdTexttype::Nullchar => value.push('\u{fffd}'),
+                    _ => value.push_str(text),
+                }
+                node.range = union(node.range.take(), range);
+                return Ok(());
+            }
+        }
+        match texttype {
+            MdTexttype::Br => {
+                let json = Json::object().with("type", "break");
+                if let Some(node) = self.stack.last_mut() {
+                    node.children.push((json, None));
+                }
+            }
+            MdTexttype::Html => self.add_text("html", text, range),
+            MdTexttype::Nullchar => {
+                self.add_text("text", "\u{fffd}", range)
+            }
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => {
+                    self.add_text("text", &decoded, range)
+                }
+                None => self.add_text("text", text, range),
+            },
+            _ => self.add_text("text", text, range),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2MdastRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2MdastRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2MdastRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn mdast(userdata: T, options: Md2MdastOptions) -> Self {
+        Self(Md2MdastRenderer::new(userdata, options), PhantomData)
+    }
+}
@@ -0,0 +1,457 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::Layout;
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2MediawikiOptions {
+    plain_tables: bool,
+}
+
+impl Md2MediawikiOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave out the `wikitable` class, which gives tables their
+    /// borders and header shading.
+    #[inline]
+    pub fn plain_tables(mut self) -> Self {
+        self.plain_tables = true;
+        self
+    }
+}
+
+/// Renders Markdown as MediaWiki wikitext.
+pub struct Md2MediawikiRenderer<T> {
+    pub options: Md2MediawikiOptions,
+    pub userdata: T,
+    layout: Layout,
+    /// Marker of each open list, `#` or `*`, the innermost last.
+    lists: Vec<char>,
+    /// Whether each open list item has had a block yet.
+    items: Vec<bool>,
+    /// Whether the current block continues a list item after `:`.
+    continued: bool,
+    heading: usize,
+    code: Option<String>,
+    in_table: bool,
+    in_math: bool,
+    open_links: Vec<(String, usize)>,
+}
+
+impl<T> Md2MediawikiRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2MediawikiOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            layout: Layout::new(0),
+            lists: Vec::new(),
+            items: Vec::new(),
+            continued: false,
+            heading: 0,
+            code: None,
+            in_table: false,
+            in_math: false,
+            open_links: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    /// Start a block. A blank line would end the list, so the blocks
+    /// of an item after its first continue it with `:`.
+    fn start_block(&mut self) {
+        let pending = !self.layout.inline().is_empty();
+        if self.lists.is_empty() {
+            self.layout.blank();
+            return;
+        }
+        self.layout.flush();
+        if let Some(seen) = self.items.last_mut() {
+            if *seen || pending {
+                self.layout.push_prefix(": ", ": ");
+                self.continued = true;
+            }
+            *seen = true;
+        }
+    }
+
+    fn end_block(&mut self) {
+        self.layout.flush();
+        if self.continued {
+            self.layout.pop_prefix();
+            self.continued = false;
+        }
+    }
+
+    /// Append `text`, in `<nowiki>` if it has anything the wiki would
+    /// take for markup where it lands.
+    fn write_escaped(&mut self, text: &str) {
+        let in_table = self.in_table;
+        let link_start = self.open_links.last().map(|link| link.1);
+        let inline = self.layout.inline();
+        let prev = inline.chars().last();
+        let line_start = matches!(prev, None | Some('\n'));
+        let markup = ["[", "]", "{{", "}}", "~~~", "__", "''"]
+            .iter()
+            .any(|pattern| text.contains(pattern))
+            || (line_start
+                && (text.starts_with(['*', '#', ':', ';', '=', ' '])
+                    || text.starts_with("----")
+                    || text.starts_with("{|")))
+            || (in_table && (text.contains('|') || text.contains("!!")))
+            // Quotes next to emphasis marks would join them.
+            || (text.starts_with('\'') && prev == Some('\''))
+            || text.ends_with('\'');
+        let encoded = text.replace('&', "&amp;").replace('<', "&lt;");
+        match markup {
+            true => {
+                // Text split by escapes or entities joins the
+                // `<nowiki>` of the text before it, unless a link
+                // starts in between.
+                let joins = inline.ends_with("</nowiki>")
+                    && link_start != Some(inline.len());
+                match joins {
+                    true => inline.truncate(inline.len() - 9),
+                    false => inline.push_str("<nowiki>"),
+                }
+                inline.push_str(&encoded);
+                inline.push_str("</nowiki>");
+            }
+            false => inline.push_str(&encoded),
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Layout::new(0);
+            }
+            MdBlockDetail::Quote => {
+                self.start_block();
+                self.layout.line("<blockquote>");
+                // Not to start the quote with a blank line.
+                self.layout.push_prefix("", "");
+            }
+            MdBlockDetail::Ul { .. } => {
+                // Right after the item text if nested: a blank line
+                // would end the list.
+                self.layout.separate();
+                self.layout.push_list(true);
+                self.lists.push('*');
+            }
+            MdBlockDetail::Ol { .. } => {
+                self.layout.separate();
+                self.layout.push_list(true);
+                self.lists.push('#');
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                let marker =
+                    self.lists.last().copied().unwrap_or('*');
+                let mut first = format!("{} ", marker);
+                // Wikitext has no task lists.
+                match (is_task, task_mark as u8) {
+                    (false, _) => (),
+                    (true, b' ') => first.push_str("☐ "),
+                    (true, _) => first.push_str("☑ "),
+                }
+                // Nested lists put their marker after this one.
+                self.layout.push_prefix(&first, &marker.to_string());
+                self.items.push(false);
+            }
+            MdBlockDetail::Hr => {
+                self.start_block();
+                self.layout.line("----");
+            }
+            MdBlockDetail::H { level } => {
+                self.start_block();
+                self.heading = level as usize;
+                let marks = "=".repeat(self.heading);
+                self.layout.inline().push_str(&format!("{} ", marks));
+            }
+            MdBlockDetail::Html | MdBlockDetail::P => {
+                self.start_block()
+            }
+            MdBlockDetail::Code { .. } => {
+                self.start_block();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { .. } => {
+                self.start_block();
+                match self.options.plain_tables {
+                    true => self.layout.line("{|"),
+                    false => {
+                        self.layout.line("{| class=\"wikitable\"")
+                    }
+                }
+                self.in_table = true;
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => self.layout.line("|-"),
+            MdBlockDetail::Th { align } => {
+                self.enter_cell('!', align)
+            }
+            MdBlockDetail::Td { align } => {
+                self.enter_cell('|', align)
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.layout.flush(),
+            MdBlockDetail::Quote => {
+                self.layout.pop_prefix();
+                self.layout.line("</blockquote>");
+                self.end_block();
+            }
+            MdBlockDetail::Li { .. } => {
+                self.layout.pop_prefix();
+                self.items.pop();
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.lists.pop();
+            }
+            MdBlockDetail::H { .. } => {
+                let marks = "=".repeat(self.heading);
+                self.layout.inline().push_str(&format!(" {}", marks));
+                self.end_block();
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                let code = self.code.take().unwrap_or_default();
+                let lang = lang.decode();
+                // The tags take every line of the code, even in a
+                // list item.
+                let block = match lang.is_empty() {
+                    // Entities are still decoded in `<pre>`.
+                    true => format!(
+                        "<pre>\n{}</pre>",
+                        code.replace('&', "&amp;")
+                            .replace('<', "&lt;")
+                    ),
+                    false => format!(
+                        "<syntaxhighlight lang=\"{}\">\n{}\
+                         </syntaxhighlight>",
+                        lang, code
+                    ),
+                };
+                self.layout.line(&block);
+                self.end_block();
+            }
+            MdBlockDetail::Table { .. } => {
+                self.layout.line("|}");
+                self.in_table = false;
+                self.end_block();
+            }
+            MdBlockDetail::Th { .. } | MdBlockDetail::Td { .. } => {
+                self.layout.flush()
+            }
+            MdBlockDetail::Thead
+            | MdBlockDetail::Tbody
+            | MdBlockDetail::Tr => (),
+            _ => self.end_block(),
+        }
+    }
+
+    fn enter_cell(&mut self, marker: char, align: MdAlign) {
+        let style = match align {
+            MdAlign::Default => "",
+            MdAlign::Left => "style=\"text-align:left;\" | ",
+            MdAlign::Center => "style=\"text-align:center;\" | ",
+            MdAlign::Right => "style=\"text-align:right;\" | ",
+        };
+        let inline = self.layout.inline();
+        inline.push(marker);
+        inline.push(' ');
+        inline.push_str(style);
+    }
+
+    fn enter_link(&mut self, url: String) {
+        let start = self.layout.inline().len();
+        self.open_links.push((url, start));
+    }
+
+    /// Close a link, image or wikilink, whose text is in the inline
+    /// text since its start.
+    fn leave_link(&mut self, spantype: MdSpantype) {
+        let (url, start) = match self.open_links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        let inline = self.layout.inline();
+        let text = inline.split_off(start.min(inline.len()));
+        let external =
+            url.contains("://") || url.starts_with("mailto:");
+        let link = match spantype {
+            // External images do not embed.
+            MdSpantype::Img if !external => match text.is_empty() {
+                true => format!("[[File:{}]]", url),
+                false => format!("[[File:{}|{}]]", url, text),
+            },
+            MdSpantype::Wikilink => match text == url {
+                true => format!("[[{}]]", url),
+                false => format!("[[{}|{}]]", url, text),
+            },
+            // Free URLs link by themselves.
+            _ if external && text == url => url,
+            _ if external && text.is_empty() => format!("[{}]", url),
+            _ if external => format!("[{} {}]", url, text),
+            // Relative links are taken for pages of the wiki.
+            _ => match text.is_empty() {
+                true => format!("[[{}]]", url),
+                false => format!("[[{}|{}]]", url, text),
+            },
+        };
+        inline.push_str(&link);
+    }
+}
+
+impl<T> MdDetailParser for Md2MediawikiRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let inline = self.layout.inline();
+        match detail {
+            MdSpanDetail::Em => inline.push_str("''"),
+            MdSpanDetail::Strong => inline.push_str("'''"),
+            MdSpanDetail::Del => inline.push_str("<s>"),
+            MdSpanDetail::U => inline.push_str("<u>"),
+            MdSpanDetail::Code => inline.push_str("<code>"),
+            MdSpanDetail::Latexmath => {
+                inline.push_str("<math>");
+                self.in_math = true;
+            }
+            MdSpanDetail::LatexmathDisplay => {
+                inline.push_str("<math display=\"block\">");
+                self.in_math = true;
+            }
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode())
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(target.decode())
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        let inline = self.layout.inline();
+        match spantype {
+            MdSpantype::Em => inline.push_str("''"),
+            MdSpantype::Strong => inline.push_str("'''"),
+            MdSpantype::Del => inline.push_str("</s>"),
+            MdSpantype::U => inline.push_str("</u>"),
+            MdSpantype::Code => inline.push_str("</code>"),
+            MdSpantype::Latexmath | MdSpantype::LatexmathDisplay => {
+                inline.push_str("</math>");
+                self.in_math = false;
+            }
+            MdSpantype::A
+            | MdSpantype::Img
+            | MdSpantype::Wikilink => self.leave_link(spantype),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(code) = self.code.as_mut() {
+            code.push_str(text);
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => self.layout.inline().push_str(text),
+            MdTexttype::Nullchar => self.write_escaped("\u{fffd}"),
+            MdTexttype::Br => self.layout.inline().push_str("<br />"),
+            // A line break would end the list item or table cell.
+            MdTexttype::Softbr => self.layout.inline().push(' '),
+            _ if self.in_math => self.layout.inline().push_str(text),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2MediawikiRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2MediawikiRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2MediawikiRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn mediawiki(
+        userdata: T,
+        options: Md2MediawikiOptions,
+    ) -> Self {
+        Self(
+            Md2MediawikiRenderer::new(userdata, options),
+            PhantomData,
+        )
+    }
+}
@@ -0,0 +1,529 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{self, Layout, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+/// Org's escape character: it keeps markup characters from being
+/// read as such, and shows as nothing.
+const ZERO_WIDTH_SPACE: char = '\u{200b}';
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2OrgOptions {
+    lowercase_keywords: bool,
+}
+
+impl Md2OrgOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Write `#+begin_src` and the like in lower case, as Org itself
+    /// inserts them.
+    #[inline]
+    pub fn lowercase_keywords(mut self) -> Self {
+        self.lowercase_keywords = true;
+        self
+    }
+}
+
+struct List {
+    /// Next number and its delimiter, for ordered lists.
+    number: Option<(u32, char)>,
+    /// Start number, for the cookie of the first item.
+    start: Option<u32>,
+}
+
+/// Renders Markdown as Emacs Org.
+pub struct Md2OrgRenderer<T> {
+    pub options: Md2OrgOptions,
+    pub userdata: T,
+    layout: Layout,
+    lists: Vec<List>,
+    quote_depth: usize,
+    table: Option<TextTable>,
+    /// Lines of a code or HTML block.
+    code: Option<String>,
+    /// Whether text goes as is, in a code span or math.
+    verbatim: bool,
+    /// Start of the current code span in the sink.
+    code_start: usize,
+    open_links: Vec<(String, usize)>,
+}
+
+impl<T> Md2OrgRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2OrgOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            layout: Layout::new(0),
+            lists: Vec::new(),
+            quote_depth: 0,
+            table: None,
+            code: None,
+            verbatim: false,
+            code_start: 0,
+            open_links: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn sink(&mut self) -> &mut String {
+        layout::sink(
+            self.code.as_mut(),
+            self.table.as_mut(),
+            self.layout.inline(),
+        )
+    }
+
+    fn keyword(&self, name: &str) -> String {
+        match self.options.lowercase_keywords {
+            true => format!("#+{}", name.to_lowercase()),
+            false => format!("#+{}", name),
+        }
+    }
+
+    /// Output a block between `#+BEGIN_…` and `#+END_…` lines.
+    fn literal_block(&mut self, kind: &str, args: &str, text: &str) {
+        let begin = self.keyword(&format!("BEGIN_{}", kind));
+        match args.is_empty() {
+            true => self.layout.line(&begin),
+            false => self.layout.line(&format!("{} {}", begin, args)),
+        }
+        for line in text.lines() {
+            // Lines that Org would read as a heading or keyword are
+            // protected with a comma.
+            let trimmed = line.trim_start().trim_start_matches(',');
+            match trimmed.starts_with('*')
+                || trimmed.starts_with("#+")
+            {
+                true => self.layout.line(&format!(",{}", line)),
+                false => self.layout.line(line),
+            }
+        }
+        self.layout.line(&self.keyword(&format!("END_{}", kind)));
+    }
+
+    fn write_escaped(&mut self, text: &str) {
+        let in_cell = self.table.is_some();
+        let sink = self.sink();
+        let mut prev = sink.chars().last();
+        let mut chars = text.char_indices().peekable();
+        while let Some((idx, ch)) = chars.next() {
+            let next = chars.peek().map(|&(_, ch)| ch);
+            let line_start =
+                !in_cell && matches!(prev, None | Some('\n'));
+            // Emphasis opens after a space or one of `-('"{`, before a
+            // non-space.
+            let could_open = match prev {
+                Some(ch) => {
+                    ch.is_whitespace() || "-('\"{".contains(ch)
+                }
+                None => true,
+            };
+            // The next chunk of text may well start with a non-space.
+            let could_open =
+                could_open && !next.is_some_and(char::is_whitespace);
+            let escape = match ch {
+                '*' | '/' | '+' | '_' | '~' | '=' if could_open => {
+                    true
+                }
+                '*' | '#' | '|' if line_start => true,
+                ':' | '-' | '+' if line_start => {
+                    next.is_none_or(char::is_whitespace)
+                }
+                '0'..='9' if line_start => {
+                    let rest =
+                        text[idx..].trim_start_matches(|ch: char| {
+                            ch.is_ascii_digit()
+                        });
+                    rest.starts_with(". ") || rest.starts_with(") ")
+                }
+                '[' => prev == Some('['),
+                _ => false,
+            };
+            if escape {
+                sink.push(ZERO_WIDTH_SPACE);
+            }
+            match ch {
+                '|' if in_cell => sink.push_str("\\vert{}"),
+                _ => sink.push(ch),
+            }
+            prev = Some(ch);
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Layout::new(0);
+            }
+            MdBlockDetail::Quote => {
+                self.layout.separate();
+                self.layout.line(&self.keyword("BEGIN_QUOTE"));
+                self.quote_depth += 1;
+                // Not to start the quote with a blank line.
+                self.layout.push_prefix("", "");
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List {
+                    number: None,
+                    start: None,
+                });
+            }
+            MdBlockDetail::Ol {
+                start,
+                is_tight,
+                mark_delimiter,
+            } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List {
+                    number: Some((
+                        start,
+                        mark_delimiter as u8 as char,
+                    )),
+                    start: Some(start).filter(|&start| start != 1),
+                });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.layout.separate();
+                let (mut marker, indent) = match self.lists.last_mut()
+                {
+                    Some(List {
+                        number: Some((number, delim)),
+                        start,
+                        ..
+                    }) => {
+                        let marker = format!("{}{} ", number, delim);
+                        let indent = marker.len();
+                        *number += 1;
+                        match start.take() {
+                            Some(start) => (
+                                format!("{}[@{}] ", marker, start),
+                                indent,
+                            ),
+                            None => (marker, indent),
+                        }
+                    }
+                    _ => ("- ".to_owned(), 2),
+                };
+                match (is_task, task_mark as u8) {
+                    (false, _) => (),
+                    (true, b' ') => marker.push_str("[ ] "),
+                    (true, _) => marker.push_str("[X] "),
+                }
+                self.layout.push_prefix(&marker, &" ".repeat(indent));
+            }
+            MdBlockDetail::Hr => {
+                self.layout.separate();
+                self.layout.line("-----");
+            }
+            MdBlockDetail::H { level } => {
+                self.layout.separate();
+                // Headings only start sections at the top level.
+                let prefix = match self.lists.is_empty()
+                    && self.quote_depth == 0
+                {
+                    true => {
+                        format!("{} ", "*".repeat(level as usize))
+                    }
+                    false => "*".to_owned(),
+                };
+                self.layout.inline().push_str(&prefix);
+            }
+            MdBlockDetail::P => self.layout.separate(),
+            MdBlockDetail::Html | MdBlockDetail::Code { .. } => {
+                self.layout.separate();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.layout.separate();
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.layout.flush(),
+            MdBlockDetail::Quote => {
+                self.layout.pop_prefix();
+                self.quote_depth -= 1;
+                self.layout.line(&self.keyword("END_QUOTE"));
+            }
+            MdBlockDetail::Li { .. } => self.layout.pop_prefix(),
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.lists.pop();
+            }
+            MdBlockDetail::H { .. } => {
+                if !self.lists.is_empty() || self.quote_depth > 0 {
+                    self.layout.inline().push('*');
+                }
+                self.layout.flush();
+            }
+            MdBlockDetail::Html => {
+                let html = self.code.take().unwrap_or_default();
+                self.literal_block("EXPORT", "html", &html);
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                let code = self.code.take().unwrap_or_default();
+                match lang.decode() {
+                    lang if lang.is_empty() => {
+                        self.literal_block("EXAMPLE", "", &code)
+                    }
+                    lang => self.literal_block("SRC", &lang, &code),
+                }
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    for line in table_lines(&table) {
+                        self.layout.line(&line);
+                    }
+                }
+            }
+            _ => self.layout.flush(),
+        }
+    }
+
+    fn enter_link(&mut self, url: String) {
+        let start = self.sink().len();
+        self.open_links.push((url, start));
+    }
+
+    /// Close a link, image or wikilink, whose description is in the
+    /// sink since its start.
+    fn leave_link(&mut self, spantype: MdSpantype) {
+        let (url, start) = match self.open_links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        let sink = self.sink();
+        let text = sink.split_off(start.min(sink.len()));
+        let url = url.replace('[', "\\[").replace(']', "\\]");
+        // Without a scheme, Org would search a heading of that name.
+        let url = match url.contains(':') || url.starts_with('#') {
+            true => url,
+            false if spantype == MdSpantype::Wikilink => url,
+            false => format!("file:{}", url),
+        };
+        // An image shows inline only as a link without description.
+        let link = match spantype == MdSpantype::Img
+            || text.is_empty()
+            || text == url
+        {
+            true => format!("[[{}]]", url),
+            false => format!("[[{}][{}]]", url, text),
+        };
+        sink.push_str(&link);
+    }
+
+    fn leave_code_span(&mut self) {
+        let start = self.code_start;
+        let sink = self.sink();
+        let code = sink.split_off(start.min(sink.len()));
+        let mark = match code.contains('~') {
+            true => '=',
+            false => '~',
+        };
+        sink.push(mark);
+        sink.push_str(&code);
+        sink.push(mark);
+    }
+}
+
+/// Lines of an Org table, with a rule under the header rows.
+fn table_lines(table: &TextTable) -> Vec<String> {
+    let rule: Vec<String> =
+        table.widths(1).iter().map(|&w| "-".repeat(w + 2)).collect();
+    let mut lines = Vec::new();
+    for (idx, row) in table.padded_rows(1).iter().enumerate() {
+        if idx == table.head_rows() && idx > 0 {
+            lines.push(format!("|{}|", rule.join("+")));
+        }
+        lines.push(format!("| {} |", row.join(" | ")));
+    }
+    lines
+}
+
+impl<T> MdDetailParser for Md2OrgRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let mark = match detail {
+            MdSpanDetail::Em => "/",
+            MdSpanDetail::Strong => "*",
+            MdSpanDetail::Del => "+",
+            MdSpanDetail::U => "_",
+            MdSpanDetail::Code => {
+                self.code_start = self.sink().len();
+                self.verbatim = true;
+                ""
+            }
+            MdSpanDetail::Latexmath => {
+                self.verbatim = true;
+                "\\("
+            }
+            MdSpanDetail::LatexmathDisplay => {
+                self.verbatim = true;
+                "\\["
+            }
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode());
+                ""
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode());
+                ""
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(target.decode());
+                ""
+            }
+        };
+        self.sink().push_str(mark);
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        let mark = match spantype {
+            MdSpantype::Em => "/",
+            MdSpantype::Strong => "*",
+            MdSpantype::Del => "+",
+            MdSpantype::U => "_",
+            MdSpantype::Code => {
+                self.leave_code_span();
+                self.verbatim = false;
+                ""
+            }
+            MdSpantype::Latexmath => {
+                self.verbatim = false;
+                "\\)"
+            }
+            MdSpantype::LatexmathDisplay => {
+                self.verbatim = false;
+                "\\]"
+            }
+            MdSpantype::A
+            | MdSpantype::Img
+            | MdSpantype::Wikilink => {
+                self.leave_link(spantype);
+                ""
+            }
+        };
+        self.sink().push_str(mark);
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(code) = self.code.as_mut() {
+            code.push_str(text);
+            return Ok(());
+        }
+        let in_cell = self.table.is_some();
+        match texttype {
+            MdTexttype::Nullchar => self.write_escaped("\u{fffd}"),
+            MdTexttype::Br | MdTexttype::Softbr if in_cell => {
+                self.sink().push(' ')
+            }
+            MdTexttype::Br => self.sink().push_str("\\\\\n"),
+            MdTexttype::Softbr => self.sink().push('\n'),
+            MdTexttype::Html => {
+                let html = format!("@@html:{}@@", text);
+                self.sink().push_str(&html);
+            }
+            _ if self.verbatim => self.sink().push_str(text),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2OrgRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2OrgRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2OrgRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn org(userdata: T, options: Md2OrgOptions) -> Self {
+        Self(Md2OrgRenderer::new(userdata, options), PhantomData)
+    }
+}
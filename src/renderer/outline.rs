@@ -0,0 +1,359 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+/// File format of the outline.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Md2OutlineFormat {
+    /// OPML 2.0, for outliners; notes are `_note` attributes.
+    Opml,
+    /// FreeMind `.mm` mind map, also read by Freeplane and XMind.
+    Freemind,
+}
+
+#[derive(Clone, Debug)]
+pub struct Md2OutlineOptions {
+    format: Md2OutlineFormat,
+    notes: bool,
+    title: Option<String>,
+}
+
+impl Md2OutlineOptions {
+    #[inline]
+    pub fn new(format: Md2OutlineFormat) -> Self {
+        Self {
+            format,
+            notes: false,
+            title: None,
+        }
+    }
+
+    /// Attach the first paragraph under each heading to it as a note.
+    #[inline]
+    pub fn notes(mut self) -> Self {
+        self.notes = true;
+        self
+    }
+
+    /// Title of the outline, and text of the root of the mind map.
+    /// Without it, the only top-level heading, if there is a single
+    /// one, is used.
+    #[inline]
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = Some(title.to_owned());
+        self
+    }
+}
+
+/// A heading and the headings under it.
+#[derive(Default)]
+struct Node {
+    text: String,
+    note: Option<String>,
+    children: Vec<Node>,
+}
+
+/// Exports the heading hierarchy of the document, the headings of
+/// quotes and lists excepted, as an outline or a mind map. A heading
+/// goes under the closest heading of a lower level before it, so
+/// skipped levels do not add empty nodes.
+pub struct Md2OutlineRenderer<T> {
+    pub options: Md2OutlineOptions,
+    pub userdata: T,
+    roots: Vec<Node>,
+    /// The open headings, with their levels, outermost first.
+    open: Vec<(u32, Node)>,
+    depth: usize,
+    /// Text of the heading or note paragraph being read.
+    text: Option<String>,
+    in_heading: bool,
+    /// Whether the next top-level paragraph is the note of the last
+    /// heading.
+    wants_note: bool,
+}
+
+impl<T> Md2OutlineRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2OutlineOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            roots: Vec::new(),
+            open: Vec::new(),
+            depth: 0,
+            text: None,
+            in_heading: false,
+            wants_note: false,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    /// Close the open headings of `level` or deeper.
+    fn close(&mut self, level: u32) {
+        while self.open.last().is_some_and(|(l, _)| *l >= level) {
+            let (_, node) = self.open.pop().unwrap();
+            match self.open.last_mut() {
+                Some((_, parent)) => parent.children.push(node),
+                None => self.roots.push(node),
+            }
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        self.depth += 1;
+        // Top-level blocks are the children of the document.
+        if self.depth != 2 {
+            return;
+        }
+        match detail {
+            MdBlockDetail::H { level } => {
+                self.close(level);
+                self.open.push((level, Node::default()));
+                self.text = Some(String::new());
+                self.in_heading = true;
+                self.wants_note = false;
+            }
+            MdBlockDetail::P if self.wants_note => {
+                self.text = Some(String::new());
+                self.wants_note = false;
+            }
+            _ => (),
+        }
+    }
+
+    fn leave(&mut self, blocktype: MdBlocktype) {
+        self.depth -= 1;
+        if self.depth == 0 {
+            self.close(1);
+            let out = self.document();
+            self.userdata.render_append(&out);
+            return;
+        }
+        let text = match (self.depth, self.text.take()) {
+            (1, Some(text)) => text.trim().to_owned(),
+            (_, text) => {
+                self.text = text;
+                return;
+            }
+        };
+        let node = match self.open.last_mut() {
+            Some((_, node)) => node,
+            None => return,
+        };
+        match blocktype {
+            MdBlocktype::H => {
+                node.text = text;
+                self.in_heading = false;
+                self.wants_note = self.options.notes;
+            }
+            _ => node.note = Some(text),
+        }
+    }
+
+    fn title(&self) -> Option<&str> {
+        match (&self.options.title, self.roots.as_slice()) {
+            (Some(title), _) => Some(title),
+            (None, [root]) => Some(&root.text),
+            _ => None,
+        }
+    }
+
+    fn document(&mut self) -> String {
+        let title = self.title().map(str::to_owned);
+        let roots = std::mem::take(&mut self.roots);
+        let mut out = String::new();
+        match self.options.format {
+            Md2OutlineFormat::Opml => {
+                out.push_str(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <opml version=\"2.0\">\n  <head>\n",
+                );
+                if let Some(title) = title {
+                    out.push_str(&format!(
+                        "    <title>{}</title>\n",
+                        escape(&title)
+                    ));
+                }
+                out.push_str("  </head>\n  <body>\n");
+                for node in &roots {
+                    opml_node(&mut out, node, 2);
+                }
+                out.push_str("  </body>\n</opml>\n");
+            }
+            Md2OutlineFormat::Freemind => {
+                // A map has a single root: the only top-level heading,
+                // or the title over all of them.
+                out.push_str("<map version=\"1.0.1\">\n");
+                match (self.options.title.is_some(), roots.as_slice())
+                {
+                    (false, [root]) => freemind_node(&mut out, root),
+                    _ => {
+                        let root = Node {
+                            text: title.unwrap_or_default(),
+                            note: None,
+                            children: roots,
+                        };
+                        freemind_node(&mut out, &root);
+                    }
+                }
+                out.push_str("</map>\n");
+            }
+        }
+        out
+    }
+}
+
+fn opml_node(out: &mut String, node: &Node, level: usize) {
+    let indent = "  ".repeat(level);
+    out.push_str(&format!(
+        "{}<outline text=\"{}\"",
+        indent,
+        escape(&node.text)
+    ));
+    if let Some(note) = &node.note {
+        out.push_str(&format!(" _note=\"{}\"", escape(note)));
+    }
+    if node.children.is_empty() {
+        out.push_str("/>\n");
+        return;
+    }
+    out.push_str(">\n");
+    for child in &node.children {
+        opml_node(out, child, level + 1);
+    }
+    out.push_str(&format!("{}</outline>\n", indent));
+}
+
+fn freemind_node(out: &mut String, node: &Node) {
+    out.push_str(&format!("<node TEXT=\"{}\"", escape(&node.text)));
+    if node.note.is_none() && node.children.is_empty() {
+        out.push_str("/>\n");
+        return;
+    }
+    out.push_str(">\n");
+    if let Some(note) = &node.note {
+        let html = escape(note).replace("&#10;", "<br/>");
+        out.push_str(&format!(
+            "<richcontent TYPE=\"NOTE\"><html><head/><body>\
+             <p>{}</p></body></html></richcontent>\n",
+            html
+        ));
+    }
+    for child in &node.children {
+        freemind_node(out, child);
+    }
+    out.push_str("</node>\n");
+}
+
+/// Escape `text` for an XML attribute, keeping its line breaks.
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\n' => out.push_str("&#10;"),
+            ch if ch.is_control() => (),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+impl<T> MdDetailParser for Md2OutlineRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        let blocktype = detail.blocktype();
+        self.leave(blocktype);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        Ok(())
+    }
+
+    fn leave_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        let out = match self.text.as_mut() {
+            Some(out) => out,
+            None => return Ok(()),
+        };
+        match texttype {
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => out.push('\u{fffd}'),
+            // Outliners show one line per heading.
+            MdTexttype::Br if self.in_heading => out.push(' '),
+            MdTexttype::Br => out.push('\n'),
+            MdTexttype::Softbr => out.push(' '),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => out.push_str(&decoded),
+                None => out.push_str(text),
+            },
+            _ => out.push_str(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2OutlineRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2OutlineRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2OutlineRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn outline(userdata: T, options: Md2OutlineOptions) -> Self {
+        Self(Md2OutlineRenderer::new(userdata, options), PhantomData)
+    }
+}
@@ -0,0 +1,584 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::json::Json;
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2PandocOptions {
+    pretty: bool,
+}
+
+impl Md2PandocOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Indent the JSON output, instead of writing it on one line.
+    #[inline]
+    pub fn pretty(mut self) -> Self {
+        self.pretty = true;
+        self
+    }
+}
+
+/// Version of pandoc-types the output follows.
+const API_VERSION: [u32; 3] = [1, 23, 1];
+
+enum Kind {
+    Doc,
+    Quote,
+    Ul,
+    Ol {
+        start: u32,
+        delimiter: u8,
+    },
+    Li {
+        task: Option<bool>,
+    },
+    Hr,
+    H {
+        level: u32,
+    },
+    Code {
+        lang: String,
+    },
+    Html,
+    P,
+    Table {
+        head_rows: usize,
+        aligns: Vec<MdAlign>,
+    },
+    Tr,
+    Cell {
+        align: MdAlign,
+    },
+    Em,
+    Strong,
+    Del,
+    U,
+    CodeSpan,
+    Link {
+        url: String,
+        title: String,
+    },
+    Img {
+        url: String,
+        title: String,
+    },
+    Math {
+        display: bool,
+    },
+    Wikilink {
+        target: String,
+    },
+}
+
+/// An element being built. Containers collect their children in
+/// `blocks` (list items and table rows included) and `inlines`;
+/// literal elements their text in `text`.
+struct Frame {
+    kind: Kind,
+    blocks: Vec<Json>,
+    inlines: Vec<Json>,
+    text: String,
+}
+
+/// Pandoc element `t` with contents `c`.
+fn node<C: Into<Json>>(t: &str, c: C) -> Json {
+    Json::object().with("t", t).with("c", c)
+}
+
+/// Pandoc element `t` without contents.
+fn tag(t: &str) -> Json {
+    Json::object().with("t", t)
+}
+
+/// An `Attr`: identifier, classes and key-value pairs.
+fn attr(classes: &[&str]) -> Json {
+    let classes = classes.iter().map(|&c| Json::from(c)).collect();
+    Json::Array(vec![
+        Json::from(""),
+        Json::Array(classes),
+        Json::Array(Vec::new()),
+    ])
+}
+
+fn align(align: &MdAlign) -> Json {
+    tag(match align {
+        MdAlign::Left => "AlignLeft",
+        MdAlign::Center => "AlignCenter",
+        MdAlign::Right => "AlignRight",
+        MdAlign::Default => "AlignDefault",
+    })
+}
+
+/// Renders the document as the JSON AST of Pandoc, as read by
+/// `pandoc -f json`.
+pub struct Md2PandocRenderer<T> {
+    pub options: Md2PandocOptions,
+    pub userdata: T,
+    stack: Vec<Frame>,
+}
+
+impl<T> Md2PandocRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2PandocOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            stack: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.stack.clear();
+        self.parse(input, parser_flags)
+    }
+
+    fn push(&mut self, kind: Kind) {
+        self.stack.push(Frame {
+            kind,
+            blocks: Vec::new(),
+            inlines: Vec::new(),
+            text: String::new(),
+        });
+    }
+
+    /// Turn the inlines collected outside of any paragraph, as in tight
+    /// list items and table cells, into a `Plain` block.
+    fn flush_plain(frame: &mut Frame) {
+        if !frame.inlines.is_empty() {
+            let inlines = std::mem::take(&mut frame.inlines);
+            frame.blocks.push(node("Plain", inlines));
+        }
+    }
+
+    fn add_block(&mut self, block: Json) {
+        if let Some(frame) = self.stack.last_mut() {
+            Self::flush_plain(frame);
+            frame.blocks.push(block);
+        }
+    }
+
+    fn add_inline(&mut self, inline: Json) {
+        if let Some(frame) = self.stack.last_mut() {
+            frame.inlines.push(inline);
+        }
+    }
+
+    /// Add text as `Str` and `Space` elements, merging it with the
+    /// preceding `Str`.
+    fn add_text(&mut self, text: &str) {
+        let frame = match self.stack.last_mut() {
+            Some(frame) => frame,
+            None => return,
+        };
+        if let Kind::Code { .. }
+        | Kind::Html
+        | Kind::CodeSpan
+        | Kind::Math { .. } = frame.kind
+        {
+            frame.text.push_str(text);
+            return;
+        }
+        for (idx, word) in text.split(' ').enumerate() {
+            let inlines = &mut frame.inlines;
+            if idx > 0 && inlines.last() != Some(&tag("Space")) {
+                inlines.push(tag("Space"));
+            }
+            if word.is_empty() {
+                continue;
+            }
+            match inlines.last_mut() {
+                Some(last)
+                    if last.get("t") == Some(&Json::from("Str")) =>
+                {
+                    if let Some(Json::Str(s)) = last.get_mut("c") {
+                        s.push_str(word);
+                    }
+                }
+                _ => inlines.push(node("Str", word)),
+            }
+        }
+    }
+
+    fn pop(&mut self) {
+        let mut frame = match self.stack.pop() {
+            Some(frame) => frame,
+            None => return,
+        };
+        let inlines = std::mem::take(&mut frame.inlines);
+        let block = match frame.kind {
+            Kind::Doc => {
+                let version = API_VERSION
+                    .iter()
+                    .map(|&n| Json::from(n))
+                    .collect();
+                let doc = Json::object()
+                    .with("pandoc-api-version", Json::Array(version))
+                    .with("meta", Json::object())
+                    .with("blocks", frame.blocks);
+                let indent = if self.options.pretty { 2 } else { 0 };
+                let mut out = doc.dump(indent);
+                out.push('\n');
+                self.userdata.render_append(&out);
+                return;
+            }
+            Kind::Quote => node("BlockQuote", frame.blocks),
+            Kind::Ul => node("BulletList", frame.blocks),
+            Kind::Ol { start, delimiter } => {
+                let delimiter = match delimiter {
+                    b')' => "OneParen",
+                    _ => "Period",
+                };
+                let attrs = vec![
+                    Json::from(start),
+                    tag("Decimal"),
+                    tag(delimiter),
+                ];
+                let c = vec![
+                    Json::Array(attrs),
+                    Json::Array(frame.blocks),
+                ];
+                node("OrderedList", c)
+            }
+            Kind::Li { task } => {
+                frame.inlines = inlines;
+                Self::flush_plain(&mut frame);
+                if let (Some(checked), Some(first)) =
+                    (task, frame.blocks.first_mut())
+                {
+                    let mark = if checked { "☒" } else { "☐" };
+                    if let Some(Json::Array(c)) = first.get_mut("c") {
+                        c.insert(0, tag("Space"));
+                        c.insert(0, node("Str", mark));
+                    }
+                }
+                // List items are plain lists of blocks.
+                if let Some(list) = self.stack.last_mut() {
+                    list.blocks.push(Json::Array(frame.blocks));
+                }
+                return;
+            }
+            Kind::Hr => tag("HorizontalRule"),
+            Kind::H { level } => node(
+                "Header",
+                vec![Json::from(level), attr(&[]), inlines.into()],
+            ),
+            Kind::Code { lang } => {
+                let mut text = frame.text;
+                if text.ends_with('\n') {
+                    text.pop();
+                }
+                let classes: &[&str] = match lang.as_str() {
+                    "" => &[],
+                    lang => &[lang],
+                };
+                node("CodeBlock", vec![attr(classes), text.into()])
+            }
+            Kind::Html => {
+                let mut text = frame.text;
+                if text.ends_with('\n') {
+                    text.pop();
+                }
+                node(
+                    "RawBlock",
+                    vec![Json::from("html"), text.into()],
+                )
+            }
+            Kind::P => node("Para", inlines),
+            Kind::Table { head_rows, aligns } => {
+                self.table(frame.blocks, head_rows, &aligns)
+            }
+            Kind::Tr => {
+                let row = vec![attr(&[]), Json::Array(frame.blocks)];
+                if let Some(table) = self.stack.last_mut() {
+                    table.blocks.push(Json::Array(row));
+                }
+                return;
+            }
+            Kind::Cell { align: cell_align } => {
+                let plain = match inlines.is_empty() {
+                    true => Vec::new(),
+                    false => vec![node("Plain", inlines)],
+                };
+                let cell = vec![
+                    attr(&[]),
+                    align(&cell_align),
+                    Json::from(1u32),
+                    Json::from(1u32),
+                    Json::Array(plain),
+                ];
+                if let Some(row) = self.stack.last_mut() {
+                    row.blocks.push(Json::Array(cell));
+                }
+                return;
+            }
+            kind => {
+                let inline = Self::inline(kind, inlines, frame.text);
+                self.add_inline(inline);
+                return;
+            }
+        };
+        self.add_block(block);
+    }
+
+    fn inline(kind: Kind, inlines: Vec<Json>, text: String) -> Json {
+        match kind {
+            Kind::Em => node("Emph", inlines),
+            Kind::Strong => node("Strong", inlines),
+            Kind::Del => node("Strikeout", inlines),
+            Kind::U => node("Underline", inlines),
+            Kind::CodeSpan => {
+                node("Code", vec![attr(&[]), text.into()])
+            }
+            Kind::Link { url, title } => {
+                let target = vec![Json::from(url), Json::from(title)];
+                node(
+                    "Link",
+                    vec![attr(&[]), inlines.into(), target.into()],
+                )
+            }
+            Kind::Img { url, title } => {
+                let target = vec![Json::from(url), Json::from(title)];
+                node(
+                    "Image",
+                    vec![attr(&[]), inlines.into(), target.into()],
+                )
+            }
+            Kind::Wikilink { target } => {
+                let target =
+                    vec![Json::from(target), Json::from("wikilink")];
+                node(
+                    "Link",
+                    vec![attr(&[]), inlines.into(), target.into()],
+                )
+            }
+            Kind::Math { display } => {
+                let math_type = match display {
+                    true => "DisplayMath",
+                    false => "InlineMath",
+                };
+                node("Math", vec![tag(math_type), text.into()])
+            }
+            _ => Json::Null,
+        }
+    }
+
+    fn table(
+        &self,
+        rows: Vec<Json>,
+        head_rows: usize,
+        aligns: &[MdAlign],
+    ) -> Json {
+        let colspecs: Vec<Json> = aligns
+            .iter()
+            .map(|a| {
+                Json::Array(vec![align(a), tag("ColWidthDefault")])
+            })
+            .collect();
+        let mut head = rows;
+        let body = head.split_off(head_rows.min(head.len()));
+        let caption = vec![Json::Null, Json::Array(Vec::new())];
+        let head = vec![attr(&[]), Json::Array(head)];
+        let body = vec![
+            attr(&[]),
+            Json::from(0u32),
+            Json::Array(Vec::new()),
+            Json::Array(body),
+        ];
+        let foot = vec![attr(&[]), Json::Array(Vec::new())];
+        node(
+            "Table",
+            vec![
+                attr(&[]),
+                caption.into(),
+                colspecs.into(),
+                head.into(),
+                Json::Array(vec![body.into()]),
+                foot.into(),
+            ],
+        )
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        let kind = match detail {
+            MdBlockDetail::Doc => Kind::Doc,
+            MdBlockDetail::Quote => Kind::Quote,
+            MdBlockDetail::Ul { .. } => Kind::Ul,
+            MdBlockDetail::Ol {
+                start,
+                mark_delimiter,
+                ..
+            } => Kind::Ol {
+                start,
+                delimiter: mark_delimiter as u8,
+            },
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => Kind::Li {
+                task: is_task.then_some(task_mark as u8 != b' '),
+            },
+            MdBlockDetail::Hr => Kind::Hr,
+            MdBlockDetail::H { level } => Kind::H { level },
+            MdBlockDetail::Code { lang, .. } => Kind::Code {
+                lang: lang.decode(),
+            },
+            MdBlockDetail::Html => Kind::Html,
+            MdBlockDetail::P => Kind::P,
+            MdBlockDetail::Table { head_row_count, .. } => {
+                Kind::Table {
+                    head_rows: head_row_count as usize,
+                    aligns: Vec::new(),
+                }
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => return,
+            MdBlockDetail::Tr => Kind::Tr,
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                // The alignments of the columns are those of the cells
+                // of the first row.
+                let depth = self.stack.len();
+                if depth >= 2 {
+                    let table = &mut self.stack[depth - 2];
+                    if let (Kind::Table { aligns, .. }, true) =
+                        (&mut table.kind, table.blocks.is_empty())
+                    {
+                        aligns.push(align);
+                    }
+                }
+                Kind::Cell { align }
+            }
+        };
+        self.push(kind);
+    }
+}
+
+impl<T> MdDetailParser for Md2PandocRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        let blocktype = detail.blocktype();
+        if let MdBlocktype::Thead | MdBlocktype::Tbody = blocktype {
+            return Ok(());
+        }
+        self.pop();
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let kind = match detail {
+            MdSpanDetail::Em => Kind::Em,
+            MdSpanDetail::Strong => Kind::Strong,
+            MdSpanDetail::Del => Kind::Del,
+            MdSpanDetail::U => Kind::U,
+            MdSpanDetail::Code => Kind::CodeSpan,
+            MdSpanDetail::A { href, title } => Kind::Link {
+                url: href.decode(),
+                title: title.decode(),
+            },
+            MdSpanDetail::Img { src, title } => Kind::Img {
+                url: src.decode(),
+                title: title.decode(),
+            },
+            MdSpanDetail::Latexmath => Kind::Math { display: false },
+            MdSpanDetail::LatexmathDisplay => {
+                Kind::Math { display: true }
+            }
+            MdSpanDetail::Wikilink { target } => Kind::Wikilink {
+                target: target.decode(),
+            },
+        };
+        self.push(kind);
+        Ok(())
+    }
+
+    fn leave_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        self.pop();
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        let in_literal = matches!(
+            self.stack.last().map(|f| &f.kind),
+            Some(
+                Kind::Code { .. }
+                    | Kind::Html
+                    | Kind::CodeSpan
+                    | Kind::Math { .. }
+            )
+        );
+        match texttype {
+            MdTexttype::Softbr if in_literal => self.add_text(" "),
+            _ if in_literal => self.add_text(text),
+            MdTexttype::Br => self.add_inline(tag("LineBreak")),
+            MdTexttype::Softbr => self.add_inline(tag("SoftBreak")),
+            MdTexttype::Html => {
+                let raw = vec![Json::from("html"), Json::from(text)];
+                self.add_inline(node("RawInline", raw));
+            }
+            MdTexttype::Nullchar => self.add_text("\u{fffd}"),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.add_text(&decoded),
+                None => self.add_text(text),
+            },
+            _ => self.add_text(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2PandocRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2PandocRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2PandocRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn pandoc(userdata: T, options: Md2PandocOptions) -> Self {
+        Self(Md2PandocRenderer::new(userdata, options), PhantomData)
+    }
+}
@@ -0,0 +1,342 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{self, Layout, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug)]
+pub struct Md2PlainOptions {
+    width: usize,
+    link_footnotes: bool,
+    bullet: char,
+}
+
+impl Md2PlainOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self {
+            width: 0,
+            link_footnotes: false,
+            bullet: '-',
+        }
+    }
+
+    /// Wrap paragraphs to `width` columns; 0, the default, keeps every
+    /// paragraph on one line.
+    #[inline]
+    pub fn width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    /// Keep the URLs of links and images as numbered footnotes, listed
+    /// at the end of the document.
+    #[inline]
+    pub fn link_footnotes(mut self) -> Self {
+        self.link_footnotes = true;
+        self
+    }
+
+    /// Bullet of unordered list items, `-` by default.
+    #[inline]
+    pub fn bullet(mut self, bullet: char) -> Self {
+        self.bullet = bullet;
+        self
+    }
+}
+
+impl Default for Md2PlainOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+struct List {
+    next: Option<u32>,
+}
+
+/// Renders Markdown as plain text: all markup stripped, entities
+/// decoded, lists with bullets or numbers and tables as aligned
+/// columns.
+pub struct Md2PlainRenderer<T> {
+    pub options: Md2PlainOptions,
+    pub userdata: T,
+    layout: Layout,
+    lists: Vec<List>,
+    table: Option<TextTable>,
+    code: Option<String>,
+    links: Vec<String>,
+    open_links: Vec<(String, usize)>,
+}
+
+impl<T> Md2PlainRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2PlainOptions) -> Self {
+        Self {
+            layout: Layout::new(options.width),
+            options,
+            userdata,
+            lists: Vec::new(),
+            table: None,
+            code: None,
+            links: Vec::new(),
+            open_links: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn sink(&mut self) -> &mut String {
+        layout::sink(
+            self.code.as_mut(),
+            self.table.as_mut(),
+            self.layout.inline(),
+        )
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Layout::new(self.options.width);
+                self.links.clear();
+            }
+            MdBlockDetail::Quote => {
+                self.layout.separate();
+                self.layout.push_prefix("  ", "  ");
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List { next: None });
+            }
+            MdBlockDetail::Ol {
+                start, is_tight, ..
+            } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List { next: Some(start) });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.layout.separate();
+                let mut marker = match self.lists.last_mut() {
+                    Some(List {
+                        next: Some(next), ..
+                    }) => {
+                        *next += 1;
+                        format!("{}. ", *next - 1)
+                    }
+                    _ => format!("{} ", self.options.bullet),
+                };
+                if is_task {
+                    marker.push('[');
+                    marker.push(task_mark as u8 as char);
+                    marker.push_str("] ");
+                }
+                let rest = " ".repeat(marker.chars().count());
+                self.layout.push_prefix(&marker, &rest);
+            }
+            MdBlockDetail::Hr
+            | MdBlockDetail::H { .. }
+            | MdBlockDetail::Html
+            | MdBlockDetail::P => self.layout.separate(),
+            MdBlockDetail::Code { .. } => {
+                self.layout.separate();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.layout.separate();
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout.flush();
+                if !self.links.is_empty() {
+                    self.layout.blank();
+                    for (idx, url) in self.links.iter().enumerate() {
+                        self.layout.line(&format!(
+                            "[{}] {}",
+                            idx + 1,
+                            url
+                        ));
+                    }
+                }
+            }
+            MdBlockDetail::Quote | MdBlockDetail::Li { .. } => {
+                self.layout.pop_prefix();
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.lists.pop();
+            }
+            MdBlockDetail::Code { .. } => {
+                let code = self.code.take().unwrap_or_default();
+                for line in code.lines() {
+                    self.layout.line(line);
+                }
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    for line in table.lines("  ", Some('-')) {
+                        self.layout.line(&line);
+                    }
+                }
+            }
+            _ => self.layout.flush(),
+        }
+    }
+
+    fn enter_link(&mut self, url: String) {
+        let start = self.sink().len();
+        self.open_links.push((url, start));
+    }
+
+    /// Close a link or image, adding a footnote reference unless the
+    /// text is the URL itself, as with autolinks.
+    fn leave_link(&mut self) {
+        let (url, start) = match self.open_links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        if !self.options.link_footnotes || url.is_empty() {
+            return;
+        }
+        let text = self.sink().get(start..).unwrap_or("").trim();
+        if text == url || url.strip_prefix("mailto:") == Some(text) {
+            return;
+        }
+        let num = match self.links.iter().position(|u| *u == url) {
+            Some(idx) => idx + 1,
+            None => {
+                self.links.push(url);
+                self.links.len()
+            }
+        };
+        self.sink().push_str(&format!("[{}]", num));
+    }
+}
+
+impl<T> MdDetailParser for Md2PlainRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        match detail {
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode())
+            }
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if let MdSpantype::A | MdSpantype::Img = spantype {
+            self.leave_link();
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        let in_cell = self.code.is_none() && self.table.is_some();
+        let decoded;
+        let text = match texttype {
+            MdTexttype::Html => return Ok(()),
+            MdTexttype::Nullchar => "\u{fffd}",
+            MdTexttype::Br if in_cell => " ",
+            MdTexttype::Br => "\n",
+            MdTexttype::Softbr if self.code.is_none() => " ",
+            MdTexttype::Entity => {
+                decoded = entity_decode(text);
+                decoded.as_deref().unwrap_or(text)
+            }
+            _ => text,
+        };
+        self.sink().push_str(text);
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2PlainRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2PlainRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2PlainRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn plain(userdata: T, options: Md2PlainOptions) -> Self {
+        Self(Md2PlainRenderer::new(userdata, options), PhantomData)
+    }
+}
@@ -0,0 +1,472 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::TextTable;
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2RoffOptions {
+    title: Option<(String, String)>,
+}
+
+impl Md2RoffOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the page with a `.TH` line for the manual of `name` in
+    /// `section`, e.g. `title("grep", "1")`.
+    #[inline]
+    pub fn title(mut self, name: &str, section: &str) -> Self {
+        self.title = Some((name.to_owned(), section.to_owned()));
+        self
+    }
+}
+
+/// Fonts of the spans, the innermost last.
+#[derive(Clone, Copy, PartialEq)]
+enum Font {
+    Roman,
+    Bold,
+    Italic,
+    BoldItalic,
+    Code,
+}
+
+impl Font {
+    fn escape(self) -> &'static str {
+        match self {
+            Font::Roman => "\\fR",
+            Font::Bold => "\\fB",
+            Font::Italic => "\\fI",
+            Font::BoldItalic => "\\f(BI",
+            Font::Code => "\\f(CW",
+        }
+    }
+}
+
+/// Quote a macro argument, escaping its backslashes, hyphens and
+/// quotes, and a leading dot or quote.
+fn quote_arg(arg: &str) -> String {
+    let mut quoted = String::from("\"");
+    if arg.starts_with(['.', '\'']) {
+        quoted.push_str("\\&");
+    }
+    for ch in arg.chars() {
+        match ch {
+            '\\' => quoted.push_str("\\e"),
+            '-' => quoted.push_str("\\-"),
+            '"' => quoted.push_str("\\(dq"),
+            _ => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+struct List {
+    next: Option<u32>,
+}
+
+/// Renders Markdown as a man(7) page: `.SH`/`.SS` headings, `.PP`
+/// paragraphs, `.IP` list items, `.nf` code blocks and tbl tables.
+pub struct Md2RoffRenderer<T> {
+    pub options: Md2RoffOptions,
+    pub userdata: T,
+    lists: Vec<List>,
+    /// The list item just started, whose first paragraph goes on its
+    /// `.IP` line.
+    item_start: bool,
+    fonts: Vec<Font>,
+    /// The table being output with tbl, whose format line is only
+    /// known once the cells of the first row are.
+    table: Option<TextTable>,
+    /// URL and text of the open links.
+    links: Vec<(String, String)>,
+    in_code: bool,
+    /// Whether the output is at the start of a line.
+    bol: bool,
+}
+
+impl<T> Md2RoffRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2RoffOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            lists: Vec::new(),
+            item_start: false,
+            fonts: Vec::new(),
+            table: None,
+            links: Vec::new(),
+            in_code: false,
+            bol: true,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn write(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        self.bol = text.ends_with('\n');
+        match self.table.as_mut().and_then(TextTable::cell) {
+            Some(cell) => cell.push_str(text),
+            None => self.userdata.render_append(text),
+        }
+    }
+
+    /// Write text, escaping backslashes and hyphens, and the dots and
+    /// quotes that would start a request at the start of a line.
+    fn write_escaped(&mut self, text: &str) {
+        let mut escaped = String::with_capacity(text.len());
+        let mut bol = self.bol;
+        for ch in text.chars() {
+            if bol && (ch == '.' || ch == '\'') {
+                escaped.push_str("\\&");
+            }
+            match ch {
+                '\\' => escaped.push_str("\\e"),
+                '-' => escaped.push_str("\\-"),
+                _ => escaped.push(ch),
+            }
+            bol = ch == '\n';
+        }
+        self.write(&escaped);
+    }
+
+    /// Write a request on a line of its own.
+    fn request(&mut self, request: &str) {
+        if !self.bol {
+            self.write("\n");
+        }
+        self.write(request);
+        self.write("\n");
+    }
+
+    /// Start a paragraph: on the `.IP` line of a new list item,
+    /// indented as the item when in a list, or a plain one.
+    fn paragraph(&mut self) {
+        if std::mem::take(&mut self.item_start) {
+            return;
+        }
+        match self.lists.is_empty() {
+            true => self.request(".PP"),
+            false => self.request(".IP"),
+        }
+    }
+
+    fn font(&self) -> Font {
+        self.fonts.last().copied().unwrap_or(Font::Roman)
+    }
+
+    fn enter_font(&mut self, font: Font) {
+        let font = match (self.font(), font) {
+            (Font::Bold, Font::Italic)
+            | (Font::Italic, Font::Bold) => Font::BoldItalic,
+            _ => font,
+        };
+        self.fonts.push(font);
+        self.write(font.escape());
+    }
+
+    fn leave_font(&mut self) {
+        self.fonts.pop();
+        self.write(self.font().escape());
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                if let Some((name, section)) =
+                    self.options.title.clone()
+                {
+                    let th = format!(
+                        ".TH {} {}",
+                        quote_arg(&name),
+                        quote_arg(&section)
+                    );
+                    self.request(&th);
+                }
+            }
+            MdBlockDetail::Quote => {
+                self.paragraph();
+                self.request(".RS");
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                // Nested lists are indented relative to their item.
+                if !self.lists.is_empty() {
+                    self.item_start = false;
+                    self.request(".RS");
+                }
+                let next = match detail {
+                    MdBlockDetail::Ol { start, .. } => Some(start),
+                    _ => None,
+                };
+                self.lists.push(List { next });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                let tag = match self.lists.last_mut() {
+                    Some(List { next: Some(next) }) => {
+                        *next += 1;
+                        format!("{}.", *next - 1)
+                    }
+                    _ => "\\(bu".to_owned(),
+                };
+                let tag = match (is_task, task_mark as u8) {
+                    (false, _) => tag,
+                    (true, b' ') => format!("{} [ ]", tag),
+                    (true, _) => format!("{} [x]", tag),
+                };
+                let indent = if is_task { 8 } else { 4 };
+                self.request(&format!(".IP \"{}\" {}", tag, indent));
+                self.item_start = true;
+            }
+            MdBlockDetail::Hr => {
+                self.paragraph();
+                self.request("\\l'\\n(.lu'");
+            }
+            MdBlockDetail::H { level } => {
+                self.item_start = false;
+                match level {
+                    1 | 2 => self.request(".SH"),
+                    _ => self.request(".SS"),
+                }
+            }
+            MdBlockDetail::Code { .. } => {
+                self.paragraph();
+                self.request(".nf");
+                self.write(Font::Code.escape());
+                self.write("\n");
+                self.in_code = true;
+            }
+            MdBlockDetail::Html => (),
+            MdBlockDetail::P => self.paragraph(),
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.paragraph();
+                self.request(".TS");
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    // The first cell starts a line of tbl data.
+                    self.bol = table.cell().is_none();
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Quote => self.request(".RE"),
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.lists.pop();
+                if !self.lists.is_empty() {
+                    self.request(".RE");
+                }
+            }
+            MdBlockDetail::Li { .. } => self.item_start = false,
+            MdBlockDetail::Code { .. } => {
+                self.in_code = false;
+                self.request(Font::Roman.escape());
+                self.request(".fi");
+            }
+            MdBlockDetail::Table { .. } => {
+                if let Some(table) = self.table.take() {
+                    self.write_table(table);
+                }
+            }
+            MdBlockDetail::Doc
+            | MdBlockDetail::H { .. }
+            | MdBlockDetail::P
+                if !self.bol =>
+            {
+                self.write("\n")
+            }
+            _ => (),
+        }
+    }
+
+    fn write_table(&mut self, table: TextTable) {
+        let format: Vec<&str> = table
+            .aligns()
+            .iter()
+            .map(|align| match align {
+                MdAlign::Center => "c",
+                MdAlign::Right => "r",
+                MdAlign::Left | MdAlign::Default => "l",
+            })
+            .collect();
+        let mut out = "allbox tab(\t);\n".to_owned();
+        out.push_str(&format!("{}.\n", format.join(" ")));
+        for (idx, row) in table.rows().iter().enumerate() {
+            out.push_str(&row.join("\t"));
+            out.push('\n');
+            if idx + 1 == table.head_rows() {
+                out.push_str("_\n");
+            }
+        }
+        self.write(&out);
+        self.request(".TE");
+    }
+
+    fn enter_link(&mut self, url: String) {
+        self.links.push((url, String::new()));
+    }
+
+    /// Close a link, following its text with the URL unless the text
+    /// is the URL itself, as with autolinks.
+    fn leave_link(&mut self) {
+        let (url, text) = match self.links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        if url.is_empty()
+            || text == url
+            || url.strip_prefix("mailto:") == Some(&text)
+        {
+            return;
+        }
+        self.write(" <");
+        self.write_escaped(&url);
+        self.write(">");
+    }
+}
+
+impl<T> MdDetailParser for Md2RoffRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        match detail {
+            MdSpanDetail::Strong => self.enter_font(Font::Bold),
+            MdSpanDetail::Em | MdSpanDetail::U => {
+                self.enter_font(Font::Italic)
+            }
+            MdSpanDetail::Code => self.enter_font(Font::Code),
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(href.decode())
+            }
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(src.decode())
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(target.decode())
+            }
+            MdSpanDetail::Del
+            | MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => (),
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        match spantype {
+            MdSpantype::Strong
+            | MdSpantype::Em
+            | MdSpantype::U
+            | MdSpantype::Code => self.leave_font(),
+            MdSpantype::A
+            | MdSpantype::Img
+            | MdSpantype::Wikilink => self.leave_link(),
+            _ => (),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        for (_, link_text) in self.links.iter_mut() {
+            link_text.push_str(text);
+        }
+        match texttype {
+            MdTexttype::Html => (),
+            _ if self.in_code => self.write_escaped(text),
+            MdTexttype::Nullchar => self.write("\u{fffd}"),
+            MdTexttype::Br if self.table.is_some() => self.write(" "),
+            MdTexttype::Br => self.request(".br"),
+            MdTexttype::Softbr if self.table.is_some() => {
+                self.write(" ")
+            }
+            MdTexttype::Softbr => self.write("\n"),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2RoffRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2RoffRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2RoffRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn roff(userdata: T, options: Md2RoffOptions) -> Self {
+        Self(Md2RoffRenderer::new(userdata, options), PhantomData)
+    }
+}
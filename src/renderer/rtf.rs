@@ -0,0 +1,432 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+/// Font size of each heading level, in half points.
+const HEADING_SIZES: [u32; 6] = [36, 32, 28, 26, 24, 24];
+
+/// Width of the text, in twips, shared by the columns of tables.
+const TEXT_WIDTH: u32 = 9360;
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2RtfOptions {
+    font: Option<String>,
+    monospace_font: Option<String>,
+}
+
+impl Md2RtfOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Font of the text, Helvetica by default.
+    #[inline]
+    pub fn font(mut self, name: &str) -> Self {
+        self.font = Some(name.to_owned());
+        self
+    }
+
+    /// Font of code, Courier New by default.
+    #[inline]
+    pub fn monospace_font(mut self, name: &str) -> Self {
+        self.monospace_font = Some(name.to_owned());
+        self
+    }
+}
+
+struct List {
+    next: Option<u32>,
+}
+
+/// Renders Markdown as an RTF document, as word processors and mail
+/// clients take it from the clipboard.
+pub struct Md2RtfRenderer<T> {
+    pub options: Md2RtfOptions,
+    pub userdata: T,
+    lists: Vec<List>,
+    quote_depth: u32,
+    /// Marker of the list item just started, for its first paragraph.
+    item_marker: Option<String>,
+    /// Whether a paragraph is open, to be ended with `\par`.
+    in_para: bool,
+    /// Whether in a table cell, ended with `\cell` instead.
+    in_cell: bool,
+    /// Column count of the current table.
+    columns: u32,
+    in_header_row: bool,
+    code: Option<String>,
+}
+
+impl<T> Md2RtfRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2RtfOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            lists: Vec::new(),
+            quote_depth: 0,
+            item_marker: None,
+            in_para: false,
+            in_cell: false,
+            columns: 0,
+            in_header_row: false,
+            code: None,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn write(&mut self, text: &str) {
+        self.userdata.render_append(text);
+    }
+
+    /// Open a paragraph for inline content if none is, as in the
+    /// items of tight lists.
+    fn inline(&mut self) {
+        if !self.in_para && !self.in_cell {
+            self.paragraph("");
+        }
+    }
+
+    fn write_escaped(&mut self, text: &str) {
+        self.inline();
+        let escaped = escape(text);
+        self.write(&escaped);
+    }
+
+    /// Start a paragraph with `style`, indented for the enclosing
+    /// quotes and lists, and carrying the marker of a new list item.
+    fn paragraph(&mut self, style: &str) {
+        self.end_paragraph();
+        let indent =
+            720 * self.quote_depth + 360 * self.lists.len() as u32;
+        let mut start = format!("{{\\pard\\sa180{}", style);
+        if indent > 0 {
+            start.push_str(&format!("\\li{}", indent));
+        }
+        match self.item_marker.take() {
+            Some(marker) => start.push_str(&format!(
+                "\\fi-360\\tx{} {}\\tab ",
+                indent, marker
+            )),
+            None => start.push(' '),
+        }
+        self.write(&start);
+        self.in_para = true;
+    }
+
+    fn end_paragraph(&mut self) {
+        if std::mem::take(&mut self.in_para) {
+            self.write("\\par}\n");
+        }
+    }
+
+    fn header(&mut self) {
+        let font =
+            self.options.font.as_deref().unwrap_or("Helvetica");
+        let mono = self
+            .options
+            .monospace_font
+            .as_deref()
+            .unwrap_or("Courier New");
+        let mut header = format!(
+            "{{\\rtf1\\ansi\\ansicpg1252\\deff0\\uc1\
+             {{\\fonttbl{{\\f0\\fswiss\\fcharset0 {};}}\
+             {{\\f1\\fmodern\\fcharset0 {};}}}}\
+             {{\\colortbl;\\red5\\green99\\blue193;}}\
+             {{\\stylesheet{{\\s0 Normal;}}",
+            escape(font),
+            escape(mono)
+        );
+        for (level, size) in HEADING_SIZES.iter().enumerate() {
+            header.push_str(&format!(
+                "{{\\s{}\\sbasedon0\\snext0\\b\\fs{} heading {};}}",
+                level + 1,
+                size,
+                level + 1
+            ));
+        }
+        header.push_str("}\n");
+        self.write(&header);
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.header(),
+            MdBlockDetail::Quote => {
+                self.end_paragraph();
+                self.quote_depth += 1;
+            }
+            MdBlockDetail::Ul { .. } => {
+                self.end_paragraph();
+                self.lists.push(List { next: None });
+            }
+            MdBlockDetail::Ol { start, .. } => {
+                self.end_paragraph();
+                self.lists.push(List { next: Some(start) });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.end_paragraph();
+                let marker = match (is_task, task_mark as u8) {
+                    (true, b' ') => escape("☐"),
+                    (true, _) => escape("☑"),
+                    (false, _) => match self.lists.last_mut() {
+                        Some(List { next: Some(number) }) => {
+                            *number += 1;
+                            format!("{}.", *number - 1)
+                        }
+                        _ => "\\bullet".to_owned(),
+                    },
+                };
+                self.item_marker = Some(marker);
+            }
+            MdBlockDetail::Hr => {
+                self.paragraph("\\brdrb\\brdrs\\brdrw10\\brsp20");
+                self.end_paragraph();
+            }
+            MdBlockDetail::H { level } => {
+                let size = HEADING_SIZES[level as usize - 1];
+                let style = format!(
+                    "\\s{}\\sb240\\keepn\\b\\fs{}",
+                    level, size
+                );
+                self.paragraph(&style);
+            }
+            MdBlockDetail::P => self.paragraph(""),
+            MdBlockDetail::Html => self.end_paragraph(),
+            MdBlockDetail::Code { .. } => {
+                self.end_paragraph();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { col_count, .. } => {
+                self.end_paragraph();
+                self.columns = col_count.max(1);
+            }
+            MdBlockDetail::Thead => self.in_header_row = true,
+            MdBlockDetail::Tbody => self.in_header_row = false,
+            MdBlockDetail::Tr => {
+                let width = TEXT_WIDTH / self.columns;
+                let mut row = "\\trowd\\trgaph108".to_owned();
+                for col in 1..=self.columns {
+                    row.push_str(&format!(
+                        "\\clbrdrt\\brdrs\\clbrdrl\\brdrs\
+                         \\clbrdrb\\brdrs\\clbrdrr\\brdrs\\cellx{}",
+                        width * col
+                    ));
+                }
+                row.push('\n');
+                self.write(&row);
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                let align = match align {
+                    MdAlign::Center => "\\qc",
+                    MdAlign::Right => "\\qr",
+                    MdAlign::Left | MdAlign::Default => "\\ql",
+                };
+                let group = match self.in_header_row {
+                    true => "{\\b ",
+                    false => "{",
+                };
+                let cell = format!("\\pard\\intbl{}{}", align, group);
+                self.write(&cell);
+                self.in_cell = true;
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.end_paragraph();
+                self.write("}\n");
+            }
+            MdBlockDetail::Quote => {
+                self.end_paragraph();
+                self.quote_depth -= 1;
+            }
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.end_paragraph();
+                self.lists.pop();
+            }
+            MdBlockDetail::Code { .. } => {
+                let code = self.code.take().unwrap_or_default();
+                self.paragraph("\\f1\\fs20");
+                let lines: Vec<String> =
+                    code.lines().map(escape).collect();
+                let lines = lines.join("\\line ");
+                self.write(&lines);
+                self.end_paragraph();
+            }
+            MdBlockDetail::Table { .. } => self.write("\\pard\n"),
+            MdBlockDetail::Tr => self.write("\\row\n"),
+            MdBlockDetail::Th { .. } | MdBlockDetail::Td { .. } => {
+                self.write("}\\cell\n");
+                self.in_cell = false;
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            _ => self.end_paragraph(),
+        }
+    }
+
+    /// Open a `HYPERLINK` field, whose result is the text of the link.
+    fn enter_link(&mut self, url: &str) {
+        let url = escape(&url.replace('"', "%22"));
+        let field = format!(
+            "{{\\field{{\\*\\fldinst{{HYPERLINK \"{}\"}}}}\
+             {{\\fldrslt{{\\ul\\cf1 ",
+            url
+        );
+        self.write(&field);
+    }
+}
+
+/// Escape the RTF special characters of `text`, and anything beyond
+/// ASCII as `\uN?`, N being a signed UTF-16 code unit.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '\\' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(ch);
+            }
+            '\t' => escaped.push_str("\\tab "),
+            '\n' => escaped.push_str("\\line "),
+            ch if ch.is_ascii() => escaped.push(ch),
+            ch => {
+                let mut units = [0; 2];
+                for unit in ch.encode_utf16(&mut units) {
+                    escaped
+                        .push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    escaped
+}
+
+impl<T> MdDetailParser for Md2RtfRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        self.inline();
+        match detail {
+            MdSpanDetail::Em => self.write("{\\i "),
+            MdSpanDetail::Strong => self.write("{\\b "),
+            MdSpanDetail::U => self.write("{\\ul "),
+            MdSpanDetail::Del => self.write("{\\strike "),
+            MdSpanDetail::Code
+            | MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => self.write("{\\f1 "),
+            MdSpanDetail::A { href, .. } => {
+                self.enter_link(&href.decode())
+            }
+            // Images are left to their description, linking to them.
+            MdSpanDetail::Img { src, .. } => {
+                self.enter_link(&src.decode())
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(&target.decode())
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        match spantype {
+            MdSpantype::A
+            | MdSpantype::Img
+            | MdSpantype::Wikilink => self.write("}}}"),
+            _ => self.write("}"),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(code) = self.code.as_mut() {
+            code.push_str(text);
+            return Ok(());
+        }
+        match texttype {
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => self.write_escaped("\u{fffd}"),
+            MdTexttype::Br => {
+                self.inline();
+                self.write("\\line ")
+            }
+            MdTexttype::Softbr => self.write(" "),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2RtfRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2RtfRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2RtfRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn rtf(userdata: T, options: Md2RtfOptions) -> Self {
+        Self(Md2RtfRenderer::new(userdata, options), PhantomData)
+    }
+}
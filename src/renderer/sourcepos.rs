@@ -0,0 +1,363 @@
+use crate::md4c::*;
+use crate::renderer::detail::{impl_md_parser, MdDetailParser};
+use std::num::NonZeroI32;
+use std::ops::Range;
+
+pub(crate) fn line_starts(input: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(input.match_indices('\n').map(|(off, _)| off + 1))
+        .collect()
+}
+
+pub(crate) fn line_col(
+    line_starts: &[usize],
+    offset: usize,
+) -> (usize, usize) {
+    let line = match line_starts.binary_search(&offset) {
+        Ok(line) => line,
+        Err(line) => line - 1,
+    };
+    (line + 1, offset - line_starts[line] + 1)
+}
+
+/// 1-based line and column of an offset of `input`, the column in
+/// characters rather than bytes.
+pub(crate) fn line_char_col(
+    input: &str,
+    line_starts: &[usize],
+    offset: usize,
+) -> (usize, usize) {
+    let (line, column) = line_col(line_starts, offset);
+    let start = line_starts[line - 1];
+    match input.get(start..offset) {
+        Some(before) => (line, before.chars().count() + 1),
+        None => (line, column),
+    }
+}
+
+/// Offset of `text` in `input`, if it is a slice of it. md4c passes
+/// some texts (line breaks, replacement characters, ...) from static
+/// buffers instead.
+pub(crate) fn slice_offset(input: &str, text: &str) -> Option<usize> {
+    let base = input.as_ptr() as usize;
+    let ptr = text.as_ptr() as usize;
+    if ptr >= base && ptr + text.len() <= base + input.len() {
+        Some(ptr - base)
+    } else {
+        None
+    }
+}
+
+struct OpenBlock {
+    slot: usize,
+    detail: MdBlockDetail,
+    range: Option<Range<usize>>,
+    /// End of the input consumed before the block was entered.
+    after: usize,
+    /// Offset of the marker of a list item.
+    marker: Option<usize>,
+}
+
+/// First pass: guesses the input range of every block, in the order
+/// md4c enters them. md4c does not report block positions, so they
+/// are derived from the text slices inside each block, widened to the
+/// block's own markers; blocks without any text take the next
+/// non-blank line.
+struct Collector<'a> {
+    input: &'a str,
+    blocks: Vec<Range<usize>>,
+    open: Vec<OpenBlock>,
+    cursor: usize,
+    /// End of the last list item marker, for the items nested on its
+    /// line.
+    marker_end: usize,
+}
+
+impl<'a> Collector<'a> {
+    fn cover(&mut self, range: &Range<usize>) {
+        for block in self.open.iter_mut() {
+            block.range = Some(match block.range.take() {
+                Some(r) => {
+                    r.start.min(range.start)..r.end.max(range.end)
+                }
+                None => range.clone(),
+            });
+        }
+        self.cursor = self.cursor.max(range.end);
+    }
+
+    fn line_end(&self, offset: usize) -> usize {
+        let data = self.input.as_bytes();
+        let mut end = offset;
+        while end < data.len() && data[end] != b'\n' {
+            end += 1;
+        }
+        while end > offset && data[end - 1].is_ascii_whitespace() {
+            end -= 1;
+        }
+        end
+    }
+
+    fn next_line(&self, offset: usize) -> Option<usize> {
+        self.input[offset..].find('\n').map(|nl| offset + nl + 1)
+    }
+
+    /// Move `offset` back over `marks` and blanks on its own line, then
+    /// forward over the blanks again.
+    fn back_over(&self, mut offset: usize, marks: &[u8]) -> usize {
+        let data = self.input.as_bytes();
+        while offset > 0
+            && (data[offset - 1] == b' '
+                || data[offset - 1] == b'\t'
+                || marks.contains(&data[offset - 1]))
+        {
+            offset -= 1;
+        }
+        while offset < data.len()
+            && (data[offset] == b' ' || data[offset] == b'\t')
+        {
+            offset += 1;
+        }
+        offset
+    }
+
+    /// The list item marker after `offset`, past blanks and quote
+    /// marks, as range.
+    fn list_marker(&self, offset: usize) -> Option<Range<usize>> {
+        let data = self.input.as_bytes();
+        let mut start = offset.min(data.len());
+        while start < data.len()
+            && (data[start].is_ascii_whitespace()
+                || data[start] == b'>')
+        {
+            start += 1;
+        }
+        let digits = data[start..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        match data.get(start + digits) {
+            Some(b'-' | b'+' | b'*') if digits == 0 => {
+                Some(start..start + 1)
+            }
+            Some(b'.' | b')') if (1..=9).contains(&digits) => {
+                Some(start..start + digits + 1)
+            }
+            _ => None,
+        }
+    }
+
+    fn next_nonblank(&self, offset: usize) -> Range<usize> {
+        let data = self.input.as_bytes();
+        let mut start = offset.min(data.len());
+        while start < data.len() && data[start].is_ascii_whitespace()
+        {
+            start += 1;
+        }
+        start..self.line_end(start).max(start)
+    }
+
+    /// Line at or after `offset` holding a run of at least three
+    /// `fence` characters, as range of its content.
+    fn fence_line(
+        &self,
+        offset: usize,
+        fence: u8,
+    ) -> Option<Range<usize>> {
+        let run = [fence; 3];
+        let mut line = offset;
+        while line < self.input.len() {
+            let end = self.line_end(line);
+            let content = &self.input.as_bytes()[line..end];
+            if let Some(pos) =
+                content.windows(3).position(|w| w == run)
+            {
+                return Some(line + pos..end);
+            }
+            line = self.next_line(line)?;
+        }
+        None
+    }
+
+    /// End of the fence closing a code block whose content ends at
+    /// `after`: the first line past it that is not blank, if it is
+    /// only a run of at least `length` `fence` characters. A fence
+    /// left open by the end of its container has none.
+    fn closing_fence(
+        &self,
+        after: usize,
+        fence: u8,
+        length: usize,
+    ) -> Option<usize> {
+        let mut line = self.next_line(after)?;
+        while line < self.input.len() {
+            let end = self.line_end(line);
+            // Blank but for the marks of the containers.
+            let content = self.input[line..end]
+                .trim_start_matches([' ', '\t', '>']);
+            if !content.is_empty() {
+                let closes = content.len() >= length
+                    && content.bytes().all(|b| b == fence);
+                return closes.then_some(end);
+            }
+            line = self.next_line(line)?;
+        }
+        None
+    }
+
+    fn resolve(&self, block: &OpenBlock) -> Range<usize> {
+        let data = self.input.as_bytes();
+        let range = match &block.detail {
+            MdBlockDetail::Code { fence_char, .. }
+                if *fence_char != 0 =>
+            {
+                let fence = *fence_char as u8;
+                let open = match self.fence_line(block.after, fence) {
+                    Some(open) => open,
+                    None => {
+                        return block.range.clone().unwrap_or(0..0)
+                    }
+                };
+                let after =
+                    block.range.as_ref().map_or(open.end, |r| r.end);
+                let length = data[open.clone()]
+                    .iter()
+                    .take_while(|&&b| b == fence)
+                    .count();
+                let close = self.closing_fence(after, fence, length);
+                return open.start..close.unwrap_or(after);
+            }
+            _ => match &block.range {
+                Some(range) => range.clone(),
+                None => return self.next_nonblank(block.after),
+            },
+        };
+
+        match &block.detail {
+            // Up to the end of the last line, past any closing inline
+            // markup.
+            MdBlockDetail::Quote => {
+                self.back_over(range.start, b">")
+                    ..self.line_end(range.end).max(range.end)
+            }
+            MdBlockDetail::Li { .. } => {
+                let start = match block.marker {
+                    Some(marker) if marker <= range.start => marker,
+                    _ => self.back_over(
+                        range.start,
+                        b"0123456789.)-+*[]xX",
+                    ),
+                };
+                start..self.line_end(range.end).max(range.end)
+            }
+            MdBlockDetail::Ul { .. }
+            | MdBlockDetail::Ol { .. }
+            | MdBlockDetail::P => {
+                range.start..self.line_end(range.end).max(range.end)
+            }
+            MdBlockDetail::Tr => {
+                let start = self.back_over(range.start, b"|");
+                start..self.line_end(range.end).max(range.end)
+            }
+            MdBlockDetail::Code { .. } => {
+                // Indented code, include the indentation.
+                let mut start = range.start;
+                while start > 0 && data[start - 1] == b' ' {
+                    start -= 1;
+                }
+                start..range.end
+            }
+            MdBlockDetail::H { .. } => {
+                let start = self.back_over(range.start, b"#");
+                if data[start] == b'#' {
+                    return start
+                        ..self.line_end(range.end).max(range.end);
+                }
+                // Setext heading, include the underline.
+                match self.next_line(range.end) {
+                    Some(line) => {
+                        let end = self.line_end(line);
+                        let underline = self.input[line..end].trim();
+                        if !underline.is_empty()
+                            && (underline.bytes().all(|b| b == b'=')
+                                || underline
+                                    .bytes()
+                                    .all(|b| b == b'-'))
+                        {
+                            start..end
+                        } else {
+                            start..range.end
+                        }
+                    }
+                    None => start..range.end,
+                }
+            }
+            _ => range,
+        }
+    }
+}
+
+impl<'a> MdDetailParser for Collector<'a> {
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        let mut marker = None;
+        if let MdBlockDetail::Li { .. } = detail {
+            let from = self.cursor.max(self.marker_end);
+            if let Some(range) = self.list_marker(from) {
+                marker = Some(range.start);
+                self.marker_end = range.end;
+            }
+        }
+        self.open.push(OpenBlock {
+            slot: self.blocks.len(),
+            detail,
+            range: None,
+            after: self.cursor,
+            marker,
+        });
+        self.blocks.push(0..0);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, _detail: MdBlockDetail) -> MdResult {
+        if let Some(block) = self.open.pop() {
+            let range = self.resolve(&block);
+            self.cover(&range);
+            self.blocks[block.slot] = range;
+        }
+        Ok(())
+    }
+
+    fn enter_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        Ok(())
+    }
+
+    fn leave_span(&mut self, _detail: MdSpanDetail) -> MdResult {
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(offset) = slice_offset(self.input, text) {
+            self.cover(&(offset..offset + text.len()));
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl['a] Collector<'a>);
+
+/// Input ranges of the blocks of `input`, in the order md4c enters
+/// them.
+pub(crate) fn block_ranges(
+    input: &str,
+    parser_flags: &MdParserFlags,
+) -> Result<Vec<Range<usize>>, NonZeroI32> {
+    let mut collector = Collector {
+        input,
+        blocks: Vec::new(),
+        open: Vec::new(),
+        cursor: 0,
+        marker_end: 0,
+    };
+    collector.parse(input, parser_flags)?;
+    Ok(collector.blocks)
+}
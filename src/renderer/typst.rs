@@ -0,0 +1,778 @@
+use std::marker::PhantomData;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::layout::{self, Layout, TextTable};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2TypstOptions {
+    numbered_headings: bool,
+}
+
+impl Md2TypstOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start the document with a rule numbering the headings.
+    #[inline]
+    pub fn numbered_headings(mut self) -> Self {
+        self.numbered_headings = true;
+        self
+    }
+}
+
+struct List {
+    /// Next number, for ordered lists.
+    number: Option<u32>,
+}
+
+/// Renders Markdown as Typst markup.
+pub struct Md2TypstRenderer<T> {
+    pub options: Md2TypstOptions,
+    pub userdata: T,
+    layout: Layout,
+    lists: Vec<List>,
+    table: Option<TextTable>,
+    code: Option<String>,
+    /// Text of the current code span or math span.
+    raw: Option<String>,
+    /// Closing marks of the open emphasis spans.
+    closers: Vec<&'static str>,
+    /// Start in the sink of each open link or image, with its
+    /// destination and title.
+    open_links: Vec<(String, String, usize)>,
+    image_nesting_level: usize,
+}
+
+impl<T> Md2TypstRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2TypstOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            layout: Layout::new(0),
+            lists: Vec::new(),
+            table: None,
+            code: None,
+            raw: None,
+            closers: Vec::new(),
+            open_links: Vec::new(),
+            image_nesting_level: 0,
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.parse(input, parser_flags)
+    }
+
+    fn sink(&mut self) -> &mut String {
+        layout::sink(
+            self.code.as_mut().or(self.raw.as_mut()),
+            self.table.as_mut(),
+            self.layout.inline(),
+        )
+    }
+
+    fn write_escaped(&mut self, text: &str) {
+        // Image descriptions end up in a string.
+        if self.image_nesting_level > 0 {
+            self.sink().push_str(text);
+            return;
+        }
+        let in_cell = self.table.is_some();
+        let sink = self.sink();
+        let mut prev = sink.chars().last();
+        let mut chars = text.chars().peekable();
+        while let Some(ch) = chars.next() {
+            let next = chars.peek().copied();
+            let line_start =
+                !in_cell && matches!(prev, None | Some('\n'));
+            let escape = match ch {
+                '\\' | '*' | '_' | '`' | '#' | '$' | '[' | ']'
+                | '<' | '@' | '~' => true,
+                // Comments, and dashes turned into en and em dashes.
+                '/' => matches!(next, Some('/' | '*')) || line_start,
+                '-' => next == Some('-') || line_start,
+                '+' | '=' if line_start => true,
+                // The dot of a number starting a line, which can come
+                // as text of its own.
+                '.' if !in_cell
+                    && matches!(next, None | Some(' ')) =>
+                {
+                    let line = &sink
+                        [sink.rfind('\n').map_or(0, |i| i + 1)..];
+                    !line.is_empty()
+                        && line.bytes().all(|b| b.is_ascii_digit())
+                }
+                _ => false,
+            };
+            if escape {
+                sink.push('\\');
+            }
+            sink.push(ch);
+            prev = Some(ch);
+        }
+    }
+
+    fn enter(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => {
+                self.layout = Layout::new(0);
+                if self.options.numbered_headings {
+                    self.layout
+                        .line("#set heading(numbering: \"1.\")");
+                }
+            }
+            MdBlockDetail::Quote => {
+                self.layout.separate();
+                self.layout.line("#quote(block: true)[");
+                // Not to start the quote with a blank line.
+                self.layout.push_prefix("", "");
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List { number: None });
+            }
+            MdBlockDetail::Ol {
+                start, is_tight, ..
+            } => {
+                self.layout.separate();
+                self.layout.push_list(is_tight);
+                self.lists.push(List {
+                    number: Some(start),
+                });
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                self.layout.separate();
+                let mut marker = match self.lists.last_mut() {
+                    Some(List {
+                        number: Some(number),
+                        ..
+                    }) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_owned(),
+                };
+                let indent = " ".repeat(marker.len());
+                // Typst has no task lists.
+                match (is_task, task_mark as u8) {
+                    (false, _) => (),
+                    (true, b' ') => marker.push_str("☐ "),
+                    (true, _) => marker.push_str("☑ "),
+                }
+                self.layout.push_prefix(&marker, &indent);
+            }
+            MdBlockDetail::Hr => {
+                self.layout.separate();
+                self.layout.line("#line(length: 100%)");
+            }
+            MdBlockDetail::H { level } => {
+                self.layout.separate();
+                let marks = "=".repeat(level as usize);
+                self.layout.inline().push_str(&format!("{} ", marks));
+            }
+            MdBlockDetail::Html | MdBlockDetail::P => {
+                self.layout.separate()
+            }
+            MdBlockDetail::Code { .. } => {
+                self.layout.separate();
+                self.code = Some(String::new());
+            }
+            MdBlockDetail::Table { head_row_count, .. } => {
+                self.layout.separate();
+                self.table =
+                    Some(TextTable::new(head_row_count as usize));
+            }
+            MdBlockDetail::Thead | MdBlockDetail::Tbody => (),
+            MdBlockDetail::Tr => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_row();
+                }
+            }
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                if let Some(table) = self.table.as_mut() {
+                    table.start_cell(align);
+                }
+            }
+        }
+    }
+
+    fn leave(&mut self, detail: MdBlockDetail) {
+        match detail {
+            MdBlockDetail::Doc => self.layout.flush(),
+            MdBlockDetail::Quote => {
+                self.layout.pop_prefix();
+                self.layout.line("]");
+            }
+            MdBlockDetail::Li { .. } => self.layout.pop_prefix(),
+            MdBlockDetail::Ul { .. } | MdBlockDetail::Ol { .. } => {
+                self.layout.flush();
+                self.layout.pop_list();
+                self.lists.pop();
+            }
+            MdBlockDetail::Code { lang, .. } => {
+                let code = self.code.take().unwrap_or_default();
+                let mut fence = "```".to_owned();
+                while code.contains(&fence) {
+                    fence.push('`');
+                }
+                let lang = lang.decode();
+                self.layout.line(&format!("{}{}", fence, lang));
+                for line in code.lines() {
+                    self.layout.line(line);
+                }
+                self.layout.line(&fence);
+            }
+            MdBlockDetail::Table { col_count, .. } => {
+                if let Some(table) = self.table.take() {
+                    for line in table_lines(&table, col_count) {
+                        self.layout.line(&line);
+                    }
+                }
+            }
+            _ => self.layout.flush(),
+        }
+    }
+
+    fn enter_emphasis(&mut self, mark: &'static str, func: &str) {
+        let sink = self.sink();
+        // Marks only work at word boundaries.
+        match sink.chars().last().is_some_and(char::is_alphanumeric) {
+            true => {
+                sink.push_str(&format!("#{}[", func));
+                self.closers.push("]");
+            }
+            false => {
+                sink.push_str(mark);
+                self.closers.push(mark);
+            }
+        }
+    }
+
+    fn leave_emphasis(&mut self) {
+        if let Some(closer) = self.closers.pop() {
+            self.sink().push_str(closer);
+        }
+    }
+
+    fn enter_link(&mut self, url: String, title: String) {
+        let start = self.sink().len();
+        self.open_links.push((url, title, start));
+    }
+
+    /// Close a link or image, whose text is in the sink since its
+    /// start.
+    fn leave_link(&mut self, is_image: bool) {
+        let (url, title, start) = match self.open_links.pop() {
+            Some(link) => link,
+            None => return,
+        };
+        let sink = self.sink();
+        let text = sink.split_off(start.min(sink.len()));
+        let url = string_literal(&url);
+        let call = if is_image {
+            let image = match text.is_empty() {
+                true => format!("image({})", url),
+                false => format!(
+                    "image({}, alt: {})",
+                    url,
+                    string_literal(&text)
+                ),
+            };
+            match title.is_empty() {
+                true => format!("#{}", image),
+                false => {
+                    let mut caption = String::new();
+                    escape_into(&mut caption, &title);
+                    format!(
+                        "#figure({}, caption: [{}])",
+                        image, caption
+                    )
+                }
+            }
+        } else if text.is_empty() {
+            format!("#link({})", url)
+        } else {
+            format!("#link({})[{}]", url, text)
+        };
+        sink.push_str(&call);
+    }
+
+    fn leave_raw(&mut self, spantype: MdSpantype) {
+        let raw = self.raw.take().unwrap_or_default();
+        let out = match spantype {
+            MdSpantype::Latexmath => {
+                format!("${}$", latex_to_typst(&raw))
+            }
+            MdSpantype::LatexmathDisplay => {
+                format!("$ {} $", latex_to_typst(&raw))
+            }
+            // Inline raw text cannot hold a backtick.
+            _ if raw.contains('`') => {
+                format!("#raw({})", string_literal(&raw))
+            }
+            _ => format!("`{}`", raw),
+        };
+        self.sink().push_str(&out);
+    }
+}
+
+/// Escape the Typst markup characters of `text` into `out`.
+fn escape_into(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        if "\\*_`#$[]<@~".contains(ch) {
+            out.push('\\');
+        }
+        out.push(ch);
+    }
+}
+
+/// `text` as a Typst string literal.
+fn string_literal(text: &str) -> String {
+    let mut literal = String::with_capacity(text.len() + 2);
+    literal.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            _ => literal.push(ch),
+        }
+    }
+    literal.push('"');
+    literal
+}
+
+/// Lines of a `#table` call, the header rows in `table.header`.
+fn table_lines(table: &TextTable, col_count: u32) -> Vec<String> {
+    let mut lines = vec![
+        "#table(".to_owned(),
+        format!("  columns: {},", col_count),
+    ];
+    let aligns = table.aligns();
+    if !aligns.iter().all(|align| matches!(align, MdAlign::Default)) {
+        let aligns: Vec<&str> = aligns
+            .iter()
+            .map(|align| match align {
+                MdAlign::Default => "auto",
+                MdAlign::Left => "left",
+                MdAlign::Center => "center",
+                MdAlign::Right => "right",
+            })
+            .collect();
+        lines.push(format!("  align: ({},),", aligns.join(", ")));
+    }
+    for (idx, row) in table.padded_rows(0).iter().enumerate() {
+        let cells: Vec<String> = row
+            .iter()
+            .map(|cell| format!("[{}]", cell.trim()))
+            .collect();
+        match idx < table.head_rows() {
+            true => lines.push(format!(
+                "  table.header({}),",
+                cells.join(", ")
+            )),
+            false => lines.push(format!("  {},", cells.join(", "))),
+        }
+    }
+    lines.push(")".to_owned());
+    lines
+}
+
+/// Best-effort translation of LaTeX math to Typst math: commands
+/// become the Typst names or functions, braces become parentheses
+/// where Typst groups with them, and letters are spaced apart as
+/// Typst reads runs of them as names.
+fn latex_to_typst(tex: &str) -> String {
+    let mut out = String::new();
+    let mut chars = tex.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\\' => {
+                let mut name = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !ch.is_ascii_alphabetic() {
+                        break;
+                    }
+                    name.push(ch);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    match chars.next() {
+                        Some('\\') => out.push_str(" \\ "),
+                        Some(',' | ';' | ':' | ' ') => {
+                            out.push_str(" thin ")
+                        }
+                        Some(ch) => {
+                            out.push('\\');
+                            out.push(ch);
+                        }
+                        None => (),
+                    }
+                    continue;
+                }
+                let command = match name.as_str() {
+                    "frac" | "dfrac" | "tfrac" => {
+                        let num = latex_group(&mut chars);
+                        let den = latex_group(&mut chars);
+                        format!("frac({}, {})", num, den)
+                    }
+                    "sqrt" => {
+                        let index = latex_optional(&mut chars);
+                        let radicand = latex_group(&mut chars);
+                        match index {
+                            Some(index) => {
+                                format!(
+                                    "root({}, {})",
+                                    index, radicand
+                                )
+                            }
+                            None => format!("sqrt({})", radicand),
+                        }
+                    }
+                    "text" | "textrm" | "mbox" => {
+                        format!("\"{}\"", latex_raw_group(&mut chars))
+                    }
+                    "operatorname" => {
+                        format!(
+                            "op(\"{}\")",
+                            latex_raw_group(&mut chars)
+                        )
+                    }
+                    "mathrm" => {
+                        format!(
+                            "upright({})",
+                            latex_group(&mut chars)
+                        )
+                    }
+                    "mathbf" | "boldsymbol" => {
+                        format!("bold({})", latex_group(&mut chars))
+                    }
+                    "mathit" => {
+                        format!("italic({})", latex_group(&mut chars))
+                    }
+                    "mathbb" => {
+                        format!("bb({})", latex_group(&mut chars))
+                    }
+                    "mathcal" => {
+                        format!("cal({})", latex_group(&mut chars))
+                    }
+                    // Typst sizes delimiters by itself.
+                    "left" | "right" | "big" | "Big" => String::new(),
+                    name => latex_symbol(name).to_owned(),
+                };
+                if !command.is_empty() {
+                    push_spaced(&mut out, &command);
+                }
+            }
+            '^' | '_' => {
+                out.push(ch);
+                match chars.peek() {
+                    Some('{') => {
+                        let group = latex_group(&mut chars);
+                        out.push_str(&format!("({})", group));
+                    }
+                    Some('\\') => (),
+                    Some(_) => {
+                        let ch = chars.next().unwrap_or_default();
+                        out.push(ch);
+                    }
+                    None => (),
+                }
+            }
+            '{' => {
+                let mut depth = 1;
+                let mut inner = String::new();
+                for ch in chars.by_ref() {
+                    match ch {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => (),
+                    }
+                    if depth == 0 {
+                        break;
+                    }
+                    inner.push(ch);
+                }
+                push_spaced(&mut out, &latex_to_typst(&inner));
+            }
+            '"' => out.push_str("\\\""),
+            ch if ch.is_alphabetic() => {
+                // Single letters are variables, runs of them names.
+                if out.chars().last().is_some_and(char::is_alphabetic)
+                {
+                    out.push(' ');
+                }
+                out.push(ch);
+            }
+            _ => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Append `text`, apart from a preceding letter.
+fn push_spaced(out: &mut String, text: &str) {
+    let joins =
+        text.chars().next().is_some_and(char::is_alphanumeric);
+    if joins && out.chars().last().is_some_and(char::is_alphanumeric)
+    {
+        out.push(' ');
+    }
+    out.push_str(text);
+}
+
+/// The next LaTeX argument, braced or a single character, translated.
+fn latex_group(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+) -> String {
+    latex_to_typst(&latex_raw_group(chars))
+}
+
+/// The next LaTeX argument as is.
+fn latex_raw_group(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+) -> String {
+    while chars.peek().is_some_and(|ch| ch.is_whitespace()) {
+        chars.next();
+    }
+    match chars.next() {
+        Some('{') => {
+            let mut depth = 1;
+            let mut group = String::new();
+            for ch in chars.by_ref() {
+                match ch {
+                    '{' => depth += 1,
+                    '}' => depth -= 1,
+                    _ => (),
+                }
+                if depth == 0 {
+                    break;
+                }
+                group.push(ch);
+            }
+            group
+        }
+        Some(ch) => ch.to_string(),
+        None => String::new(),
+    }
+}
+
+/// The optional `[…]` argument of a LaTeX command, translated.
+fn latex_optional(
+    chars: &mut std::iter::Peekable<std::str::Chars>,
+) -> Option<String> {
+    if chars.peek() != Some(&'[') {
+        return None;
+    }
+    chars.next();
+    let arg: String =
+        chars.by_ref().take_while(|&ch| ch != ']').collect();
+    Some(latex_to_typst(&arg))
+}
+
+/// Typst name of a LaTeX symbol command; Greek letters and most
+/// operators share their names.
+fn latex_symbol(name: &str) -> &str {
+    match name {
+        "cdot" => "dot.op",
+        "cdots" => "dots.c",
+        "ldots" | "dots" => "dots",
+        "le" | "leq" => "<=",
+        "ge" | "geq" => ">=",
+        "ne" | "neq" => "!=",
+        "to" | "rightarrow" => "->",
+        "leftarrow" | "gets" => "<-",
+        "Rightarrow" | "implies" => "=>",
+        "Leftrightarrow" | "iff" => "<=>",
+        "mapsto" => "|->",
+        "infty" => "infinity",
+        "pm" => "plus.minus",
+        "mp" => "minus.plus",
+        "partial" => "diff",
+        "prod" => "product",
+        "int" => "integral",
+        "iint" => "integral.double",
+        "oint" => "integral.cont",
+        "cup" => "union",
+        "cap" => "sect",
+        "notin" => "in.not",
+        "subseteq" => "subset.eq",
+        "supseteq" => "supset.eq",
+        "varepsilon" => "epsilon.alt",
+        "varphi" => "phi.alt",
+        "vartheta" => "theta.alt",
+        "langle" => "angle.l",
+        "rangle" => "angle.r",
+        "lbrace" => "{",
+        "rbrace" => "}",
+        "neg" | "lnot" => "not",
+        "land" | "wedge" => "and",
+        "lor" | "vee" => "or",
+        "circ" => "compose",
+        "sim" => "tilde.op",
+        "approx" => "approx",
+        name => name,
+    }
+}
+
+impl<T> MdDetailParser for Md2TypstRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.enter(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.leave(detail);
+        self.layout.emit(&mut self.userdata);
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        if self.image_nesting_level > 0 {
+            // Only the text of spans goes in the description.
+            if let MdSpanDetail::Img { .. } = detail {
+                self.image_nesting_level += 1;
+            }
+            return Ok(());
+        }
+        match detail {
+            MdSpanDetail::Em => self.enter_emphasis("_", "emph"),
+            MdSpanDetail::Strong => {
+                self.enter_emphasis("*", "strong")
+            }
+            MdSpanDetail::Del => self.sink().push_str("#strike["),
+            MdSpanDetail::U => self.sink().push_str("#underline["),
+            MdSpanDetail::Code
+            | MdSpanDetail::Latexmath
+            | MdSpanDetail::LatexmathDisplay => {
+                self.raw = Some(String::new())
+            }
+            MdSpanDetail::A { href, title } => {
+                self.enter_link(href.decode(), title.decode())
+            }
+            MdSpanDetail::Img { src, title } => {
+                self.enter_link(src.decode(), title.decode());
+                self.image_nesting_level += 1;
+            }
+            MdSpanDetail::Wikilink { target } => {
+                self.enter_link(target.decode(), String::new())
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        if self.image_nesting_level > 0 {
+            if spantype == MdSpantype::Img {
+                self.image_nesting_level -= 1;
+                if self.image_nesting_level == 0 {
+                    self.leave_link(true);
+                }
+            }
+            return Ok(());
+        }
+        match spantype {
+            MdSpantype::Em | MdSpantype::Strong => {
+                self.leave_emphasis()
+            }
+            MdSpantype::Del | MdSpantype::U => self.sink().push(']'),
+            MdSpantype::Code
+            | MdSpantype::Latexmath
+            | MdSpantype::LatexmathDisplay => {
+                self.leave_raw(spantype)
+            }
+            MdSpantype::A | MdSpantype::Wikilink => {
+                self.leave_link(false)
+            }
+            MdSpantype::Img => (),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if self.code.is_some() || self.raw.is_some() {
+            let text = match texttype {
+                MdTexttype::Softbr if self.raw.is_some() => " ",
+                _ => text,
+            };
+            self.sink().push_str(text);
+            return Ok(());
+        }
+        let in_cell = self.table.is_some();
+        match texttype {
+            // Typst has no use for HTML.
+            MdTexttype::Html => (),
+            MdTexttype::Nullchar => self.write_escaped("\u{fffd}"),
+            MdTexttype::Br if in_cell => self.sink().push_str("\\ "),
+            MdTexttype::Br => self.sink().push_str("\\\n"),
+            MdTexttype::Softbr if in_cell => self.sink().push(' '),
+            MdTexttype::Softbr => self.sink().push('\n'),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.write_escaped(&decoded),
+                None => self.write_escaped(text),
+            },
+            _ => self.write_escaped(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2TypstRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2TypstRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2TypstRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn typst(userdata: T, options: Md2TypstOptions) -> Self {
+        Self(Md2TypstRenderer::new(userdata, options), PhantomData)
+    }
+}
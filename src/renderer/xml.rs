@@ -0,0 +1,484 @@
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use super::detail::{impl_md_parser, MdDetailParser};
+use super::sourcepos::{block_ranges, line_col, line_starts};
+use super::{Md2TextUserdata, Renderer, SpecifiedRenderer};
+use crate::entity::entity_decode;
+use crate::md4c::*;
+
+pub use crate::md4c::{MdParserFlags, MdResult};
+
+#[derive(Clone, Debug, Default)]
+pub struct Md2XmlOptions {
+    sourcepos: bool,
+}
+
+impl Md2XmlOptions {
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Give the block elements a `sourcepos` attribute: the lines and
+    /// columns of their first and last characters, as cmark does.
+    #[inline]
+    pub fn sourcepos(mut self) -> Self {
+        self.sourcepos = true;
+        self
+    }
+}
+
+/// An open element.
+struct Element {
+    name: &'static str,
+    /// Whether the children of its items are tight, for lists.
+    tight: bool,
+    /// A paragraph of a tight list item, which md4c does not report.
+    implicit: bool,
+}
+
+/// An element whose text is collected and written at once.
+struct Literal {
+    start_tag: String,
+    name: &'static str,
+    text: String,
+}
+
+fn escape_xml(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            ch => out.push(ch),
+        }
+    }
+    out
+}
+
+/// Renders the document in the XML format of the CommonMark DTD, as
+/// `cmark -t xml` does, with the element names of cmark-gfm for the
+/// extensions.
+pub struct Md2XmlRenderer<T> {
+    pub options: Md2XmlOptions,
+    pub userdata: T,
+    stack: Vec<Element>,
+    literal: Option<Literal>,
+    /// Text not output yet, so that adjacent texts make one element.
+    pending: String,
+    in_thead: bool,
+    blocks: Vec<Range<usize>>,
+    next_block: usize,
+    line_starts: Vec<usize>,
+}
+
+impl<T> Md2XmlRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    pub fn new(userdata: T, options: Md2XmlOptions) -> Self {
+        Self {
+            options,
+            userdata,
+            stack: Vec::new(),
+            literal: None,
+            pending: String::new(),
+            in_thead: false,
+            blocks: Vec::new(),
+            next_block: 0,
+            line_starts: Vec::new(),
+        }
+    }
+
+    pub fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.stack.clear();
+        self.literal = None;
+        self.pending.clear();
+        self.next_block = 0;
+        if self.options.sourcepos {
+            self.blocks = block_ranges(input, parser_flags)?;
+            self.line_starts = line_starts(input);
+        }
+        self.parse(input, parser_flags)
+    }
+
+    /// The `sourcepos` attribute of the next block, if enabled.
+    fn sourcepos(&mut self) -> String {
+        if !self.options.sourcepos {
+            return String::new();
+        }
+        let range = self.blocks.get(self.next_block).cloned();
+        self.next_block += 1;
+        match range {
+            Some(range) => {
+                let (line, col) =
+                    line_col(&self.line_starts, range.start);
+                let last = range.end.max(range.start + 1) - 1;
+                let (end_line, end_col) =
+                    line_col(&self.line_starts, last);
+                format!(
+                    " sourcepos=\"{}:{}-{}:{}\"",
+                    line, col, end_line, end_col
+                )
+            }
+            None => String::new(),
+        }
+    }
+
+    fn indent(&mut self) {
+        let indent = "  ".repeat(self.stack.len());
+        self.userdata.render_append(&indent);
+    }
+
+    fn open(&mut self, name: &'static str, attrs: &str, tight: bool) {
+        self.indent();
+        self.userdata
+            .render_append(&format!("<{}{}>\n", name, attrs));
+        self.stack.push(Element {
+            name,
+            tight,
+            implicit: false,
+        });
+    }
+
+    fn close(&mut self) {
+        if let Some(element) = self.stack.pop() {
+            self.indent();
+            let end_tag = format!("</{}>\n", element.name);
+            self.userdata.render_append(&end_tag);
+        }
+    }
+
+    fn empty(&mut self, name: &str) {
+        self.indent();
+        self.userdata.render_append(&format!("<{} />\n", name));
+    }
+
+    fn start_literal(&mut self, name: &'static str, attrs: &str) {
+        self.literal = Some(Literal {
+            start_tag: format!(
+                "<{}{} xml:space=\"preserve\">",
+                name, attrs
+            ),
+            name,
+            text: String::new(),
+        });
+    }
+
+    fn end_literal(&mut self) {
+        if let Some(literal) = self.literal.take() {
+            self.indent();
+            let element = format!(
+                "{}{}</{}>\n",
+                literal.start_tag,
+                escape_xml(&literal.text),
+                literal.name
+            );
+            self.userdata.render_append(&element);
+        }
+    }
+
+    fn flush_text(&mut self) {
+        if !self.pending.is_empty() {
+            let text = std::mem::take(&mut self.pending);
+            self.indent();
+            let element = format!(
+                "<text xml:space=\"preserve\">{}</text>\n",
+                escape_xml(&text)
+            );
+            self.userdata.render_append(&element);
+        }
+    }
+
+    /// Open the paragraph of a tight list item before its first inline.
+    fn start_inline(&mut self) {
+        if let Some(Element {
+            name: "item" | "tasklist",
+            ..
+        }) = self.stack.last()
+        {
+            self.open("paragraph", "", false);
+            if let Some(paragraph) = self.stack.last_mut() {
+                paragraph.implicit = true;
+            }
+        }
+    }
+
+    /// Close the paragraph of a tight list item before its next block
+    /// or its end.
+    fn end_implicit(&mut self) {
+        self.flush_text();
+        if let Some(Element { implicit: true, .. }) =
+            self.stack.last()
+        {
+            self.close();
+        }
+    }
+}
+
+impl<T> MdDetailParser for Md2XmlRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    fn enter_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        self.end_implicit();
+        let pos = self.sourcepos();
+        let tight = self.stack.last().is_some_and(|e| e.tight);
+        match detail {
+            MdBlockDetail::Doc => {
+                self.userdata.render_append(
+                    "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                     <!DOCTYPE document SYSTEM \"CommonMark.dtd\">\n",
+                );
+                let attrs = format!(
+                    "{} xmlns=\"http://commonmark.org/xml/1.0\"",
+                    pos
+                );
+                self.open("document", &attrs, false);
+            }
+            MdBlockDetail::Quote => {
+                self.open("block_quote", &pos, false)
+            }
+            MdBlockDetail::Ul { is_tight, .. } => {
+                let attrs = format!(
+                    "{} type=\"bullet\" tight=\"{}\"",
+                    pos, is_tight
+                );
+                self.open("list", &attrs, is_tight);
+            }
+            MdBlockDetail::Ol {
+                start,
+                is_tight,
+                mark_delimiter,
+            } => {
+                let delim = match mark_delimiter as u8 {
+                    b')' => "paren",
+                    _ => "period",
+                };
+                let attrs = format!(
+                    "{} type=\"ordered\" start=\"{}\" \
+                     delim=\"{}\" tight=\"{}\"",
+                    pos, start, delim, is_tight
+                );
+                self.open("list", &attrs, is_tight);
+            }
+            MdBlockDetail::Li {
+                is_task, task_mark, ..
+            } => {
+                if is_task {
+                    let completed = task_mark as u8 != b' ';
+                    let attrs = format!(
+                        "{} completed=\"{}\"",
+                        pos, completed
+                    );
+                    self.open("tasklist", &attrs, tight);
+                } else {
+                    self.open("item", &pos, tight);
+                }
+            }
+            MdBlockDetail::Hr => {
+                self.empty(&format!("thematic_break{}", pos));
+            }
+            MdBlockDetail::H { level } => {
+                let attrs = format!("{} level=\"{}\"", pos, level);
+                self.open("heading", &attrs, false);
+            }
+            MdBlockDetail::Code { info, .. } => {
+                let info = info.decode();
+                let attrs = match info.is_empty() {
+                    true => pos,
+                    false => format!(
+                        "{} info=\"{}\"",
+                        pos,
+                        escape_xml(&info)
+                    ),
+                };
+                self.start_literal("code_block", &attrs);
+            }
+            MdBlockDetail::Html => {
+                self.start_literal("html_block", &pos)
+            }
+            MdBlockDetail::P => self.open("paragraph", &pos, false),
+            MdBlockDetail::Table { .. } => {
+                self.open("table", &pos, false)
+            }
+            MdBlockDetail::Thead => self.in_thead = true,
+            MdBlockDetail::Tbody => self.in_thead = false,
+            MdBlockDetail::Tr => match self.in_thead {
+                true => self.open("table_header", &pos, false),
+                false => self.open("table_row", &pos, false),
+            },
+            MdBlockDetail::Th { align }
+            | MdBlockDetail::Td { align } => {
+                let align = match align {
+                    MdAlign::Default => "",
+                    MdAlign::Left => " align=\"left\"",
+                    MdAlign::Center => " align=\"center\"",
+                    MdAlign::Right => " align=\"right\"",
+                };
+                self.open(
+                    "table_cell",
+                    &format!("{}{}", pos, align),
+                    false,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_block(&mut self, detail: MdBlockDetail) -> MdResult {
+        let blocktype = detail.blocktype();
+        self.end_implicit();
+        match blocktype {
+            MdBlocktype::Hr
+            | MdBlocktype::Thead
+            | MdBlocktype::Tbody => {}
+            MdBlocktype::Code | MdBlocktype::Html => {
+                self.end_literal()
+            }
+            _ => self.close(),
+        }
+        Ok(())
+    }
+
+    fn enter_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        self.start_inline();
+        self.flush_text();
+        match detail {
+            MdSpanDetail::Em => self.open("emph", "", false),
+            MdSpanDetail::Strong => self.open("strong", "", false),
+            MdSpanDetail::Del => {
+                self.open("strikethrough", "", false)
+            }
+            MdSpanDetail::U => self.open("underline", "", false),
+            MdSpanDetail::A { href, title } => {
+                let attrs = format!(
+                    " destination=\"{}\" title=\"{}\"",
+                    escape_xml(&href.decode()),
+                    escape_xml(&title.decode())
+                );
+                self.open("link", &attrs, false);
+            }
+            MdSpanDetail::Img { src, title } => {
+                let attrs = format!(
+                    " destination=\"{}\" title=\"{}\"",
+                    escape_xml(&src.decode()),
+                    escape_xml(&title.decode())
+                );
+                self.open("image", &attrs, false);
+            }
+            MdSpanDetail::Wikilink { target } => {
+                let attrs = format!(
+                    " destination=\"{}\"",
+                    escape_xml(&target.decode())
+                );
+                self.open("wikilink", &attrs, false);
+            }
+            MdSpanDetail::Code => self.start_literal("code", ""),
+            MdSpanDetail::Latexmath => {
+                self.start_literal("math_inline", "")
+            }
+            MdSpanDetail::LatexmathDisplay => {
+                self.start_literal("math_display", "")
+            }
+        }
+        Ok(())
+    }
+
+    fn leave_span(&mut self, detail: MdSpanDetail) -> MdResult {
+        let spantype = detail.spantype();
+        self.flush_text();
+        match spantype {
+            MdSpantype::Code
+            | MdSpantype::Latexmath
+            | MdSpantype::LatexmathDisplay => self.end_literal(),
+            _ => self.close(),
+        }
+        Ok(())
+    }
+
+    fn text(&mut self, texttype: MdTexttype, text: &str) -> MdResult {
+        if let Some(literal) = &mut self.literal {
+            match texttype {
+                MdTexttype::Softbr
+                    if !literal.name.ends_with("_block") =>
+                {
+                    literal.text.push(' ')
+                }
+                MdTexttype::Nullchar => literal.text.push('\u{fffd}'),
+                _ => literal.text.push_str(text),
+            }
+            return Ok(());
+        }
+        self.start_inline();
+        match texttype {
+            MdTexttype::Br => {
+                self.flush_text();
+                self.empty("linebreak");
+            }
+            MdTexttype::Softbr => {
+                self.flush_text();
+                self.empty("softbreak");
+            }
+            MdTexttype::Html => {
+                self.flush_text();
+                self.start_literal("html_inline", "");
+                if let Some(literal) = &mut self.literal {
+                    literal.text.push_str(text);
+                }
+                self.end_literal();
+            }
+            MdTexttype::Nullchar => self.pending.push('\u{fffd}'),
+            MdTexttype::Entity => match entity_decode(text) {
+                Some(decoded) => self.pending.push_str(&decoded),
+                None => self.pending.push_str(text),
+            },
+            _ => self.pending.push_str(text),
+        }
+        Ok(())
+    }
+}
+
+impl_md_parser!(impl[T: Md2TextUserdata] Md2XmlRenderer<T>);
+
+impl<T> SpecifiedRenderer for Md2XmlRenderer<T>
+where
+    T: Md2TextUserdata,
+{
+    type Userdata = T;
+
+    #[inline]
+    fn render(
+        &mut self,
+        input: &str,
+        parser_flags: &MdParserFlags,
+    ) -> MdResult {
+        self.render(input, parser_flags)
+    }
+
+    #[inline]
+    fn userdata(&mut self) -> &mut Self::Userdata {
+        &mut self.userdata
+    }
+
+    #[inline]
+    fn unwrap(self) -> Self::Userdata {
+        self.userdata
+    }
+}
+
+impl<T> Renderer<T, Md2XmlRenderer<T>>
+where
+    T: Md2TextUserdata,
+{
+    pub fn xml(userdata: T, options: Md2XmlOptions) -> Self {
+        Self(Md2XmlRenderer::new(userdata, options), PhantomData)
+    }
+}
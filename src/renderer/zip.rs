@@ -0,0 +1,130 @@
+/// CRC-32 (IEEE) lookup table, as zip archives checksum their files.
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut n = 0;
+    while n < 256 {
+        let mut crc = n as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => 0xedb8_8320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[n] = crc;
+        n += 1;
+    }
+    table
+};
+
+/// DOS date of the files, 1980-01-01, the earliest one there is.
+const DOS_DATE: u16 = (1 << 5) | 1;
+
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc = CRC_TABLE[((crc ^ byte as u32) & 0xff) as usize]
+            ^ (crc >> 8);
+    }
+    !crc
+}
+
+struct Entry {
+    name: String,
+    crc: u32,
+    size: u32,
+    offset: u32,
+}
+
+/// Zip archive of stored, i.e. uncompressed, files: enough for the
+/// containers of document formats, without a compression library.
+#[derive(Default)]
+pub(crate) struct Zip {
+    data: Vec<u8>,
+    entries: Vec<Entry>,
+}
+
+impl Zip {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, content: &[u8]) {
+        let entry = Entry {
+            name: name.to_owned(),
+            crc: crc32(content),
+            size: content.len() as u32,
+            offset: self.data.len() as u32,
+        };
+        self.data.extend_from_slice(&0x0403_4b50u32.to_le_bytes());
+        self.data.extend_from_slice(&20u16.to_le_bytes());
+        self.file_fields(&entry);
+        self.data.extend_from_slice(&0u16.to_le_bytes());
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(content);
+        self.entries.push(entry);
+    }
+
+    /// Write the central directory and return the archive.
+    pub fn finish(mut self) -> Vec<u8> {
+        let start = self.data.len() as u32;
+        for entry in std::mem::take(&mut self.entries) {
+            self.data
+                .extend_from_slice(&0x0201_4b50u32.to_le_bytes());
+            self.data.extend_from_slice(&20u16.to_le_bytes());
+            self.data.extend_from_slice(&20u16.to_le_bytes());
+            self.file_fields(&entry);
+            // Extra field, comment, disk and attributes.
+            self.data.extend_from_slice(&[0; 12]);
+            self.data.extend_from_slice(&entry.offset.to_le_bytes());
+            self.data.extend_from_slice(entry.name.as_bytes());
+            self.entries.push(entry);
+        }
+        let size = self.data.len() as u32 - start;
+        let count = self.entries.len() as u16;
+        self.data.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+        self.data.extend_from_slice(&[0; 4]);
+        self.data.extend_from_slice(&count.to_le_bytes());
+        self.data.extend_from_slice(&count.to_le_bytes());
+        self.data.extend_from_slice(&size.to_le_bytes());
+        self.data.extend_from_slice(&start.to_le_bytes());
+        self.data.extend_from_slice(&0u16.to_le_bytes());
+        self.data
+    }
+
+    /// Fields shared by the local header and the central directory,
+    /// from the flags to the length of the name.
+    fn file_fields(&mut self, entry: &Entry) {
+        // Flags, stored method and time.
+        self.data.extend_from_slice(&[0; 6]);
+        self.data.extend_from_slice(&DOS_DATE.to_le_bytes());
+        self.data.extend_from_slice(&entry.crc.to_le_bytes());
+        self.data.extend_from_slice(&entry.size.to_le_bytes());
+        self.data.extend_from_slice(&entry.size.to_le_bytes());
+        let name_len = entry.name.len() as u16;
+        self.data.extend_from_slice(&name_len.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stored_archive() {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+
+        let mut zip = Zip::new();
+        zip.add("a.txt", b"hello");
+        let data = zip.finish();
+        assert_eq!(&data[..4], b"PK\x03\x04");
+        assert_eq!(&data[30..35], b"a.txt");
+        assert_eq!(&data[35..40], b"hello");
+        // End of central directory: one entry, directory at 40.
+        let end = &data[data.len() - 22..];
+        assert_eq!(&end[..4], b"PK\x05\x06");
+        assert_eq!(&end[8..10], &1u16.to_le_bytes());
+        assert_eq!(&end[16..20], &40u32.to_le_bytes());
+    }
+}
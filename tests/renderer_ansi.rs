@@ -0,0 +1,110 @@
+use md4rust::renderer::prelude::*;
+
+fn ansi(input: &str, options: Md2AnsiOptions) -> String {
+    let mut renderer = MdRenderer::ansi(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_ansi_styles_and_links() {
+    let input = "## Notes\n\n\
+                 *new* **bold** text [docs](https://a.b/c)\n\n\
+                 - [x] done\n";
+    assert_eq!(
+        ansi(input, Md2AnsiOptions::new().width(16)),
+        "\x1b[1;34mNotes\x1b[0m\n\n\
+         \x1b[3mnew\x1b[23m \x1b[1mbold\x1b[22m text\n\
+         \x1b]8;;https://a.b/c\x1b\\\x1b[4mdocs\x1b[24m\
+         \x1b]8;;\x1b\\\n\n\
+         • ☑ done\n"
+    );
+    assert_eq!(
+        ansi(
+            "[docs](https://a.b) <https://a.b>\n",
+            Md2AnsiOptions::new().width(0).no_hyperlinks()
+        ),
+        "\x1b[4mdocs\x1b[24m (https://a.b) \
+         \x1b[4mhttps://a.b\x1b[24m\n"
+    );
+}
+
+#[test]
+fn renderer_ansi_boxes() {
+    let input = "```rust\nfn main() {}\n```\n\n\
+                 | a | 表 |\n|--:|:-:|\n| 10 | x |\n\n\
+                 | `x` | *y* |\n|---|---|\n";
+    assert_eq!(
+        ansi(input, Md2AnsiOptions::new().width(0)),
+        "\x1b[90m┌─ rust ───────┐\x1b[39m\n\
+         \x1b[90m│\x1b[39m fn main() {} \x1b[90m│\x1b[39m\n\
+         \x1b[90m└──────────────┘\x1b[39m\n\n\
+         ┌────┬────┐\n\
+         │ \x1b[1m a\x1b[22m │ \x1b[1m表\x1b[22m │\n\
+         ├────┼────┤\n\
+         │ 10 │ x  │\n\
+         └────┴────┘\n\n\
+         ┌───┬───┐\n\
+         │ \x1b[1m\x1b[36mx\x1b[39m\x1b[22m │ \
+         \x1b[1m\x1b[3my\x1b[23m\x1b[22m │\n\
+         ├───┼───┤\n\
+         └───┴───┘\n"
+    );
+}
+
+#[test]
+fn renderer_ansi_quote_keeps_styles() {
+    let input = "> # quick brown fox\n";
+    assert_eq!(
+        ansi(input, Md2AnsiOptions::new().width(12)),
+        "\x1b[90m│\x1b[39m \x1b[1;4;35mquick\x1b[0m\n\
+         \x1b[90m│\x1b[39m \x1b[1;4;35mbrown fox\x1b[0m\n"
+    );
+}
+
+#[test]
+fn renderer_ansi_nested_lists() {
+    let input = "- a\n  - b\n    1. c\n- [ ] e\n";
+    assert_eq!(
+        ansi(input, Md2AnsiOptions::new().width(0)),
+        "• a\n  • b\n    1. c\n• ☐ e\n"
+    );
+}
+
+#[test]
+fn renderer_ansi_images() {
+    assert_eq!(
+        ansi("![alt *x*](img.png)\n", Md2AnsiOptions::new().width(0)),
+        "\x1b]8;;img.png\x1b\\\x1b[4m[image: alt \x1b[3mx\x1b[23m]\
+         \x1b[24m\x1b]8;;\x1b\\\n"
+    );
+}
+
+#[test]
+fn renderer_ansi_control_characters() {
+    assert_eq!(
+        ansi("a \x1b[31m b\n", Md2AnsiOptions::new().width(0)),
+        "a \u{fffd}[31m b\n"
+    );
+    // Nor through link targets or info strings.
+    let input = "[x](<a&#27;]2;pwned&#7;>)\n";
+    assert_eq!(
+        ansi(input, Md2AnsiOptions::new().width(0)),
+        "\x1b]8;;a%1B]2;pwned%07\x1b\\\x1b[4mx\x1b[24m\x1b]8;;\x1b\\\n"
+    );
+    assert_eq!(
+        ansi(input, Md2AnsiOptions::new().width(0).no_hyperlinks()),
+        "\x1b[4mx\x1b[24m (a%1B]2;pwned%07)\n"
+    );
+    let input = "``` a\x1b]2;b\nc\n```\n";
+    assert!(!ansi(input, Md2AnsiOptions::new()).contains("\x1b]"));
+}
+
+#[test]
+fn renderer_ansi_default_width() {
+    // 80 columns, whatever the width of the terminal running tests.
+    let input = "word ".repeat(20);
+    let out = ansi(&input, Md2AnsiOptions::new());
+    let widths: Vec<usize> = out.lines().map(str::len).collect();
+    assert_eq!(widths, [79, 19]);
+}
@@ -0,0 +1,65 @@
+use md4rust::renderer::prelude::*;
+
+fn asciidoc(input: &str, options: Md2AsciidocOptions) -> String {
+    let mut renderer = MdRenderer::asciidoc(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_asciidoc_blocks() {
+    let input = "# Guide\n\n\
+                 Use *a_b* and `x+y`, see [docs](https://a.b/).\n\
+                 3. not a list\n\n\
+                 - [x] done\n  - nested\n- two\n\n  more\n\n\
+                 ```rust\nfn f() {}\n```\n\n\
+                 ![logo](logo.png)\n";
+    assert_eq!(
+        asciidoc(input, Md2AsciidocOptions::new().heading_offset(1)),
+        "== Guide\n\n\
+         Use __a_b__ and ``+x+y+``, see link:https://a.b/[docs].\n\
+         {empty}3. not a list\n\n\
+         * [x] done\n\
+         ** nested\n\
+         * two\n+\nmore\n\n\
+         [source,rust]\n----\nfn f() {}\n----\n\n\
+         image::logo.png[logo]\n"
+    );
+}
+
+#[test]
+fn renderer_asciidoc_table_and_quote() {
+    let input = "> 2 * 3 = 6\n\n\
+                 | a | b |\n|:-:|--:|\n| x\\|y | ~~z~~ |\n";
+    assert_eq!(
+        asciidoc(input, Md2AsciidocOptions::new()),
+        "____\n2 {asterisk} 3 = 6\n____\n\n\
+         [cols=\"^,>\",options=\"header\"]\n|===\n\
+         | a | b\n\n\
+         | x\\|y | [.line-through]##z##\n\
+         |===\n"
+    );
+}
+
+#[test]
+fn renderer_asciidoc_nested_lists() {
+    let input = "- a\n  1. b\n     - c\n- [x] d\n";
+    assert_eq!(
+        asciidoc(input, Md2AsciidocOptions::new()),
+        "* a\n.. b\n*** c\n* [x] d\n"
+    );
+}
+
+#[test]
+fn renderer_asciidoc_images() {
+    let input = "![i *j*](x.png \"t\")\n\n\
+                 | a | b\\|c |\n|---|---|\n| *x* | ![y](z.png) |\n";
+    assert_eq!(
+        asciidoc(input, Md2AsciidocOptions::new()),
+        "image::x.png[i j]\n\n\
+         [cols=\"1,1\",options=\"header\"]\n|===\n\
+         | a | b\\|c\n\n\
+         | __x__ | image:z.png[y]\n\
+         |===\n"
+    );
+}
@@ -0,0 +1,56 @@
+use md4rust::renderer::prelude::*;
+
+fn bbcode(input: &str, options: Md2BbcodeOptions) -> String {
+    let mut renderer = MdRenderer::bbcode(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_bbcode_blocks_and_spans() {
+    let input = "## Notes\n\n\
+                 Type `[b]` for **bold**, see [the FAQ](/faq) \
+                 [1].\n\n\
+                 > quoted ![x](a.png)\n\n\
+                 1. one\n   - [x] two\n";
+    assert_eq!(
+        bbcode(input, Md2BbcodeOptions::new()),
+        "[size=150][b]Notes[/b][/size]\n\n\
+         Type [font=monospace][[i][/i]b][/font] for [b]bold[/b], \
+         see [url=/faq]the FAQ[/url] [1].\n\n\
+         [quote]quoted [img]a.png[/img][/quote]\n\n\
+         [list=1]\n\
+         [*]one\n[list]\n[*]☑ two\n[/list]\n\
+         [/list]\n"
+    );
+}
+
+#[test]
+fn renderer_bbcode_code_and_table() {
+    let input = "```\n[i]x\n```\n\n| a | b |\n|---|:-:|\n| 1 | 2 |\n";
+    assert_eq!(
+        bbcode(input, Md2BbcodeOptions::new()),
+        "[code][i]x[/code]\n\n\
+         [code]a  b\n-  -\n1  2[/code]\n"
+    );
+}
+
+#[test]
+fn renderer_bbcode_escaping() {
+    let input = "[b]x[/b] & \\\\\n\n```\na [/code] b\n```\n";
+    assert_eq!(
+        bbcode(input, Md2BbcodeOptions::new()),
+        "[[i][/i]b]x[[i][/i]/b] & \\\n\n\
+         [code]a [/\u{200b}code] b[/code]\n"
+    );
+}
+
+#[test]
+fn renderer_bbcode_nested_lists_and_images() {
+    let input = "- a\n  1. b\n- c\n\n![i](x.png)\n";
+    assert_eq!(
+        bbcode(input, Md2BbcodeOptions::new()),
+        "[list]\n[*]a\n[list=1]\n[*]b\n[/list]\n[*]c\n[/list]\n\n\
+         [img]x.png[/img]\n"
+    );
+}
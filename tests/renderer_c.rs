@@ -1,4 +1,5 @@
 use md4rust::renderer::prelude::*;
+use std::num::NonZeroI32;
 
 #[derive(Default)]
 struct MyRenderer(String);
@@ -31,3 +32,172 @@ fn renderer_c() -> MdResult {
 
     Ok(())
 }
+
+#[derive(Default)]
+struct Highlighter {
+    html: String,
+    code: Option<String>,
+}
+
+impl Md2HtmlC for Highlighter {
+    fn render_append(&mut self, text: &str) {
+        self.html += text;
+    }
+}
+
+#[test]
+fn renderer_c_hooks() -> MdResult {
+    let mut renderer = MdRenderer::html_c(
+        Highlighter::default(),
+        Md2HtmlFlags::default(),
+    );
+
+    renderer
+        .on_enter_block(MdBlocktype::Code, |ctx, _| {
+            ctx.userdata.code = Some(String::new());
+            Ok(())
+        })
+        .on_leave_block(MdBlocktype::Code, |ctx, detail| {
+            let code = ctx.userdata.code.take().unwrap_or_default();
+            let lang = match detail {
+                MdBlockDetail::Code { lang, .. } => lang.clone().as_str(),
+                _ => None,
+            };
+            ctx.render_append(&format!(
+                "<pre data-lang=\"{}\">",
+                lang.unwrap_or("")
+            ));
+            ctx.render_html_escaped(&code.to_uppercase());
+            ctx.render_append("</pre>\n");
+            Ok(())
+        })
+        .on_text(|ctx, _, text| {
+            match ctx.userdata.code.as_mut() {
+                Some(code) => *code += text,
+                None => ctx.render_default(),
+            }
+            Ok(())
+        })
+        .on_enter_span(MdSpantype::A, |ctx, detail| {
+            if let MdSpanDetail::A { href, .. } = detail {
+                if href.clone().as_str() == Some("keep") {
+                    ctx.render_default();
+                    return Ok(());
+                }
+            }
+            ctx.render_append("<a href=\"/rewritten\">");
+            Ok(())
+        });
+
+    renderer.render(
+        "[*x*](keep) [y](other)\n\n```rust\nlet a = \"<b>\";\n```\n",
+        &MdParserFlags::github(),
+    )?;
+
+    assert_eq!(
+        renderer.userdata().html,
+        "<p><a href=\"keep\"><em>x</em></a> \
+         <a href=\"/rewritten\">y</a></p>\n\
+         <pre data-lang=\"rust\">LET A = &quot;&lt;B&gt;&quot;;\n</pre>\n"
+    );
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Figures {
+    html: String,
+    closed: Vec<String>,
+}
+
+impl Md2HtmlC for Figures {
+    fn render_append(&mut self, text: &str) {
+        self.html += text;
+    }
+}
+
+#[test]
+fn renderer_c_nested_image_hooks() -> MdResult {
+    let mut renderer = MdRenderer::html_c(
+        Figures::default(),
+        Md2HtmlFlags::default(),
+    );
+
+    renderer
+        .on_enter_span(MdSpantype::Img, |ctx, _| {
+            ctx.render_append("<figure>");
+            Ok(())
+        })
+        .on_leave_span(MdSpantype::Img, |ctx, detail| {
+            if let MdSpanDetail::Img { src, .. } = detail {
+                let src =
+                    src.clone().as_str().unwrap_or("").to_owned();
+                ctx.userdata.closed.push(src);
+            }
+            ctx.render_append("</figure>");
+            Ok(())
+        })
+        .on_text(|ctx, _, text| {
+            ctx.render_html_escaped(&text.to_uppercase());
+            Ok(())
+        });
+
+    renderer.render(
+        "![a ![b](x) c](y) *d*\n",
+        &MdParserFlags::github(),
+    )?;
+
+    let figures = renderer.userdata();
+    assert_eq!(
+        figures.html,
+        "<p><figure>A B C</figure> <em>d</em></p>\n"
+    );
+    assert_eq!(figures.closed, ["y"]);
+
+    Ok(())
+}
+
+#[derive(Default)]
+struct Failing {
+    html: String,
+    fail: bool,
+}
+
+impl Md2HtmlC for Failing {
+    fn render_append(&mut self, text: &str) {
+        self.html += text;
+    }
+}
+
+#[test]
+fn renderer_c_render_after_hook_error() {
+    let mut renderer = MdRenderer::html_c(
+        Failing::default(),
+        Md2HtmlFlags::default(),
+    );
+
+    renderer
+        .on_enter_span(MdSpantype::Em, |ctx, _| {
+            if ctx.userdata.fail {
+                return Err(NonZeroI32::new(-1).unwrap());
+            }
+            ctx.render_default();
+            Ok(())
+        })
+        .on_text(|ctx, _, text| {
+            ctx.render_html_escaped(&text.to_uppercase());
+            Ok(())
+        });
+
+    let input = "a *b*\n";
+
+    renderer.userdata().fail = true;
+    let res = renderer.render(input, &MdParserFlags::github());
+    assert_eq!(res, Err(NonZeroI32::new(-1).unwrap()));
+
+    let failing = renderer.userdata();
+    failing.fail = false;
+    failing.html.clear();
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    assert_eq!(renderer.userdata().html, "<p>a <em>B</em></p>\n");
+}
@@ -0,0 +1,137 @@
+use md4rust::renderer::prelude::*;
+
+fn chat(input: &str, dialect: Md2ChatDialect) -> String {
+    let mut renderer =
+        MdRenderer::chat(String::new(), Md2ChatOptions::new(dialect));
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+const INPUT: &str = "# Release 1.2!\n\n\
+                     **New:** *faster* <https://a.b> and \
+                     [docs](https://a.b/c_(d)) ![logo](l.png)\n\n\
+                     - [x] a < b\n";
+
+#[test]
+fn renderer_chat_dialects() {
+    assert_eq!(
+        chat(INPUT, Md2ChatDialect::Slack),
+        "*Release 1.2!*\n\n\
+         *New:* _faster_ <https://a.b> and \
+         <https://a.b/c_(d)|docs> <l.png|logo>\n\n\
+         • ☑ a &lt; b\n"
+    );
+    assert_eq!(
+        chat(INPUT, Md2ChatDialect::Telegram),
+        "*Release 1\\.2\\!*\n\n\
+         *New:* _faster_ [https://a\\.b](https://a.b) and \
+         [docs](https://a.b/c_(d\\)) [logo](l.png)\n\n\
+         • ☑ a < b\n"
+    );
+    assert_eq!(
+        chat(INPUT, Md2ChatDialect::Discord),
+        "# Release 1.2!\n\n\
+         **New:** *faster* https://a.b and \
+         [docs](https://a.b/c_%28d%29) [logo](l.png)\n\n\
+         - ☑ a < b\n"
+    );
+}
+
+#[test]
+fn renderer_chat_link_targets() {
+    // Characters ending the link syntax are percent-encoded.
+    let input = "[a](x|y>z) [b](<p q)>)\n";
+    assert_eq!(
+        chat(input, Md2ChatDialect::Slack),
+        "<x%7Cy%3Ez|a> <p%20q)|b>\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Discord),
+        "[a](x|y%3Ez) [b](p%20q%29)\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Telegram),
+        "[a](x|y>z) [b](p q\\))\n"
+    );
+}
+
+#[test]
+fn renderer_chat_code_and_tables() {
+    let input = "```py\nprint(`x`)\n```\n\n\
+                 | a | b |\n|---|--:|\n| *x* | 10 |\n";
+    assert_eq!(
+        chat(input, Md2ChatDialect::Telegram),
+        "```py\nprint(\\`x\\`)\n```\n\n\
+         ```\na   b\n-  --\nx  10\n```\n"
+    );
+}
+
+#[test]
+fn renderer_chat_table_escaping() {
+    // Escaped once, as code, and aligned on the unescaped text.
+    let input = "| x | y |\n|---|---|\n\
+                 | a & b | 1.5 |\n| a_b | \\ |\n";
+    assert_eq!(
+        chat(input, Md2ChatDialect::Slack),
+        "```\nx      y\n-----  ---\n\
+         a &amp; b  1.5\na_b    \\\n```\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Telegram),
+        "```\nx      y\n-----  ---\n\
+         a & b  1.5\na_b    \\\\\n```\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Discord),
+        "```\nx      y\n-----  ---\n\
+         a & b  1.5\na_b    \\\n```\n"
+    );
+}
+
+#[test]
+fn renderer_chat_escaping() {
+    let input = "*a_b* & [l](u) 1.5\n";
+    assert_eq!(
+        chat(input, Md2ChatDialect::Slack),
+        "_a_b_ &amp; <u|l> 1.5\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Telegram),
+        "_a\\_b_ & [l](u) 1\\.5\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Discord),
+        "*a\\_b* & [l](u) 1.5\n"
+    );
+}
+
+#[test]
+fn renderer_chat_nested_lists_and_images() {
+    let input = "- a\n  1. b\n- c\n\n![i](x.png)\n";
+    assert_eq!(
+        chat(input, Md2ChatDialect::Slack),
+        "• a\n  1. b\n• c\n\n<x.png|i>\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Telegram),
+        "• a\n  1\\. b\n• c\n\n[i](x.png)\n"
+    );
+    assert_eq!(
+        chat(input, Md2ChatDialect::Discord),
+        "- a\n  1. b\n- c\n\n[i](x.png)\n"
+    );
+}
+
+#[test]
+fn renderer_chat_md_parser() {
+    // The renderers are parsers of their own.
+    use md4rust::md4c::MdParser;
+    use md4rust::renderer::chat::Md2ChatRenderer;
+
+    let options = Md2ChatOptions::new(Md2ChatDialect::Slack);
+    let mut renderer = Md2ChatRenderer::new(String::new(), options);
+    renderer
+        .parse("*a* `b`\n", &MdParserFlags::github())
+        .unwrap();
+    assert_eq!(renderer.userdata, "_a_ `b`\n");
+}
@@ -0,0 +1,190 @@
+use md4rust::renderer::prelude::*;
+use std::collections::BTreeMap;
+
+fn docx<T: Md2Docx>(userdata: T, input: &str) -> T {
+    let mut renderer =
+        MdRenderer::docx(userdata, Md2DocxOptions::new());
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+fn u16_at(data: &[u8], at: usize) -> usize {
+    u16::from_le_bytes([data[at], data[at + 1]]) as usize
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(data[at..at + 4].try_into().unwrap())
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = match crc & 1 {
+                1 => 0xedb8_8320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+        }
+    }
+    !crc
+}
+
+/// The files of a zip archive of stored files, by name, checking the
+/// central directory against the local headers and the checksums.
+fn unzip(data: &[u8]) -> BTreeMap<String, Vec<u8>> {
+    let end = data.len() - 22;
+    assert_eq!(u32_at(data, end), 0x0605_4b50);
+    let count = u16_at(data, end + 10);
+    let mut at = u32_at(data, end + 16) as usize;
+    let mut files = BTreeMap::new();
+    for _ in 0..count {
+        assert_eq!(u32_at(data, at), 0x0201_4b50);
+        assert_eq!(u16_at(data, at + 10), 0, "stored");
+        let crc = u32_at(data, at + 16);
+        let size = u32_at(data, at + 20) as usize;
+        assert_eq!(u32_at(data, at + 24) as usize, size);
+        let name_len = u16_at(data, at + 28);
+        let name = &data[at + 46..at + 46 + name_len];
+        let local = u32_at(data, at + 42) as usize;
+        assert_eq!(u32_at(data, local), 0x0403_4b50);
+        assert_eq!(u32_at(data, local + 14), crc);
+        assert_eq!(&data[local + 30..local + 30 + name_len], name);
+        let start = local + 30 + name_len + u16_at(data, local + 28);
+        let content = &data[start..start + size];
+        assert_eq!(crc32(content), crc);
+        let name = String::from_utf8(name.to_vec()).unwrap();
+        files.insert(name, content.to_vec());
+        at += 46
+            + name_len
+            + u16_at(data, at + 30)
+            + u16_at(data, at + 32);
+    }
+    files
+}
+
+fn part(files: &BTreeMap<String, Vec<u8>>, name: &str) -> String {
+    String::from_utf8(files[name].clone()).unwrap()
+}
+
+#[test]
+fn renderer_docx_archive() {
+    let data = docx(Vec::new(), "Hi\n");
+    assert!(data.starts_with(b"PK\x03\x04"));
+    let files = unzip(&data);
+    assert_eq!(
+        files.keys().collect::<Vec<_>>(),
+        [
+            "[Content_Types].xml",
+            "_rels/.rels",
+            "word/_rels/document.xml.rels",
+            "word/document.xml",
+            "word/numbering.xml",
+            "word/styles.xml",
+        ]
+    );
+    let types = part(&files, "[Content_Types].xml");
+    assert!(types.contains("PartName=\"/word/document.xml\""));
+    let rels = part(&files, "_rels/.rels");
+    assert!(rels.contains("Target=\"word/document.xml\""));
+}
+
+#[test]
+fn renderer_docx_document_parts() {
+    let input = "## Scope\n\n\
+                 **Must** see [the spec](https://s.io/a?b&c).\n\n\
+                 3. three\n\n\
+                 | a | b |\n|---|:-:|\n| 1 | 2 |\n";
+    let files = unzip(&docx(Vec::new(), input));
+    let document = part(&files, "word/document.xml");
+    for xml in [
+        "<w:p><w:pPr><w:pStyle w:val=\"Heading2\"/></w:pPr>\
+         <w:r><w:t xml:space=\"preserve\">Scope</w:t></w:r></w:p>",
+        "<w:r><w:rPr><w:b/></w:rPr>\
+         <w:t xml:space=\"preserve\">Must</w:t></w:r>",
+        "<w:hyperlink r:id=\"rIdLink1\"><w:r><w:rPr>\
+         <w:rStyle w:val=\"Hyperlink\"/></w:rPr>",
+        "<w:numPr><w:ilvl w:val=\"0\"/><w:numId w:val=\"1\"/>",
+        "<w:tr><w:trPr><w:tblHeader/></w:trPr><w:tc><w:p>\
+         <w:r><w:rPr><w:b/></w:rPr>",
+        "<w:tc><w:p><w:pPr><w:jc w:val=\"center\"/></w:pPr>\
+         <w:r><w:t xml:space=\"preserve\">2</w:t>",
+    ] {
+        assert!(document.contains(xml), "{}", xml);
+    }
+    let rels = part(&files, "word/_rels/document.xml.rels");
+    assert!(rels.contains(
+        "Target=\"https://s.io/a?b&amp;c\" TargetMode=\"External\""
+    ));
+    let numbering = part(&files, "word/numbering.xml");
+    assert!(numbering.contains("<w:startOverride w:val=\"3\"/>"));
+}
+
+#[test]
+fn renderer_docx_escaping() {
+    let files = unzip(&docx(Vec::new(), "a < b & \"q\"\n"));
+    let document = part(&files, "word/document.xml");
+    assert!(document.contains(
+        "<w:t xml:space=\"preserve\">\
+         a &lt; b &amp; &quot;q&quot;</w:t>"
+    ));
+}
+
+#[test]
+fn renderer_docx_nested_lists() {
+    let files = unzip(&docx(Vec::new(), "- a\n  1. b\n"));
+    let document = part(&files, "word/document.xml");
+    assert!(document.contains(
+        "<w:p><w:pPr><w:numPr><w:ilvl w:val=\"0\"/>\
+         <w:numId w:val=\"1\"/></w:numPr></w:pPr><w:r>\
+         <w:t xml:space=\"preserve\">a</w:t></w:r></w:p>\
+         <w:p><w:pPr><w:numPr><w:ilvl w:val=\"1\"/>\
+         <w:numId w:val=\"2\"/></w:numPr></w:pPr><w:r>\
+         <w:t xml:space=\"preserve\">b</w:t></w:r></w:p>"
+    ));
+    let numbering = part(&files, "word/numbering.xml");
+    assert!(numbering.contains(
+        "<w:num w:numId=\"1\"><w:abstractNumId w:val=\"0\"/></w:num>"
+    ));
+    assert!(numbering.contains(
+        "<w:num w:numId=\"2\"><w:abstractNumId w:val=\"1\"/>"
+    ));
+}
+
+struct Images(Vec<u8>);
+
+impl Md2Docx for Images {
+    fn render_append(&mut self, data: &[u8]) {
+        self.0.extend_from_slice(data);
+    }
+
+    fn resolve_image(&mut self, src: &str) -> Option<Vec<u8>> {
+        // A PNG signature and header of 2×1 pixels.
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&[0, 0, 0, 2, 0, 0, 0, 1]);
+        (src == "dot.png").then_some(png)
+    }
+}
+
+#[test]
+fn renderer_docx_resolved_images() {
+    let input = "![a *dot*](dot.png) ![gone](gone.png)\n";
+    let Images(data) = docx(Images(Vec::new()), input);
+    let files = unzip(&data);
+    assert!(files["word/media/image1.png"].starts_with(b"\x89PNG"));
+    let document = part(&files, "word/document.xml");
+    for xml in [
+        "<wp:extent cx=\"19050\" cy=\"9525\"/>",
+        "descr=\"a dot\"",
+        "<a:blip r:embed=\"rIdImage1\"/>",
+        "<w:t xml:space=\"preserve\">gone</w:t>",
+    ] {
+        assert!(document.contains(xml), "{}", xml);
+    }
+    let rels = part(&files, "word/_rels/document.xml.rels");
+    assert!(rels.contains("Target=\"media/image1.png\""));
+    let types = part(&files, "[Content_Types].xml");
+    assert!(types.contains(
+        "<Default Extension=\"png\" ContentType=\"image/png\"/>"
+    ));
+}
@@ -0,0 +1,36 @@
+use md4rust::renderer::prelude::*;
+
+fn choose(backend: &str) -> Box<dyn MarkdownRenderer> {
+    match backend {
+        "c" => Box::new(MdRenderer::html_c(
+            String::new(),
+            Md2HtmlFlags::default(),
+        )),
+        _ => Box::new(MdRenderer::html_rs(
+            String::new(),
+            Md2HtmlFlags::default().xhtml(),
+        )),
+    }
+}
+
+#[test]
+fn renderer_dyn() {
+    let input = "## Hello *World* ##\n\n---\n";
+    let flags = MdParserFlags::github();
+
+    let mut renderers = [choose("c"), choose("rs")];
+
+    assert_eq!(
+        renderers[0].render_to_string(input, &flags).unwrap(),
+        "<h2>Hello <em>World</em></h2>\n<hr>\n"
+    );
+    assert_eq!(
+        renderers[1].render_to_string(input, &flags).unwrap(),
+        "<h2>Hello <em>World</em></h2>\n<hr />\n"
+    );
+
+    // Output does not leak between calls.
+    renderers[0].render("text\n", &flags).unwrap();
+    assert_eq!(renderers[0].take_output(), "<p>text</p>\n");
+    assert_eq!(renderers[0].take_output(), "");
+}
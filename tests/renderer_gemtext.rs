@@ -0,0 +1,64 @@
+use md4rust::renderer::prelude::*;
+
+fn gemtext(input: &str, options: Md2GemtextOptions) -> String {
+    let mut renderer = MdRenderer::gemtext(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+const INPUT: &str = "#### Deep *title*\n\n\
+                     Read [the spec](gemini://a.b/spec) and\n\
+                     <https://a.b>.\n\n\
+                     > See ![map](map.png)\n\n\
+                     1. one\n   - two\n\n\
+                     ```sh\nls\n```\n";
+
+#[test]
+fn renderer_gemtext_links_after_blocks() {
+    assert_eq!(
+        gemtext(INPUT, Md2GemtextOptions::new()),
+        "### Deep title\n\n\
+         Read the spec and https://a.b.\n\
+         => gemini://a.b/spec the spec\n\
+         => https://a.b\n\n\
+         > See map\n\
+         => map.png map\n\n\
+         * one\n* two\n\n\
+         ```sh\nls\n```\n"
+    );
+}
+
+#[test]
+fn renderer_gemtext_links_at_end() {
+    assert_eq!(
+        gemtext(INPUT, Md2GemtextOptions::new().links_at_end()),
+        "### Deep title\n\n\
+         Read the spec and https://a.b.\n\n\
+         > See map\n\n\
+         * one\n* two\n\n\
+         ```sh\nls\n```\n\n\
+         => gemini://a.b/spec the spec\n\
+         => https://a.b\n\
+         => map.png map\n"
+    );
+}
+
+#[test]
+fn renderer_gemtext_escaping() {
+    let input = "=> not a link\n# x\n\n\\* a\n\n```\n```y\n```\n";
+    assert_eq!(
+        gemtext(input, Md2GemtextOptions::new()),
+        "\u{200b}=> not a link\n\n# x\n\n\u{200b}* a\n\n\
+         ```\n\u{200b}```y\n```\n"
+    );
+}
+
+#[test]
+fn renderer_gemtext_nested_lists_and_tables() {
+    let input = "- a\n  1. b\n- [x] c\n\n\
+                 | a | b |\n|---|---|\n| 1 | 2 |\n";
+    assert_eq!(
+        gemtext(input, Md2GemtextOptions::new()),
+        "* a\n* b\n* [x] c\n\n```\na  b\n-  -\n1  2\n```\n"
+    );
+}
@@ -0,0 +1,125 @@
+use md4rust::renderer::prelude::*;
+
+fn ipynb(input: &str, options: Md2IpynbOptions) -> String {
+    let mut renderer = MdRenderer::ipynb(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_ipynb_cells() {
+    let input = "# Setup\n\nInstall *it*:\n\n\
+                 ```sh\npip install it\n```\n\n\
+                 ```Python\nimport it\n\nit.run()\n```\n\
+                 - nested\n\n  ```python\n  skipped\n  ```\n";
+    let expected = r##"{
+ "cells": [
+  {
+   "cell_type": "markdown",
+   "id": "cell-1",
+   "metadata": {},
+   "source": [
+    "# Setup\n",
+    "\n",
+    "Install *it*:\n",
+    "\n",
+    "```sh\n",
+    "pip install it\n",
+    "```"
+   ]
+  },
+  {
+   "cell_type": "code",
+   "execution_count": null,
+   "id": "cell-2",
+   "metadata": {},
+   "outputs": [],
+   "source": [
+    "import it\n",
+    "\n",
+    "it.run()"
+   ]
+  },
+  {
+   "cell_type": "markdown",
+   "id": "cell-3",
+   "metadata": {},
+   "source": [
+    "- nested\n",
+    "\n",
+    "  ```python\n",
+    "  skipped\n",
+    "  ```"
+   ]
+  }
+ ],
+ "metadata": {
+  "kernelspec": {
+   "display_name": "Python 3",
+   "language": "python",
+   "name": "python3"
+  },
+  "language_info": {
+   "name": "python"
+  }
+ },
+ "nbformat": 4,
+ "nbformat_minor": 5
+}
+"##;
+    assert_eq!(ipynb(input, Md2IpynbOptions::new()), expected);
+}
+
+#[test]
+fn renderer_ipynb_kernel_language() {
+    let options = Md2IpynbOptions::new()
+        .language("julia")
+        .kernel("julia-1.10", "Julia 1.10");
+    let out = ipynb("```julia\nx = 1\n```\n```\ny\n```", options);
+    assert!(out.contains("\"source\": [\n    \"x = 1\"\n   ]"));
+    assert!(out.contains("\"source\": [\n    \"```\\n\","));
+    assert!(out.contains("\"name\": \"julia-1.10\""));
+    assert!(
+        out.contains("\"language_info\": {\n   \"name\": \"julia\"")
+    );
+}
+
+#[test]
+fn renderer_ipynb_splitting() {
+    // No empty Markdown cell before, between or after code cells.
+    let input = "```python\na\n```\n```python\nb\n```\n\n\
+                 > ```python\n> quoted\n> ```\n";
+    let out = ipynb(input, Md2IpynbOptions::new());
+    assert_eq!(out.matches("\"cell_type\": \"code\"").count(), 2);
+    assert_eq!(out.matches("\"cell_type\": \"markdown\"").count(), 1);
+    assert!(out.contains("\"id\": \"cell-3\""));
+    assert!(out.contains(
+        "\"source\": [\n    \"> ```python\\n\",\n    \
+         \"> quoted\\n\",\n    \"> ```\"\n   ]"
+    ));
+    assert!(!out.contains("\"source\": []"));
+}
+
+#[test]
+fn renderer_ipynb_escaping() {
+    let input =
+        "```python\na = \"q\\\\\"\tb\n```\nText é | ![i](x.png)\n";
+    let out = ipynb(input, Md2IpynbOptions::new());
+    assert!(out.contains(
+        r#""source": [
+    "a = \"q\\\\\"\tb"
+   ]"#
+    ));
+    assert!(out.contains("\"Text é | ![i](x.png)\""));
+}
+
+#[test]
+fn renderer_ipynb_trailing_blank_lines() {
+    // The closing fence stays with its cell after blank lines.
+    let input = "```python\nx\n\n\n```\ntext\n";
+    let out = ipynb(input, Md2IpynbOptions::new());
+    assert!(out
+        .contains("\"source\": [\n    \"x\\n\",\n    \"\\n\"\n   ]"));
+    assert!(out.contains("\"source\": [\n    \"text\"\n   ]"));
+    assert!(!out.contains("```"));
+}
@@ -0,0 +1,55 @@
+use md4rust::renderer::prelude::*;
+
+fn jira(input: &str, options: Md2JiraOptions) -> String {
+    let mut renderer = MdRenderer::jira(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_jira_blocks_and_spans() {
+    let input = "## Changes in *1.2*\n\n\
+                 Fixed `{x}` in [PROJ-1](https://j/PROJ-1) \
+                 and ~~old~~ _-x_ [y] 2 * 3!\n\n\
+                 1. one\n   - [x] two\n\n\
+                 > quoted\n\n\
+                 ```\n*raw*\n```\n";
+    assert_eq!(
+        jira(input, Md2JiraOptions::new()),
+        "h2. Changes in _1.2_\n\n\
+         Fixed {{\\{x\\}}} in [PROJ-1|https://j/PROJ-1] \
+         and -old- _\\-x_ \\[y\\] 2 * 3\\!\n\n\
+         # one\n#* ☑ two\n\n\
+         {quote}\nquoted\n{quote}\n\n\
+         {noformat}\n*raw*\n{noformat}\n"
+    );
+}
+
+#[test]
+fn renderer_jira_table_and_code() {
+    let input = "| a | b |\n|---|---|\n| 1 | x\\|y |\n\n\
+                 ```\nfn\n```\n";
+    assert_eq!(
+        jira(input, Md2JiraOptions::new().default_lang("rust")),
+        "||a||b||\n|1|x\\|y|\n\n{code:rust}\nfn\n{code}\n"
+    );
+}
+
+#[test]
+fn renderer_jira_escaping() {
+    let input = "{b} [c] |d| -e- +f+ ^g^ _i_\n";
+    assert_eq!(
+        jira(input, Md2JiraOptions::new()),
+        "\\{b\\} \\[c\\] \\|d\\| \\-e\\- \\+f\\+ \\^g\\^ _i_\n"
+    );
+}
+
+#[test]
+fn renderer_jira_nested_lists_and_images() {
+    let input =
+        "- a\n  1. b\n     - c\n- [x] d\n\n![i](x.png \"t\")\n";
+    assert_eq!(
+        jira(input, Md2JiraOptions::new()),
+        "* a\n*# b\n*#* c\n* ☑ d\n\n!x.png!\n"
+    );
+}
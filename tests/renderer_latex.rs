@@ -0,0 +1,78 @@
+use md4rust::renderer::prelude::*;
+
+fn latex(input: &str, options: Md2LatexOptions) -> String {
+    let mut renderer = MdRenderer::latex(String::new(), options);
+    let flags = MdParserFlags::github().permissive_latexmath_spans();
+    renderer.render(input, &flags).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_latex_blocks_and_spans() {
+    let input = "## 50% *off* & $x^2$\n\n\
+                 3. a_b\n4. [link](https://a.b/#c)\n\n\
+                 ```rust\nlet a = {};\n```\n";
+    assert_eq!(
+        latex(input, Md2LatexOptions::new().listings()),
+        "\\subsection{50\\% \\emph{off} \\& $x^2$}\n\n\
+         \\begin{enumerate}\n\\setcounter{enumi}{2}\n\
+         \\item a\\_b\n\
+         \\item \\href{https://a.b/\\#c}{link}\n\
+         \\end{enumerate}\n\n\
+         \\begin{lstlisting}[language=rust]\nlet a = {};\n\
+         \\end{lstlisting}\n\n"
+    );
+}
+
+#[test]
+fn renderer_latex_escaping() {
+    assert_eq!(
+        latex("% $ # ^ ~ \\\\ { } _ &\n", Md2LatexOptions::new()),
+        "\\% \\$ \\# \\textasciicircum{} \\textasciitilde{} \
+         \\textbackslash{} \\{ \\} \\_ \\&\n\n"
+    );
+}
+
+#[test]
+fn renderer_latex_nested_lists() {
+    let input = "- a\n  - b\n    1. c\n- [ ] e\n";
+    assert_eq!(
+        latex(input, Md2LatexOptions::new()),
+        "\\begin{itemize}\n\\item a\n\
+         \\begin{itemize}\n\\item b\n\
+         \\begin{enumerate}\n\\item c\n\\end{enumerate}\n\
+         \\end{itemize}\n\
+         \\item[$\\square$] e\n\
+         \\end{itemize}\n\n"
+    );
+}
+
+#[test]
+fn renderer_latex_table() {
+    let input =
+        "| a | b | c |\n|:--|:-:|--:|\n| 1 | ~ | ![x](y.png) |\n";
+    assert_eq!(
+        latex(input, Md2LatexOptions::new()),
+        "\\begin{tabular}{lcr}\n\
+         \\textbf{a} & \\textbf{b} & \\textbf{c} \\\\\n\\hline\n\
+         1 & \\textasciitilde{} & \\includegraphics{y.png} \\\\\n\
+         \\end{tabular}\n\n"
+    );
+    assert_eq!(
+        latex("| [a](/?b_c&d) |\n|---|\n", Md2LatexOptions::new()),
+        "\\begin{tabular}{l}\n\
+         \\textbf{\\href{/?b\\_c\\&d}{a}} \\\\\n\\hline\n\
+         \\end{tabular}\n\n"
+    );
+}
+
+#[test]
+fn renderer_latex_images() {
+    assert_eq!(
+        latex(
+            "![alt *x*](img.png \"T\")\n",
+            Md2LatexOptions::new()
+        ),
+        "\\includegraphics{img.png}\n\n"
+    );
+}
@@ -0,0 +1,136 @@
+use md4rust::renderer::prelude::*;
+use serde::Deserialize;
+use std::fs::File;
+
+#[derive(Deserialize)]
+struct Test {
+    markdown: String,
+    example: u32,
+}
+
+fn html(input: &str, parser_flags: &MdParserFlags) -> String {
+    let mut renderer =
+        MdRenderer::html_rs(String::new(), Md2HtmlFlags::default());
+    renderer.render(input, parser_flags).unwrap();
+    renderer.unwrap()
+}
+
+fn markdown(
+    input: &str,
+    parser_flags: &MdParserFlags,
+    options: Md2MarkdownOptions,
+) -> String {
+    let mut renderer = MdRenderer::markdown(String::new(), options);
+    renderer.render(input, parser_flags).unwrap();
+    renderer.unwrap()
+}
+
+fn format(input: &str, options: Md2MarkdownOptions) -> String {
+    markdown(input, &MdParserFlags::github(), options)
+}
+
+fn squeeze(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[test]
+fn renderer_markdown_roundtrip() {
+    // Line breaks and tabs inside setext headings do not survive the
+    // conversion to ATX headings.
+    let known = [10, 81, 82, 95];
+
+    let test_file = File::open("./commonmark0.30.json").unwrap();
+    let tests: Vec<Test> =
+        serde_json::from_reader(test_file).unwrap();
+    let mut fails = Vec::<u32>::new();
+    for parser_flags in
+        [MdParserFlags::commonmark(), MdParserFlags::github()]
+    {
+        for test in tests.iter() {
+            if known.contains(&test.example) {
+                continue;
+            }
+            for width in [0, 20] {
+                let options = Md2MarkdownOptions::new().width(width);
+                let formatted =
+                    markdown(&test.markdown, &parser_flags, options);
+                let mut expected =
+                    html(&test.markdown, &parser_flags);
+                let mut got = html(&formatted, &parser_flags);
+                if width > 0 {
+                    // Re-wrapping moves the soft line breaks.
+                    expected = squeeze(&expected);
+                    got = squeeze(&got);
+                }
+                if got != expected {
+                    fails.push(test.example);
+                }
+            }
+        }
+    }
+    assert_eq!(fails, Vec::<u32>::new());
+}
+
+#[test]
+fn renderer_markdown_normalize() {
+    let input = "Title\n=====\n\n\
+                 * one\n* two\n\n\
+                 + three\n\n\
+                 ~~~ rust\nlet a = 1;\n~~~\n\n\
+                 |a|b|\n|-|:-:|\n|1|22|\n";
+    assert_eq!(
+        format(input, Md2MarkdownOptions::new()),
+        "# Title\n\n\
+         - one\n- two\n\n\
+         * three\n\n\
+         ```rust\nlet a = 1;\n```\n\n\
+         | a   |  b  |\n| --- | :-: |\n| 1   | 22  |\n"
+    );
+}
+
+#[test]
+fn renderer_markdown_escape_and_wrap() {
+    let input = "A *b* \\* c `d  e` [f](</g h> \"i\")\\\n\
+                 wordy texts - x, yyyyy 10) z <https://j.k>\n";
+    assert_eq!(
+        format(input, Md2MarkdownOptions::new().width(12)),
+        "A *b* \\* c\n\
+         `d  e`\n\
+         [f](</g h> \"i\")\\\n\
+         wordy texts\n\
+         \\- x, yyyyy\n\
+         10\\) z\n\
+         <https://j.k>\n"
+    );
+}
+
+#[test]
+fn renderer_markdown_nested_lists() {
+    let input = "- a\n  - b\n    1. c\n\n       d\n- [ ] e\n\n\
+                 > quote\n> - in\n";
+    assert_eq!(
+        format(input, Md2MarkdownOptions::new()),
+        "- a\n  - b\n    1. c\n\n       d\n- [ ] e\n\n\
+         > quote\n>\n> - in\n"
+    );
+    // Only the direct children of a tight item are packed.
+    let input = "- > a\n  >\n  > b\n- c\n";
+    assert_eq!(format(input, Md2MarkdownOptions::new()), input);
+}
+
+#[test]
+fn renderer_markdown_tables_and_images() {
+    let input = "![alt *x*](img.png \"T\") ![b](<a b.png>)\n\n\
+                 | a | ![i](x.png) |\n|---|---|\n| \\| | `c\\|d` |\n";
+    assert_eq!(
+        format(input, Md2MarkdownOptions::new()),
+        "![alt *x*](img.png \"T\") ![b](<a b.png>)\n\n\
+         | a   | ![i](x.png) |\n\
+         | --- | ----------- |\n\
+         | \\|  | `c\\|d`      |\n"
+    ); // A header without body rows keeps its delimiter row.
+    assert_eq!(
+        format("| a |\n|---|\n", Md2MarkdownOptions::new()),
+        "| a   |\n| --- |\n"
+    );
+}
@@ -0,0 +1,149 @@
+use md4rust::renderer::prelude::*;
+
+fn mdast(input: &str, options: Md2MdastOptions) -> String {
+    mdast_with(input, options, &MdParserFlags::github())
+}
+
+fn mdast_with(
+    input: &str,
+    options: Md2MdastOptions,
+    flags: &MdParserFlags,
+) -> String {
+    let mut renderer = MdRenderer::mdast(String::new(), options);
+    renderer.render(input, flags).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_mdast_tree() {
+    let input = "# Hi *you*\n\n\
+                 1. [x] a&amp;b\n\n\
+                 ```rust ignore\nfn f() {}\n```\n\n\
+                 | a | b |\n|:-|-|\n| ![i](x.png) | |\n";
+    assert_eq!(
+        mdast(input, Md2MdastOptions::new()),
+        concat!(
+            r#"{"type":"root","children":["#,
+            r#"{"type":"heading","depth":1,"children":["#,
+            r#"{"type":"text","value":"Hi "},"#,
+            r#"{"type":"emphasis","children":["#,
+            r#"{"type":"text","value":"you"}]}]},"#,
+            r#"{"type":"list","ordered":true,"start":1,"#,
+            r#""spread":false,"children":["#,
+            r#"{"type":"listItem","spread":false,"checked":true,"#,
+            r#""children":[{"type":"paragraph","children":["#,
+            r#"{"type":"text","value":"a&b"}]}]}]},"#,
+            r#"{"type":"code","lang":"rust","meta":"ignore","#,
+            r#""value":"fn f() {}"},"#,
+            r#"{"type":"table","align":["left",null],"children":["#,
+            r#"{"type":"tableRow","children":["#,
+            r#"{"type":"tableCell","children":["#,
+            r#"{"type":"text","value":"a"}]},"#,
+            r#"{"type":"tableCell","children":["#,
+            r#"{"type":"text","value":"b"}]}]},"#,
+            r#"{"type":"tableRow","children":["#,
+            r#"{"type":"tableCell","children":["#,
+            r#"{"type":"image","url":"x.png","title":null,"#,
+            r#""alt":"i"}]},"#,
+            r#"{"type":"tableCell","children":[]}]}]}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn renderer_mdast_position() {
+    let input = "Some *text*\n";
+    let out = mdast(input, Md2MdastOptions::new().position());
+    assert!(out.starts_with(
+        r#"{"type":"root","children":[{"type":"paragraph","children":[{"type":"text","value":"Some ","position":{"start":{"line":1,"column":1,"offset":0},"end":{"line":1,"column":6,"offset":5}}}"#
+    ));
+    assert!(out.contains(
+        r#"{"type":"emphasis","children":[{"type":"text","value":"text","position":{"start":{"line":1,"column":7,"offset":6},"end":{"line":1,"column":11,"offset":10}}}],"position":{"start":{"line":1,"column":7,"offset":6},"end":{"line":1,"column":11,"offset":10}}}"#
+    ));
+}
+
+#[test]
+fn renderer_mdast_position_columns_count_characters() {
+    let input = "é *x*\n";
+    let out = mdast(input, Md2MdastOptions::new().position());
+    assert!(out.contains(
+        r#"{"type":"emphasis","children":[{"type":"text","value":"x","position":{"start":{"line":1,"column":4,"offset":4},"end":{"line":1,"column":5,"offset":5}}}]"#
+    ));
+}
+
+#[test]
+fn renderer_mdast_display_math() {
+    let flags = MdParserFlags::github().permissive_latexmath_spans();
+    let out =
+        mdast_with("$a$ $$b$$\n", Md2MdastOptions::new(), &flags);
+    assert!(out.contains(r#"{"type":"inlineMath","value":"a"}"#));
+    assert!(out.contains(r#"{"type":"math","value":"b"}"#));
+}
+
+#[test]
+fn renderer_mdast_void_nodes() {
+    assert_eq!(
+        mdast("a\\\nb\n\n---\n", Md2MdastOptions::new()),
+        concat!(
+            r#"{"type":"root","children":[{"type":"paragraph","#,
+            r#""children":[{"type":"text","value":"a"},"#,
+            r#"{"type":"break"},{"type":"text","value":"b"}]},"#,
+            r#"{"type":"thematicBreak"}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn renderer_mdast_escaping() {
+    assert_eq!(
+        mdast("a \"q\" \\\\ \u{1} &lt;\n", Md2MdastOptions::new()),
+        concat!(
+            r#"{"type":"root","children":[{"type":"paragraph","#,
+            r#""children":[{"type":"text","value":"a \"q\" \\ "#,
+            r#"\u0001 <"}]}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn renderer_mdast_nested_lists() {
+    assert_eq!(
+        mdast("- a\n  - b\n", Md2MdastOptions::new()),
+        concat!(
+            r#"{"type":"root","children":[{"type":"list","#,
+            r#""ordered":false,"start":null,"spread":false,"#,
+            r#""children":[{"type":"listItem","spread":false,"#,
+            r#""checked":null,"children":[{"type":"paragraph","#,
+            r#""children":[{"type":"text","value":"a"}]},"#,
+            r#"{"type":"list","ordered":false,"start":null,"#,
+            r#""spread":false,"children":[{"type":"listItem","#,
+            r#""spread":false,"checked":null,"children":["#,
+            r#"{"type":"paragraph","children":["#,
+            r#"{"type":"text","value":"b"}]}]}]}]}]}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn renderer_mdast_html_and_images() {
+    assert_eq!(
+        mdast(
+            "a <b>c</b> ![i](x.png \"t\")\n",
+            Md2MdastOptions::new()
+        ),
+        concat!(
+            r#"{"type":"root","children":[{"type":"paragraph","#,
+            r#""children":[{"type":"text","value":"a "},"#,
+            r#"{"type":"html","value":"<b>"},"#,
+            r#"{"type":"text","value":"c"},"#,
+            r#"{"type":"html","value":"</b>"},"#,
+            r#"{"type":"text","value":" "},"#,
+            r#"{"type":"image","url":"x.png","title":"t","alt":"i"}]}]}"#,
+            "\n"
+        )
+    );
+}
@@ -0,0 +1,66 @@
+use md4rust::renderer::prelude::*;
+
+fn mediawiki(input: &str, options: Md2MediawikiOptions) -> String {
+    let mut renderer = MdRenderer::mediawiki(String::new(), options);
+    let flags = MdParserFlags::github().permissive_wikilinks();
+    renderer.render(input, &flags).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_mediawiki_blocks_and_spans() {
+    let input = "## Setup *now*\n\n\
+                 See [[Install|the guide]], [docs](https://d/x) \
+                 and `a[0]` for <b>x</b> & **y**.\n\n\
+                 1. one\n\n   more\n   - [x] two\n\n\
+                 > \\* not a list\n\n\
+                 ```sh\nmake\n```\n";
+    assert_eq!(
+        mediawiki(input, Md2MediawikiOptions::new()),
+        "== Setup ''now'' ==\n\n\
+         See [[Install|the guide]], [https://d/x docs] \
+         and <code><nowiki>a[0]</nowiki></code> for <b>x</b> \
+         &amp; '''y'''.\n\n\
+         # one\n#: more\n#* ☑ two\n\n\
+         <blockquote>\n<nowiki>*</nowiki> not a list\n\
+         </blockquote>\n\n\
+         <syntaxhighlight lang=\"sh\">\nmake\n</syntaxhighlight>\n"
+    );
+}
+
+#[test]
+fn renderer_mediawiki_table() {
+    let input = "| a | b |\n|:-:|---|\n| 1 | x\\|y |\n";
+    assert_eq!(
+        mediawiki(input, Md2MediawikiOptions::new().plain_tables()),
+        "{|\n|-\n! style=\"text-align:center;\" | a\n! b\n\
+         |-\n| style=\"text-align:center;\" | 1\n\
+         | x<nowiki>|</nowiki>y\n|}\n"
+    );
+}
+
+#[test]
+fn renderer_mediawiki_escaping() {
+    let input = "\\[[a]] {{b}} & ''d'' ~~~~\n";
+    assert_eq!(
+        mediawiki(input, Md2MediawikiOptions::new()),
+        "<nowiki>[[a]] {{b}} &amp; ''d'' ~~~~</nowiki>\n"
+    );
+    // The escaped link text does not join the text before the link.
+    assert_eq!(
+        mediawiki(
+            "a {{b}} [c {{d}}](e)\n",
+            Md2MediawikiOptions::new()
+        ),
+        "<nowiki>a {{b}} </nowiki>[[e|<nowiki>c {{d}}</nowiki>]]\n"
+    );
+}
+
+#[test]
+fn renderer_mediawiki_nested_lists_and_images() {
+    let input = "- a\n  1. b\n     - c\n- [x] d\n\n![i *j*](x.png)\n";
+    assert_eq!(
+        mediawiki(input, Md2MediawikiOptions::new()),
+        "* a\n*# b\n*#* c\n* ☑ d\n\n[[File:x.png|i ''j'']]\n"
+    );
+}
@@ -0,0 +1,57 @@
+use md4rust::renderer::prelude::*;
+
+fn org(input: &str, options: Md2OrgOptions) -> String {
+    let mut renderer = MdRenderer::org(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_org_blocks_and_spans() {
+    let input = "## Notes on *org*\n\n\
+                 Read [the manual](https://orgmode.org) or \
+                 `a~b`, ~~not~~ \\*this\\*.\n\n\
+                 3. three\n4. [x] four\n   - nested\n\n\
+                 > quoted\n\n\
+                 ```elisp\n* not a heading\n```\n";
+    assert_eq!(
+        org(input, Md2OrgOptions::new()),
+        "** Notes on /org/\n\n\
+         Read [[https://orgmode.org][the manual]] or \
+         =a~b=, +not+ \u{200b}*this*.\n\n\
+         3. [@3] three\n4. [X] four\n   - nested\n\n\
+         #+BEGIN_QUOTE\nquoted\n#+END_QUOTE\n\n\
+         #+BEGIN_SRC elisp\n,* not a heading\n#+END_SRC\n"
+    );
+}
+
+#[test]
+fn renderer_org_table_and_image() {
+    let input = "| a | b |\n|---|--:|\n| 1 | x\\|y |\n\n\
+                 ![map](map.png)\n\n```\nx\n```\n";
+    assert_eq!(
+        org(input, Md2OrgOptions::new().lowercase_keywords()),
+        "| a |         b |\n|---+-----------|\n\
+         | 1 | x\\vert{}y |\n\n[[file:map.png]]\n\n\
+         #+begin_example\nx\n#+end_example\n"
+    );
+}
+
+#[test]
+fn renderer_org_escaping() {
+    let input = "/c/ =d= +e+ [[g]]\n\n```\n* x\n#+END_SRC\n```\n";
+    assert_eq!(
+        org(input, Md2OrgOptions::new()),
+        "\u{200b}/c/ \u{200b}=d= \u{200b}+e+ [\u{200b}[g]]\n\n\
+         #+BEGIN_EXAMPLE\n,* x\n,#+END_SRC\n#+END_EXAMPLE\n"
+    );
+}
+
+#[test]
+fn renderer_org_nested_lists() {
+    let input = "- a\n  1. b\n     - c\n- [x] d\n";
+    assert_eq!(
+        org(input, Md2OrgOptions::new()),
+        "- a\n  1. b\n     - c\n- [X] d\n"
+    );
+}
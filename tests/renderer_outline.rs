@@ -0,0 +1,117 @@
+use md4rust::renderer::prelude::*;
+
+fn outline(input: &str, options: Md2OutlineOptions) -> String {
+    let mut renderer = MdRenderer::outline(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+const DOC: &str = "## Intro\n\nWhy *we* do it,\nand how.\n\n\
+                   More.\n\n# Design\n\n- list\n\n#### Data & \"I/O\"\n\n\
+                   > # Quoted\n\nSee [this](x).\n\n### Risks\n";
+
+#[test]
+fn renderer_outline_opml() {
+    let options =
+        Md2OutlineOptions::new(Md2OutlineFormat::Opml).notes();
+    let expected = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<opml version=\"2.0\">
+  <head>
+  </head>
+  <body>
+    <outline text=\"Intro\" _note=\"Why we do it, and how.\"/>
+    <outline text=\"Design\">
+      <outline text=\"Data &amp; &quot;I/O&quot;\" _note=\"See this.\"/>
+      <outline text=\"Risks\"/>
+    </outline>
+  </body>
+</opml>
+";
+    assert_eq!(outline(DOC, options), expected);
+
+    let options = Md2OutlineOptions::new(Md2OutlineFormat::Opml);
+    let out = outline("# Plan\n\n## Step\n", options);
+    assert!(out.contains("<head>\n    <title>Plan</title>\n"));
+}
+
+#[test]
+fn renderer_outline_freemind() {
+    let options = Md2OutlineOptions::new(Md2OutlineFormat::Freemind);
+    let expected = "<map version=\"1.0.1\">
+<node TEXT=\"\">
+<node TEXT=\"Intro\"/>
+<node TEXT=\"Design\">
+<node TEXT=\"Data &amp; &quot;I/O&quot;\"/>
+<node TEXT=\"Risks\"/>
+</node>
+</node>
+</map>
+";
+    assert_eq!(outline(DOC, options), expected);
+
+    let options =
+        Md2OutlineOptions::new(Md2OutlineFormat::Freemind).notes();
+    let expected = "<map version=\"1.0.1\">
+<node TEXT=\"Plan\">
+<richcontent TYPE=\"NOTE\"><html><head/><body>\
+<p>a<br/>b</p></body></html></richcontent>
+<node TEXT=\"Step\"/>
+</node>
+</map>
+";
+    let doc = "# Plan\n\na\\\nb\n\n### Step\n";
+    assert_eq!(outline(doc, options), expected);
+}
+
+#[test]
+fn renderer_outline_skipped_levels() {
+    // A heading nests under the closest shallower one, however deep.
+    let input = "# A\n\n### B\n\n## C\n\n##### D\n\n# E\n";
+    let expected = "<map version=\"1.0.1\">
+<node TEXT=\"\">
+<node TEXT=\"A\">
+<node TEXT=\"B\"/>
+<node TEXT=\"C\">
+<node TEXT=\"D\"/>
+</node>
+</node>
+<node TEXT=\"E\"/>
+</node>
+</map>
+";
+    let options = Md2OutlineOptions::new(Md2OutlineFormat::Freemind);
+    assert_eq!(outline(input, options), expected);
+}
+
+#[test]
+fn renderer_outline_escaping() {
+    let input = "# A <b> & 'c'\n\n## `x<y>` \"z\"\n";
+    let options = Md2OutlineOptions::new(Md2OutlineFormat::Opml);
+    let out = outline(input, options);
+    assert!(out.contains("<outline text=\"A  &amp; 'c'\">"));
+    assert!(
+        out.contains("<outline text=\"x&lt;y&gt; &quot;z&quot;\"/>")
+    );
+
+    let options = Md2OutlineOptions::new(Md2OutlineFormat::Freemind);
+    let out = outline(input, options);
+    assert!(out.contains("<node TEXT=\"A  &amp; 'c'\">"));
+    assert!(out.contains(
+        "<node TEXT=\"x&lt;y&gt; \
+         &quot;z&quot;\"/>"
+    ));
+}
+
+#[test]
+fn renderer_outline_nested_blocks() {
+    // Headings inside lists, quotes and tables are not outline nodes.
+    let input = "# Top ![img](i.png)\n\n- a\n  - b\n\n  # Not\n\n\
+                 > ## Quoted\n\n| h |\n|---|\n| c |\n";
+    let options = Md2OutlineOptions::new(Md2OutlineFormat::Opml);
+    let out = outline(input, options);
+    assert!(
+        out.contains("    <outline text=\"Top img\"/>\n  </body>")
+    );
+    assert!(!out.contains("Not"));
+    assert!(!out.contains("Quoted"));
+}
@@ -0,0 +1,81 @@
+use md4rust::renderer::prelude::*;
+
+fn pandoc(input: &str) -> String {
+    let mut renderer =
+        MdRenderer::pandoc(String::new(), Md2PandocOptions::new());
+    let flags = MdParserFlags::github().permissive_latexmath_spans();
+    renderer.render(input, &flags).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_pandoc_blocks() {
+    let input = "## A  b\n\n\
+                 2) [x] one\n3) `t`\n\n\
+                 ```rust\nx\n```\n";
+    assert_eq!(
+        pandoc(input),
+        concat!(
+            r#"{"pandoc-api-version":[1,23,1],"meta":{},"blocks":["#,
+            r#"{"t":"Header","c":[2,["",[],[]],["#,
+            r#"{"t":"Str","c":"A"},{"t":"Space"},{"t":"Str","c":"b"}]]},"#,
+            r#"{"t":"OrderedList","c":[[2,{"t":"Decimal"},{"t":"OneParen"}],["#,
+            r#"[{"t":"Plain","c":[{"t":"Str","c":"☒"},{"t":"Space"},"#,
+            r#"{"t":"Str","c":"one"}]}],"#,
+            r#"[{"t":"Plain","c":[{"t":"Code","c":[["",[],[]],"t"]}]}]]]},"#,
+            r#"{"t":"CodeBlock","c":[["",["rust"],[]],"x"]}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn renderer_pandoc_table_and_math() {
+    let input = "| $x$ |\n|:-:|\n| [a](u \"t\") |\n";
+    assert_eq!(
+        pandoc(input),
+        concat!(
+            r#"{"pandoc-api-version":[1,23,1],"meta":{},"blocks":["#,
+            r#"{"t":"Table","c":[["",[],[]],[null,[]],"#,
+            r#"[[{"t":"AlignCenter"},{"t":"ColWidthDefault"}]],"#,
+            r#"[["",[],[]],[[["",[],[]],[[["",[],[]],{"t":"AlignCenter"},1,1,"#,
+            r#"[{"t":"Plain","c":[{"t":"Math","c":[{"t":"InlineMath"},"x"]}]}]]]]]],"#,
+            r#"[[["",[],[]],0,[],[[["",[],[]],[[["",[],[]],{"t":"AlignCenter"},1,1,"#,
+            r#"[{"t":"Plain","c":[{"t":"Link","c":[["",[],[]],"#,
+            r#"[{"t":"Str","c":"a"}],["u","t"]]}]}]]]]]]],"#,
+            r#"[["",[],[]],[]]]}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn renderer_pandoc_nested_lists() {
+    assert_eq!(
+        pandoc("- a\n  1. b\n"),
+        concat!(
+            r#"{"pandoc-api-version":[1,23,1],"meta":{},"blocks":["#,
+            r#"{"t":"BulletList","c":[[{"t":"Plain","c":[{"t":"Str","c":"a"}]},"#,
+            r#"{"t":"OrderedList","c":[[1,{"t":"Decimal"},{"t":"Period"}],"#,
+            r#"[[{"t":"Plain","c":[{"t":"Str","c":"b"}]}]]]}]]}]}"#,
+            "\n"
+        )
+    );
+}
+
+#[test]
+fn renderer_pandoc_escaping_and_images() {
+    assert_eq!(
+        pandoc("a \"q\" \\\\ &lt;\n\n![i *j*](x.png \"t\")\n"),
+        concat!(
+            r#"{"pandoc-api-version":[1,23,1],"meta":{},"blocks":["#,
+            r#"{"t":"Para","c":[{"t":"Str","c":"a"},{"t":"Space"},"#,
+            r#"{"t":"Str","c":"\"q\""},{"t":"Space"},"#,
+            r#"{"t":"Str","c":"\\"},{"t":"Space"},{"t":"Str","c":"<"}]},"#,
+            r#"{"t":"Para","c":[{"t":"Image","c":[["",[],[]],"#,
+            r#"[{"t":"Str","c":"i"},{"t":"Space"},"#,
+            r#"{"t":"Emph","c":[{"t":"Str","c":"j"}]}],["x.png","t"]]}]}]}"#,
+            "\n"
+        )
+    );
+}
@@ -0,0 +1,67 @@
+use md4rust::renderer::prelude::*;
+
+fn plain(input: &str, options: Md2PlainOptions) -> String {
+    let mut renderer = MdRenderer::plain(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_plain_strip_markup() {
+    let input = "# Caf&eacute; *menu*\n\n\
+                 - [x] **tea**\n- [ ] coffee\n\n\
+                 3. one\n4. two\n\n\
+                 > quoted <b>html</b>\n";
+    assert_eq!(
+        plain(input, Md2PlainOptions::new()),
+        "Café menu\n\n\
+         - [x] tea\n- [ ] coffee\n\n\
+         3. one\n4. two\n\n  \
+         quoted html\n"
+    );
+}
+
+#[test]
+fn renderer_plain_tables_links_and_wrapping() {
+    let input = "| a | bbb |\n|--:|:-:|\n| 10 | x |\n\n\
+                 See [the docs](https://example.com/docs) and \
+                 <https://example.com> for all the details.\n";
+    assert_eq!(
+        plain(
+            input,
+            Md2PlainOptions::new().width(30).link_footnotes()
+        ),
+        " a  bbb\n\
+         --  ---\n\
+         10   x\n\n\
+         See the docs[1] and\n\
+         https://example.com for all\n\
+         the details.\n\n\
+         [1] https://example.com/docs\n"
+    );
+}
+
+#[test]
+fn renderer_plain_escapes_and_entities() {
+    let input = "a &amp; \\*b\\* <i>c</i> `d`\n";
+    assert_eq!(plain(input, Md2PlainOptions::new()), "a & *b* c d\n");
+}
+
+#[test]
+fn renderer_plain_nested_lists() {
+    let input = "- a\n  - b\n    1. c\n- [ ] e\n";
+    assert_eq!(
+        plain(input, Md2PlainOptions::new()),
+        "- a\n  - b\n    1. c\n- [ ] e\n"
+    );
+}
+
+#[test]
+fn renderer_plain_images_and_header_only_tables() {
+    let input =
+        "![alt *x*](img.png) and ![](y.png)\n\n| h |\n|---|\n";
+    assert_eq!(
+        plain(input, Md2PlainOptions::new()),
+        "alt x and\n\nh\n-\n"
+    );
+}
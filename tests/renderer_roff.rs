@@ -0,0 +1,71 @@
+use md4rust::renderer::prelude::*;
+
+fn roff(input: &str, options: Md2RoffOptions) -> String {
+    let mut renderer = MdRenderer::roff(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_roff_man_page() {
+    let input = "# Options\n\n\
+                 - `-v`: *be **loud***\n\
+                 - see [docs](https://a.b)\n\n\
+                 ```\n.dot \\ line\n```\n";
+    assert_eq!(
+        roff(input, Md2RoffOptions::new().title("tool", "1")),
+        ".TH \"tool\" \"1\"\n\
+         .SH\nOptions\n\
+         .IP \"\\(bu\" 4\n\
+         \\f(CW\\-v\\fR: \\fIbe \\f(BIloud\\fI\\fR\n\
+         .IP \"\\(bu\" 4\n\
+         see docs <https://a.b>\n\
+         .PP\n.nf\n\\f(CW\n\\&.dot \\e line\n\\fR\n.fi\n"
+    );
+}
+
+#[test]
+fn renderer_roff_title() {
+    assert_eq!(
+        roff("", Md2RoffOptions::new().title(".my \"tool\"", "1-x")),
+        ".TH \"\\&.my \\(dqtool\\(dq\" \"1\\-x\"\n"
+    );
+}
+
+#[test]
+fn renderer_roff_escaping() {
+    assert_eq!(
+        roff("a-b \\\\ c\n.d\n'e\n", Md2RoffOptions::new()),
+        ".PP\na\\-b \\e c\n\\&.d\n\\&'e\n"
+    );
+}
+
+#[test]
+fn renderer_roff_nested_lists() {
+    let input = "- a\n  - b\n    1. c\n- [ ] e\n";
+    assert_eq!(
+        roff(input, Md2RoffOptions::new()),
+        ".IP \"\\(bu\" 4\na\n\
+         .RS\n.IP \"\\(bu\" 4\nb\n\
+         .RS\n.IP \"1.\" 4\nc\n.RE\n.RE\n\
+         .IP \"\\(bu [ ]\" 8\ne\n"
+    );
+}
+
+#[test]
+fn renderer_roff_table() {
+    let input = "| a | b |\n|--:|:-:|\n| 1 | .x |\n| 'y | z |\n";
+    assert_eq!(
+        roff(input, Md2RoffOptions::new()),
+        ".PP\n.TS\nallbox tab(\t);\nr c.\n\
+         a\tb\n_\n1\t.x\n\\&'y\tz\n.TE\n"
+    );
+}
+
+#[test]
+fn renderer_roff_images() {
+    assert_eq!(
+        roff("![alt *x*](img.png \"T\")\n", Md2RoffOptions::new()),
+        ".PP\nalt \\fIx\\fR <img.png>\n"
+    );
+}
@@ -1,4 +1,5 @@
 use md4rust::renderer::prelude::*;
+use std::num::NonZeroI32;
 
 #[derive(Default)]
 struct MyData(String);
@@ -23,3 +24,264 @@ fn renderer_rs() {
     // println!("{}", renderer.userdata().0);
     assert_eq!(renderer.userdata().0, "<h2>Hello World</h2>\n");
 }
+
+#[test]
+fn renderer_rs_hooks() {
+    let mut renderer = MdRenderer::html_rs(
+        MyData::default(),
+        Md2HtmlFlags::default(),
+    );
+
+    renderer
+        .on_enter_span(MdSpantype::Img, |ctx, detail| {
+            if let MdSpanDetail::Img { src, .. } = detail {
+                if src.clone().as_str() == Some("skip.png") {
+                    return ctx.enter_span_default(detail);
+                }
+            }
+            ctx.render_append("<figure>");
+            ctx.enter_span_default(detail)
+        })
+        .on_leave_span(MdSpantype::Img, |ctx, detail| {
+            ctx.leave_span_default(detail)?;
+            if let MdSpanDetail::Img { src, .. } = detail {
+                if src.clone().as_str() != Some("skip.png") {
+                    ctx.render_append("</figure>");
+                }
+            }
+            Ok(())
+        })
+        .on_enter_block(MdBlocktype::H, |ctx, detail| {
+            if let MdBlockDetail::H { level } = detail {
+                ctx.render_append(&format!(
+                    "<h{} class=\"t\">",
+                    level
+                ));
+            }
+            Ok(())
+        });
+
+    let input = "# Title\n\n![a *b*](a.png) ![c](skip.png)\n";
+
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+
+    assert_eq!(
+        renderer.userdata().0,
+        "<h1 class=\"t\">Title</h1>\n\
+         <p><figure><img src=\"a.png\" alt=\"a b\"></figure> \
+         <img src=\"skip.png\" alt=\"c\"></p>\n"
+    );
+}
+
+#[derive(Default)]
+struct Figures {
+    html: String,
+    closed: Vec<String>,
+}
+
+impl Md2HtmlRs for Figures {
+    fn render_append(&mut self, text: &str) {
+        self.html += text;
+    }
+}
+
+#[test]
+fn renderer_rs_nested_image_hooks() {
+    let mut renderer = MdRenderer::html_rs(
+        Figures::default(),
+        Md2HtmlFlags::default(),
+    );
+
+    renderer
+        .on_enter_span(MdSpantype::Img, |ctx, detail| {
+            ctx.render_append("<figure>");
+            ctx.enter_span_default(detail)
+        })
+        .on_leave_span(MdSpantype::Img, |ctx, detail| {
+            ctx.leave_span_default(detail)?;
+            if let MdSpanDetail::Img { src, .. } = detail {
+                let src =
+                    src.clone().as_str().unwrap_or("").to_owned();
+                ctx.userdata.closed.push(src);
+            }
+            ctx.render_append("</figure>");
+            Ok(())
+        });
+
+    let input = "![a ![b](x) c](y) *d*\n";
+
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+
+    let figures = renderer.userdata();
+    assert_eq!(
+        figures.html,
+        "<p><figure><img src=\"y\" alt=\"a b c\"></figure> \
+         <em>d</em></p>\n"
+    );
+    assert_eq!(figures.closed, ["y"]);
+}
+
+#[derive(Default)]
+struct Failing {
+    html: String,
+    fail: bool,
+}
+
+impl Md2HtmlRs for Failing {
+    fn render_append(&mut self, text: &str) {
+        self.html += text;
+    }
+}
+
+#[test]
+fn renderer_rs_render_after_hook_error() {
+    let mut renderer = MdRenderer::html_rs(
+        Failing::default(),
+        Md2HtmlFlags::default(),
+    );
+
+    renderer
+        .on_enter_span(MdSpantype::Em, |ctx, detail| {
+            if ctx.userdata.fail {
+                return Err(NonZeroI32::new(-1).unwrap());
+            }
+            ctx.enter_span_default(detail)
+        })
+        .on_text(|ctx, _, text| {
+            ctx.render_append(&text.to_uppercase());
+            Ok(())
+        });
+
+    let input = "a *b*\n";
+
+    renderer.userdata().fail = true;
+    let res = renderer.render(input, &MdParserFlags::github());
+    assert_eq!(res, Err(NonZeroI32::new(-1).unwrap()));
+
+    let failing = renderer.userdata();
+    failing.fail = false;
+    failing.html.clear();
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    assert_eq!(renderer.userdata().html, "<p>a <em>B</em></p>\n");
+}
+
+#[test]
+fn renderer_rs_sourcepos() {
+    let mut renderer = MdRenderer::html_rs(
+        MyData::default(),
+        Md2HtmlFlags::new().sourcepos().source_map(),
+    );
+
+    let input = "# Title #\n\n> - one\n>   two\n\n---\n\n\
+                 ```rust\nfn x() {}\n```\n";
+
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+
+    let output = renderer.userdata().0.clone();
+    assert_eq!(
+        output,
+        "<h1 data-sourcepos=\"1:1-1:9\">Title</h1>\n\
+         <blockquote data-sourcepos=\"3:1-4:7\">\n\
+         <ul data-sourcepos=\"3:3-4:7\">\n\
+         <li data-sourcepos=\"3:3-4:7\">one\ntwo</li>\n\
+         </ul>\n\
+         </blockquote>\n\
+         <hr data-sourcepos=\"6:1-6:3\">\n\
+         <pre data-sourcepos=\"8:1-10:3\"><code class=\"language-rust\">\
+         fn x() {}\n</code></pre>\n"
+    );
+
+    let map = renderer.source_map().unwrap();
+    let click = output.find("two").unwrap();
+    let entry = map.find(click).unwrap();
+    assert_eq!(entry.blocktype, None);
+    assert_eq!(&input[entry.input.clone()], "two");
+    assert_eq!(map.line_col(entry.input.start), (4, 5));
+
+    let click = output.find("<hr").unwrap();
+    let entry = map.find(click).unwrap();
+    assert_eq!(entry.blocktype, Some(MdBlocktype::Hr));
+    assert_eq!(&input[entry.input.clone()], "---");
+}
+
+#[test]
+fn renderer_rs_sourcepos_inline_ends() {
+    // Blocks end past the closing inline markup of their last line,
+    // and list items start at their marker, as with cmark.
+    let sourcepos = |input: &str| {
+        let mut renderer = MdRenderer::html_rs(
+            MyData::default(),
+            Md2HtmlFlags::new().sourcepos(),
+        );
+        renderer.render(input, &MdParserFlags::github()).unwrap();
+        renderer.unwrap().0
+    };
+    assert_eq!(
+        sourcepos("a *x*\n"),
+        "<p data-sourcepos=\"1:1-1:5\">a <em>x</em></p>\n"
+    );
+    assert_eq!(
+        sourcepos("see [a](http://b.c)\n"),
+        "<p data-sourcepos=\"1:1-1:19\">see \
+         <a href=\"http://b.c\">a</a></p>\n"
+    );
+    assert_eq!(
+        sourcepos("- [x](y)\n"),
+        "<ul data-sourcepos=\"1:1-1:8\">\n\
+         <li data-sourcepos=\"1:1-1:8\"><a href=\"y\">x</a></li>\n\
+         </ul>\n"
+    );
+    assert_eq!(
+        sourcepos("- \n  x\n"),
+        "<ul data-sourcepos=\"1:1-2:3\">\n\
+         <li data-sourcepos=\"1:1-2:3\">x</li>\n\
+         </ul>\n"
+    );
+    assert_eq!(
+        sourcepos("- - a\n> 1. *b*\n"),
+        "<ul data-sourcepos=\"1:1-1:5\">\n\
+         <li data-sourcepos=\"1:1-1:5\">\
+         <ul data-sourcepos=\"1:3-1:5\">\n\
+         <li data-sourcepos=\"1:3-1:5\">a</li>\n\
+         </ul>\n\
+         </li>\n\
+         </ul>\n\
+         <blockquote data-sourcepos=\"2:1-2:8\">\n\
+         <ol data-sourcepos=\"2:3-2:8\">\n\
+         <li data-sourcepos=\"2:3-2:8\"><em>b</em></li>\n\
+         </ol>\n\
+         </blockquote>\n"
+    );
+}
+
+#[test]
+fn renderer_rs_interactive_tasks() {
+    use md4rust::ootb::toggle_task;
+
+    let mut renderer = MdRenderer::html_rs(
+        MyData::default(),
+        Md2HtmlFlags::new().interactive_tasks().skip_utf8_bom(),
+    );
+
+    let input = "\u{feff}- [ ] one\n- [x] two\n";
+
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+
+    assert_eq!(
+        renderer.userdata().0,
+        "<ul>\n\
+         <li class=\"task-list-item\"><input type=\"checkbox\" \
+         class=\"task-list-item-checkbox\" data-task-offset=\"6\">\
+         one</li>\n\
+         <li class=\"task-list-item\"><input type=\"checkbox\" \
+         class=\"task-list-item-checkbox\" data-task-offset=\"16\" \
+         checked>two</li>\n\
+         </ul>\n"
+    );
+
+    let input = toggle_task(input, 6);
+    assert_eq!(input, "\u{feff}- [x] one\n- [x] two\n");
+    let input = toggle_task(&input, 16);
+    assert_eq!(input, "\u{feff}- [x] one\n- [ ] two\n");
+    assert_eq!(toggle_task(&input, 8), input);
+}
@@ -0,0 +1,76 @@
+use md4rust::renderer::prelude::*;
+
+/// The document after its header line.
+fn rtf(input: &str, options: Md2RtfOptions) -> String {
+    let mut renderer = MdRenderer::rtf(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    let document = renderer.unwrap();
+    let (header, body) = document.split_once('\n').unwrap();
+    assert!(header.starts_with("{\\rtf1\\ansi"));
+    body.to_owned()
+}
+
+#[test]
+fn renderer_rtf_blocks_and_spans() {
+    let input = "## Café\n\n\
+                 **Bold** {x} at [home](https://h.io) 😀\n\n\
+                 1. one\n   - [x] two\n\n\
+                 ```\nlet a;\nlet b;\n```\n";
+    assert_eq!(
+        rtf(input, Md2RtfOptions::new()),
+        "{\\pard\\sa180\\s2\\sb240\\keepn\\b\\fs32 Caf\\u233?\\par}\n\
+         {\\pard\\sa180 {\\b Bold} \\{x\\} at \
+         {\\field{\\*\\fldinst{HYPERLINK \"https://h.io\"}}\
+         {\\fldrslt{\\ul\\cf1 home}}} \\u-10179?\\u-8704?\\par}\n\
+         {\\pard\\sa180\\li360\\fi-360\\tx360 1.\\tab one\\par}\n\
+         {\\pard\\sa180\\li720\\fi-360\\tx720 \\u9745?\\tab two\
+         \\par}\n\
+         {\\pard\\sa180\\f1\\fs20 let a;\\line let b;\\par}\n}\n"
+    );
+}
+
+#[test]
+fn renderer_rtf_table_and_fonts() {
+    let input = "| a | b |\n|---|--:|\n| 1 | 2 |\n";
+    let options = Md2RtfOptions::new().font("Arial");
+    let mut renderer = MdRenderer::rtf(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    let document = renderer.unwrap();
+    assert!(document.contains("{\\f0\\fswiss\\fcharset0 Arial;}"));
+    let row = "\\trowd\\trgaph108\
+               \\clbrdrt\\brdrs\\clbrdrl\\brdrs\
+               \\clbrdrb\\brdrs\\clbrdrr\\brdrs\\cellx4680\
+               \\clbrdrt\\brdrs\\clbrdrl\\brdrs\
+               \\clbrdrb\\brdrs\\clbrdrr\\brdrs\\cellx9360\n";
+    assert_eq!(
+        document.split_once('\n').unwrap().1,
+        format!(
+            "{}\\pard\\intbl\\ql{{\\b a}}\\cell\n\
+             \\pard\\intbl\\qr{{\\b b}}\\cell\n\\row\n\
+             {}\\pard\\intbl\\ql{{1}}\\cell\n\
+             \\pard\\intbl\\qr{{2}}\\cell\n\\row\n\\pard\n}}\n",
+            row, row
+        )
+    );
+}
+
+#[test]
+fn renderer_rtf_escaping() {
+    assert_eq!(
+        rtf("{a} \\\\ é 😀\n", Md2RtfOptions::new()),
+        "{\\pard\\sa180 \\{a\\} \\\\ \\u233? \\u-10179?\\u-8704?\\par}\n}\n"
+    );
+}
+
+#[test]
+fn renderer_rtf_nested_lists_and_images() {
+    let input = "- a\n  1. b\n- [x] d\n\n![i](x.png)\n";
+    assert_eq!(
+        rtf(input, Md2RtfOptions::new()),
+        "{\\pard\\sa180\\li360\\fi-360\\tx360 \\bullet\\tab a\\par}\n\
+         {\\pard\\sa180\\li720\\fi-360\\tx720 1.\\tab b\\par}\n\
+         {\\pard\\sa180\\li360\\fi-360\\tx360 \\u9745?\\tab d\\par}\n\
+         {\\pard\\sa180 {\\field{\\*\\fldinst{HYPERLINK \"x.png\"}}\
+         {\\fldrslt{\\ul\\cf1 i}}}\\par}\n}\n"
+    );
+}
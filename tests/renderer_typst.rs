@@ -0,0 +1,62 @@
+use md4rust::renderer::prelude::*;
+
+fn typst(input: &str, options: Md2TypstOptions) -> String {
+    let mut renderer = MdRenderer::typst(String::new(), options);
+    let flags = MdParserFlags::github().permissive_latexmath_spans();
+    renderer.render(input, &flags).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_typst_blocks_and_spans() {
+    let input = "# Spec *v2*\n\n\
+                 Use **bold**ly `a` and $\\frac{x^{2}}{ab}$ \
+                 at [home](https://h.io) for #1 @x.\n\n\
+                 ![Map](map.png \"The map\")\n\n\
+                 3. three\n   - [x] done\n\n\
+                 > quoted\n\n\
+                 ```rust\nfn main() {}\n```\n";
+    assert_eq!(
+        typst(input, Md2TypstOptions::new().numbered_headings()),
+        "#set heading(numbering: \"1.\")\n\
+         \n= Spec _v2_\n\n\
+         Use *bold*ly `a` and $frac(x^(2), a b)$ \
+         at #link(\"https://h.io\")[home] for \\#1 \\@x.\n\n\
+         #figure(image(\"map.png\", alt: \"Map\"), \
+         caption: [The map])\n\n\
+         3. three\n   - ☑ done\n\n\
+         #quote(block: true)[\nquoted\n]\n\n\
+         ```rust\nfn main() {}\n```\n"
+    );
+}
+
+#[test]
+fn renderer_typst_table() {
+    let input = "| a | b |\n|---|--:|\n| 1 | *x* |\n";
+    assert_eq!(
+        typst(input, Md2TypstOptions::new()),
+        "#table(\n  columns: 2,\n  align: (auto, right,),\n  \
+         table.header([a], [b]),\n  [1], [_x_],\n)\n"
+    );
+}
+
+#[test]
+fn renderer_typst_escaping() {
+    let input = "a // b /* c */ ~ x\n\n\\= d\n\n\\- e\n\n1\\. f\n\n\
+                 $ * _ @ < \\\\ ` [x]\n";
+    assert_eq!(
+        typst(input, Md2TypstOptions::new()),
+        "a \\// b \\/\\* c \\*/ \\~ x\n\n\\= d\n\n\\- e\n\n1\\. f\n\n\
+         \\$ \\* \\_ \\@ \\< \\\\ \\` \\[x\\]\n"
+    );
+}
+
+#[test]
+fn renderer_typst_nested_lists_and_images() {
+    let input = "- a\n  1. b\n     - c\n- [x] d\n\n![i *j*](x.png)\n";
+    assert_eq!(
+        typst(input, Md2TypstOptions::new()),
+        "- a\n  1. b\n     - c\n- ☑ d\n\n\
+         #image(\"x.png\", alt: \"i j\")\n"
+    );
+}
@@ -0,0 +1,133 @@
+use md4rust::renderer::prelude::*;
+
+fn xml(input: &str, options: Md2XmlOptions) -> String {
+    let mut renderer = MdRenderer::xml(String::new(), options);
+    renderer.render(input, &MdParserFlags::github()).unwrap();
+    renderer.unwrap()
+}
+
+#[test]
+fn renderer_xml_document() {
+    let input = "# A &amp; *b*\n\n\
+                 3) x  \n   y\n4) [ ] `<z>`\n\n\
+                 ~~~ rust\nfn\n~~~\n";
+    assert_eq!(
+        xml(input, Md2XmlOptions::new()),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE document SYSTEM "CommonMark.dtd">
+<document xmlns="http://commonmark.org/xml/1.0">
+  <heading level="1">
+    <text xml:space="preserve">A &amp; </text>
+    <emph>
+      <text xml:space="preserve">b</text>
+    </emph>
+  </heading>
+  <list type="ordered" start="3" delim="paren" tight="true">
+    <item>
+      <paragraph>
+        <text xml:space="preserve">x</text>
+        <linebreak />
+        <text xml:space="preserve">y</text>
+      </paragraph>
+    </item>
+    <tasklist completed="false">
+      <paragraph>
+        <code xml:space="preserve">&lt;z&gt;</code>
+      </paragraph>
+    </tasklist>
+  </list>
+  <code_block info="rust" xml:space="preserve">fn
+</code_block>
+</document>
+"#
+    );
+}
+
+#[test]
+fn renderer_xml_sourcepos() {
+    let input = "> a\n> b\n\n---\n";
+    assert_eq!(
+        xml(input, Md2XmlOptions::new().sourcepos()),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE document SYSTEM "CommonMark.dtd">
+<document sourcepos="1:1-4:3" xmlns="http://commonmark.org/xml/1.0">
+  <block_quote sourcepos="1:1-2:3">
+    <paragraph sourcepos="1:3-2:3">
+      <text xml:space="preserve">a</text>
+      <softbreak />
+      <text xml:space="preserve">b</text>
+    </paragraph>
+  </block_quote>
+  <thematic_break sourcepos="4:1-4:3" />
+</document>
+"#
+    );
+}
+
+#[test]
+fn renderer_xml_nested_lists_images_and_escaping() {
+    let input = "- a\n  1. b\n\n![i *j*](x.png \"t&\")\n\na < & \"\n";
+    assert_eq!(
+        xml(input, Md2XmlOptions::new()),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE document SYSTEM "CommonMark.dtd">
+<document xmlns="http://commonmark.org/xml/1.0">
+  <list type="bullet" tight="true">
+    <item>
+      <paragraph>
+        <text xml:space="preserve">a</text>
+      </paragraph>
+      <list type="ordered" start="1" delim="period" tight="true">
+        <item>
+          <paragraph>
+            <text xml:space="preserve">b</text>
+          </paragraph>
+        </item>
+      </list>
+    </item>
+  </list>
+  <paragraph>
+    <image destination="x.png" title="t&amp;">
+      <text xml:space="preserve">i </text>
+      <emph>
+        <text xml:space="preserve">j</text>
+      </emph>
+    </image>
+  </paragraph>
+  <paragraph>
+    <text xml:space="preserve">a &lt; &amp; &quot;</text>
+  </paragraph>
+</document>
+"#
+    );
+}
+
+#[test]
+fn renderer_xml_table() {
+    let input = "| a | b |\n|:-|-:|\n| 1 | |\n";
+    assert_eq!(
+        xml(input, Md2XmlOptions::new()),
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE document SYSTEM "CommonMark.dtd">
+<document xmlns="http://commonmark.org/xml/1.0">
+  <table>
+    <table_header>
+      <table_cell align="left">
+        <text xml:space="preserve">a</text>
+      </table_cell>
+      <table_cell align="right">
+        <text xml:space="preserve">b</text>
+      </table_cell>
+    </table_header>
+    <table_row>
+      <table_cell align="left">
+        <text xml:space="preserve">1</text>
+      </table_cell>
+      <table_cell align="right">
+      </table_cell>
+    </table_row>
+  </table>
+</document>
+"#
+    );
+}